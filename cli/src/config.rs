@@ -0,0 +1,322 @@
+use compiler_core::{
+  CompileOptions, ExpressionStrategy, HtmlOptions, JsxRuntime, JsxStrategy, OutputFormat,
+  SourceMapKind, TargetKind,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "mdxor.toml";
+
+// keys given without a value on the command line, `--math` rather than `--math true`
+const SWITCHES: &[&str] = &[
+  "toc-export",
+  "math",
+  "directives",
+  "alerts",
+  "task-lists",
+  "footnotes",
+  "autolink-literals",
+  "frontmatter",
+  "development",
+  "jsx",
+  "html",
+  "watch",
+  "deny-warnings",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+  Str(String),
+  Bool(bool),
+  Int(usize),
+}
+
+impl Value {
+  fn string(self, key: &str) -> Result<String, String> {
+    match self {
+      Value::Str(value) => Ok(value),
+      _ => Err(format!("`{}` takes a string", key)),
+    }
+  }
+
+  fn bool(self, key: &str) -> Result<bool, String> {
+    match self {
+      Value::Bool(value) => Ok(value),
+      _ => Err(format!("`{}` takes `true` or `false`", key)),
+    }
+  }
+
+  // flags are strings, `--toc-max-depth 3`
+  fn int(self, key: &str) -> Result<usize, String> {
+    let int = match self {
+      Value::Int(value) => Some(value),
+      Value::Str(value) => value.parse().ok(),
+      Value::Bool(_) => None,
+    };
+    int.ok_or_else(|| format!("`{}` takes a number", key))
+  }
+}
+
+// one of the names `new` knows, e.g. `commonjs` for an output format
+fn named<T>(key: &str, value: Value, new: fn(&str) -> Option<T>) -> Result<T, String> {
+  let name = value.string(key)?;
+  new(&name).ok_or_else(|| format!("unknown {} `{}`", key, name))
+}
+
+// what to compile and how, from `mdxor.toml` and the flags, which win over it
+#[derive(Default)]
+pub struct Config {
+  // a file or a directory, stdin when there is none
+  pub input: Option<PathBuf>,
+  // the output file, or the directory mirroring an input directory; stdout otherwise
+  pub out: Option<PathBuf>,
+  pub options: CompileOptions,
+  // static HTML instead of a module
+  pub html: bool,
+  pub html_options: HtmlOptions,
+  pub watch: bool,
+  // warnings fail the run like errors
+  pub deny_warnings: bool,
+}
+
+impl Config {
+  pub fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+    let options = &mut self.options;
+    match key {
+      "input" => self.input = Some(PathBuf::from(value.string(key)?)),
+      "out" => self.out = Some(PathBuf::from(value.string(key)?)),
+      "toc-export" => options.toc_export = value.bool(key)?,
+      "toc-min-depth" => options.toc_min_depth = value.int(key)?,
+      "toc-max-depth" => options.toc_max_depth = value.int(key)?,
+      "math" => options.math = value.bool(key)?,
+      "directives" => options.directives = value.bool(key)?,
+      "alerts" => options.alerts = value.bool(key)?,
+      "alert-component" => options.alert_component = Some(value.string(key)?),
+      "task-lists" => options.task_lists = value.bool(key)?,
+      "checkbox-component" => options.checkbox_component = Some(value.string(key)?),
+      "footnotes" => options.footnotes = value.bool(key)?,
+      "footnote-id-prefix" => options.footnote_id_prefix = value.string(key)?,
+      "autolink-literals" => options.autolink_literals = value.bool(key)?,
+      "frontmatter" => options.frontmatter = value.bool(key)?,
+      "development" => options.development = value.bool(key)?,
+      "source-map" => options.source_map = Some(named(key, value, SourceMapKind::new)?),
+      "format" => options.output_format = named(key, value, OutputFormat::new)?,
+      "runtime" => options.jsx_runtime = named(key, value, JsxRuntime::new)?,
+      "pragma" => options.pragma = value.string(key)?,
+      "pragma-frag" => options.pragma_frag = value.string(key)?,
+      "jsx" => options.jsx = value.bool(key)?,
+      "target" => options.target = named(key, value, TargetKind::new)?,
+      "jsx-import-source" => options.jsx_import_source = Some(value.string(key)?),
+      "provider-import-source" => options.provider_import_source = Some(value.string(key)?),
+      "html" => self.html = value.bool(key)?,
+      "html-jsx" => self.html_options.jsx = named(key, value, JsxStrategy::new)?,
+      "html-expressions" => {
+        self.html_options.expressions = named(key, value, ExpressionStrategy::new)?
+      }
+      "watch" => self.watch = value.bool(key)?,
+      "deny-warnings" => self.deny_warnings = value.bool(key)?,
+      _ => return Err(format!("unknown option `{}`", key)),
+    }
+    Ok(())
+  }
+
+  // `key = value` lines of strings, booleans and numbers; tables aren't supported
+  pub fn read_toml(&mut self, text: &str) -> Result<(), String> {
+    for (i, line) in text.lines().enumerate() {
+      let line = strip_comment(line).trim();
+      if line.is_empty() {
+        continue;
+      }
+      let result = match line.split_once('=') {
+        Some((key, value)) => {
+          let key = key.trim().trim_matches('"');
+          toml_value(value.trim()).and_then(|value| self.set(key, value))
+        }
+        None => Err(format!("expected `key = value`, found `{}`", line)),
+      };
+      result.map_err(|message| format!("line {}: {}", i + 1, message))?;
+    }
+    Ok(())
+  }
+
+  // input and output paths are relative to the directory of the file
+  pub fn load(&mut self, path: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(path)
+      .map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+    self
+      .read_toml(&text)
+      .map_err(|message| format!("{}: {}", path.display(), message))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    for path in [&mut self.input, &mut self.out].into_iter().flatten() {
+      *path = base.join(&*path);
+    }
+    Ok(())
+  }
+
+  // `mdxor [options] [input]`, reading `--config` or an `mdxor.toml` in the current
+  // directory before the flags
+  pub fn from_args(args: &[String]) -> Result<Self, String> {
+    let mut config = Config::default();
+    let mut flags = vec![];
+    let mut config_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+      let (key, value) = match arg.strip_prefix("--") {
+        Some(flag) => match flag.split_once('=') {
+          Some((key, value)) => (key, Some(value.to_string())),
+          None => (flag, None),
+        },
+        None => match arg.as_str() {
+          "-o" => ("out", None),
+          "-w" => ("watch", None),
+          "-c" => ("config", None),
+          "-" => continue,
+          _ if arg.starts_with('-') => return Err(format!("unknown flag `{}`", arg)),
+          _ if flags.iter().any(|(key, _)| *key == "input") => {
+            return Err(format!("unexpected argument `{}`", arg))
+          }
+          _ => ("input", Some(arg.to_string())),
+        },
+      };
+      let value = match value {
+        Some(value) if SWITCHES.contains(&key) => match value.as_str() {
+          "true" => Value::Bool(true),
+          "false" => Value::Bool(false),
+          _ => return Err(format!("`--{}` takes `true` or `false`", key)),
+        },
+        None if SWITCHES.contains(&key) => Value::Bool(true),
+        Some(value) => Value::Str(value),
+        None => match args.next() {
+          Some(value) => Value::Str(value.to_string()),
+          None => return Err(format!("`--{}` needs a value", key)),
+        },
+      };
+      if key == "config" {
+        config_file = Some(PathBuf::from(value.string(key)?));
+      } else {
+        flags.push((key, value));
+      }
+    }
+    match config_file {
+      Some(path) => config.load(&path)?,
+      None if Path::new(CONFIG_FILE).is_file() => config.load(Path::new(CONFIG_FILE))?,
+      None => {}
+    }
+    for (key, value) in flags {
+      config.set(key, value)?;
+    }
+    config.validate()?;
+    Ok(config)
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    let input_dir = self.input.as_ref().is_some_and(|input| input.is_dir());
+    if input_dir && self.out.is_none() {
+      return Err("a directory is compiled into `--out <dir>`".to_string());
+    }
+    if self.watch && self.input.is_none() {
+      return Err("`--watch` needs a file or directory".to_string());
+    }
+    if self.options.source_map == Some(SourceMapKind::Separate) && self.out.is_none() {
+      return Err("separate source maps are written next to `--out`".to_string());
+    }
+    Ok(())
+  }
+}
+
+fn strip_comment(line: &str) -> &str {
+  let mut quote = None;
+  for (i, ch) in line.char_indices() {
+    match (ch, quote) {
+      ('"' | '\'', None) => quote = Some(ch),
+      (ch, Some(open)) if ch == open => quote = None,
+      ('#', None) => return &line[..i],
+      _ => {}
+    }
+  }
+  line
+}
+
+fn toml_value(value: &str) -> Result<Value, String> {
+  if let Some(literal) = value
+    .strip_prefix('\'')
+    .and_then(|value| value.strip_suffix('\''))
+  {
+    return Ok(Value::Str(literal.to_string()));
+  }
+  if let Some(basic) = value
+    .strip_prefix('"')
+    .and_then(|value| value.strip_suffix('"'))
+  {
+    let mut string = String::new();
+    let mut chars = basic.chars();
+    while let Some(ch) = chars.next() {
+      if ch != '\\' {
+        string.push(ch);
+        continue;
+      }
+      match chars.next() {
+        Some('n') => string.push('\n'),
+        Some('t') => string.push('\t'),
+        Some(ch @ ('"' | '\\')) => string.push(ch),
+        _ => return Err(format!("unsupported escape in `{}`", value)),
+      }
+    }
+    return Ok(Value::Str(string));
+  }
+  match value {
+    "true" => Ok(Value::Bool(true)),
+    "false" => Ok(Value::Bool(false)),
+    _ => value
+      .parse()
+      .map(Value::Int)
+      .map_err(|_| format!("expected a string, boolean or number, found `{}`", value)),
+  }
+}
+
+#[test]
+fn test_read_toml() {
+  let mut config = Config::default();
+  config
+    .read_toml(
+      "# docs site\ninput = \"docs\"\nout = 'dist' # mirrored\nformat = \"commonjs\"\nmath = true\ntoc-max-depth = 3\njsx-import-source = \"pre#act\"\n",
+    )
+    .unwrap();
+  assert_eq!(config.input, Some(PathBuf::from("docs")));
+  assert_eq!(config.out, Some(PathBuf::from("dist")));
+  assert_eq!(config.options.output_format, OutputFormat::CommonJs);
+  assert!(config.options.math);
+  assert_eq!(config.options.toc_max_depth, 3);
+  assert_eq!(config.options.jsx_import_source.as_deref(), Some("pre#act"));
+
+  let error = |text: &str| Config::default().read_toml(text).unwrap_err();
+  assert_eq!(error("math = 1"), "line 1: `math` takes `true` or `false`");
+  assert_eq!(
+    error("\ntarget = \"svelte\""),
+    "line 2: unknown target `svelte`"
+  );
+  assert_eq!(
+    error("[compile]"),
+    "line 1: expected `key = value`, found `[compile]`"
+  );
+}
+
+#[test]
+fn test_from_args() {
+  let args = |args: &str| -> Vec<String> { args.split(' ').map(str::to_string).collect() };
+  let config = Config::from_args(&args(
+    "page.mdx --format=function-body --target vue --jsx --toc-min-depth 2",
+  ))
+  .unwrap();
+  assert_eq!(config.input, Some(PathBuf::from("page.mdx")));
+  assert_eq!(config.options.output_format, OutputFormat::FunctionBody);
+  assert_eq!(config.options.target, TargetKind::Vue);
+  assert!(config.options.jsx);
+  assert_eq!(config.options.toc_min_depth, 2);
+
+  let error = |line: &str| Config::from_args(&args(line)).err().unwrap();
+  assert_eq!(error("a.mdx b.mdx"), "unexpected argument `b.mdx`");
+  assert_eq!(error("--runtime"), "`--runtime` needs a value");
+  assert_eq!(error("--math=yes"), "`--math` takes `true` or `false`");
+  assert_eq!(error("--watch"), "`--watch` needs a file or directory");
+}
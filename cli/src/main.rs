@@ -0,0 +1,289 @@
+mod config;
+mod report;
+mod watch;
+
+use crate::config::Config;
+use crate::report::{Level, Report};
+use compiler_core::{compile, render_html_with_diagnostics, Diagnostic, Span};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "\
+Compile MDX to JavaScript
+
+Usage: mdxor [options] [input]
+
+  input                         a file, or a directory of .md and .mdx files; stdin when
+                                left out or `-`
+
+Options:
+  -o, --out <path>              output file, or the directory mirroring an input directory;
+                                stdout otherwise
+  -c, --config <path>           settings file, `mdxor.toml` in the current directory by default
+  -w, --watch                   compile again when the input changes
+      --deny-warnings           exit with an error on warnings
+      --format <format>         program, commonjs or function-body
+      --runtime <runtime>       automatic or classic
+      --target <target>         react, preact, vue, solid or hyperscript
+      --jsx                     JSX source instead of calls
+      --jsx-import-source <pkg> package of the JSX runtime, e.g. `react`
+      --provider-import-source <pkg>
+                                package exporting `useMDXComponents`
+      --pragma <name>           element function of the classic runtime
+      --pragma-frag <name>      fragment of the classic runtime
+      --development             development calls with source locations
+      --source-map <kind>       inline, or separate for a `.map` file next to the output
+      --html                    static HTML instead of a module
+      --html-jsx <strategy>     drop or render JSX in HTML
+      --html-expressions <strategy>
+                                drop expressions in HTML, or error on them
+      --math, --directives, --alerts, --task-lists, --footnotes, --autolink-literals,
+      --frontmatter, --toc-export
+                                syntax extensions and the toc export
+      --toc-min-depth <n>, --toc-max-depth <n>, --footnote-id-prefix <prefix>,
+      --alert-component <name>, --checkbox-component <name>
+  -h, --help                    print this help
+  -V, --version                 print the version
+
+Every option can be set in `mdxor.toml` as `key = value`, e.g. `format = \"commonjs\"`,
+`math = true`, and `input`/`out` relative to it. Flags override it.
+";
+
+fn main() {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+    print!("{}", USAGE);
+    return;
+  }
+  if args.iter().any(|arg| arg == "-V" || arg == "--version") {
+    println!("mdxor {}", env!("CARGO_PKG_VERSION"));
+    return;
+  }
+  let config = match Config::from_args(&args) {
+    Ok(config) => config,
+    Err(message) => {
+      eprintln!("error: {}\n\nSee `mdxor --help`", message);
+      process::exit(2);
+    }
+  };
+  if config.watch {
+    watch::watch(&config);
+  }
+  let report = run(&config);
+  if report.failed(config.deny_warnings) {
+    process::exit(1);
+  }
+}
+
+fn run(config: &Config) -> Report {
+  let mut report = Report::default();
+  let input = match &config.input {
+    Some(input) => input,
+    None => {
+      let mut source = String::new();
+      match io::stdin().read_to_string(&mut source) {
+        Ok(_) => compile_source(config, &source, None, &mut report),
+        Err(error) => report.error(&format!("could not read stdin: {}", error)),
+      }
+      return report;
+    }
+  };
+  match sources(input) {
+    Ok(paths) => {
+      for path in paths {
+        compile_path(config, &path, &mut report);
+      }
+    }
+    Err(error) => report.error(&format!("could not read {}: {}", input.display(), error)),
+  }
+  report
+}
+
+// the input file, or the markdown files under the input directory in path order
+pub fn sources(input: &Path) -> io::Result<Vec<PathBuf>> {
+  if !input.is_dir() {
+    return Ok(vec![input.to_path_buf()]);
+  }
+  let mut paths = vec![];
+  let mut dirs = vec![input.to_path_buf()];
+  while let Some(dir) = dirs.pop() {
+    for entry in fs::read_dir(dir)? {
+      let entry = entry?;
+      let path = entry.path();
+      // symlinked directories are skipped, they could lead back to a parent
+      if entry.file_type()?.is_dir() {
+        dirs.push(path);
+      } else if !path.is_dir()
+        && matches!(
+          path.extension().and_then(|ext| ext.to_str()),
+          Some("md" | "mdx")
+        )
+      {
+        paths.push(path);
+      }
+    }
+  }
+  paths.sort();
+  Ok(paths)
+}
+
+// `--out` for stdin or a file, the same path under `--out` for a file of an input directory
+fn output_path(config: &Config, path: Option<&Path>) -> Option<PathBuf> {
+  let out = config.out.as_ref()?;
+  let (Some(input), Some(path)) = (&config.input, path) else {
+    return Some(out.clone());
+  };
+  if !input.is_dir() {
+    return Some(out.clone());
+  }
+  let extension = match (config.html, config.options.jsx) {
+    (true, _) => "html",
+    (false, true) => "jsx",
+    (false, false) => "js",
+  };
+  let relative = path.strip_prefix(input).unwrap_or(path);
+  Some(out.join(relative).with_extension(extension))
+}
+
+pub fn compile_path(config: &Config, path: &Path, report: &mut Report) {
+  match fs::read_to_string(path) {
+    Ok(source) => compile_source(config, &source, Some(path), report),
+    Err(error) => report.error(&format!("could not read {}: {}", path.display(), error)),
+  }
+}
+
+fn compile_source(config: &Config, source: &str, path: Option<&Path>, report: &mut Report) {
+  let name = match path {
+    Some(path) => path.display().to_string(),
+    None => "<stdin>".to_string(),
+  };
+  let mut map = None;
+  let code = if config.html {
+    let (html, diagnostics) =
+      render_html_with_diagnostics(source, &config.options, &config.html_options);
+    emit_diagnostics(report, &diagnostics, &name, source);
+    match html {
+      Ok(html) => html,
+      Err(error) => {
+        report.emit(
+          Level::Error,
+          &error.message,
+          &name,
+          source,
+          Some(&error.span),
+        );
+        return;
+      }
+    }
+  } else {
+    let mut options = config.options.clone();
+    if path.is_some() {
+      options.filepath = Some(name.clone());
+    }
+    let result = compile(source, &options);
+    emit_diagnostics(report, &result.diagnostics, &name, source);
+    map = result.source_map;
+    result.code
+  };
+  let written = match output_path(config, path) {
+    Some(out) => write_output(&out, code, map),
+    None => writeln!(io::stdout(), "{}", code.trim_end_matches('\n')),
+  };
+  if let Err(error) = written {
+    report.error(&format!(
+      "could not write the output of {}: {}",
+      name, error
+    ));
+  }
+}
+
+fn emit_diagnostics(report: &mut Report, diagnostics: &[Diagnostic], name: &str, source: &str) {
+  for diagnostic in diagnostics {
+    let span = diagnostic.position.as_ref().map(|position| Span {
+      start: position.start.offset,
+      end: position.end.offset,
+    });
+    report.emit(
+      Level::Warning,
+      &diagnostic.message,
+      name,
+      source,
+      span.as_ref(),
+    );
+  }
+}
+
+// the code, and the source map as `<file>.map` next to it with `SourceMapKind::Separate`
+fn write_output(out: &Path, mut code: String, map: Option<String>) -> io::Result<()> {
+  if let Some(dir) = out.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  if let Some(map) = map {
+    let mut map_path = out.as_os_str().to_owned();
+    map_path.push(".map");
+    let map_path = PathBuf::from(map_path);
+    fs::write(&map_path, map)?;
+    let name = map_path.file_name().unwrap_or_default().to_string_lossy();
+    code.push_str(&format!("\n//# sourceMappingURL={}", name));
+  }
+  fs::write(out, code)
+}
+
+#[test]
+fn test_compile_directory() {
+  let dir = tempfile::tempdir().unwrap();
+  let input = dir.path().join("docs");
+  fs::create_dir_all(input.join("guide")).unwrap();
+  fs::write(input.join("index.mdx"), "# Home\n").unwrap();
+  fs::write(input.join("guide/intro.md"), "some <Foo bar={>\n").unwrap();
+  fs::write(input.join("guide/notes.txt"), "not markdown").unwrap();
+  let mut config = Config {
+    input: Some(input),
+    out: Some(dir.path().join("dist")),
+    ..Default::default()
+  };
+  config.options.source_map = Some(compiler_core::SourceMapKind::Separate);
+
+  let report = run(&config);
+  assert_eq!((report.errors, report.warnings), (0, 1));
+  assert!(!report.failed(false));
+  assert!(report.failed(true));
+  let dist = dir.path().join("dist");
+  let code = fs::read_to_string(dist.join("index.js")).unwrap();
+  assert!(code.contains("_jsxRuntime.jsx(\"h1\""));
+  assert!(code.ends_with("\n//# sourceMappingURL=index.js.map"));
+  assert!(dist.join("index.js.map").is_file());
+  assert!(dist.join("guide/intro.js").is_file());
+  assert!(!dist.join("guide/notes.js").exists());
+}
+
+#[test]
+fn test_compile_html_diagnostics() {
+  let dir = tempfile::tempdir().unwrap();
+  let input = dir.path().join("a.md");
+  fs::write(&input, "some <Foo bar={>\n").unwrap();
+  let config = Config {
+    input: Some(input),
+    out: Some(dir.path().join("a.html")),
+    html: true,
+    ..Default::default()
+  };
+  let report = run(&config);
+  assert_eq!((report.errors, report.warnings), (0, 1));
+  assert!(report.failed(true));
+  assert!(dir.path().join("a.html").is_file());
+}
+
+// a link back to a parent directory doesn't loop
+#[cfg(unix)]
+#[test]
+fn test_sources_symlink_cycle() {
+  let dir = tempfile::tempdir().unwrap();
+  let input = dir.path().join("docs");
+  fs::create_dir_all(input.join("guide")).unwrap();
+  fs::write(input.join("guide/intro.md"), "# Intro\n").unwrap();
+  std::os::unix::fs::symlink(&input, input.join("guide/up")).unwrap();
+  assert_eq!(sources(&input).unwrap(), vec![input.join("guide/intro.md")]);
+}
@@ -0,0 +1,111 @@
+use compiler_core::Span;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+  Error,
+  Warning,
+}
+
+// counts what was printed to stderr, the exit code follows from it
+#[derive(Debug, Default)]
+pub struct Report {
+  pub errors: usize,
+  pub warnings: usize,
+}
+
+impl Report {
+  pub fn error(&mut self, message: &str) {
+    self.emit(Level::Error, message, "", "", None);
+  }
+
+  pub fn emit(
+    &mut self,
+    level: Level,
+    message: &str,
+    file: &str,
+    source: &str,
+    span: Option<&Span>,
+  ) {
+    match level {
+      Level::Error => self.errors += 1,
+      Level::Warning => self.warnings += 1,
+    }
+    eprint!("{}", render(level, message, file, source, span));
+  }
+
+  pub fn failed(&self, deny_warnings: bool) -> bool {
+    self.errors > 0 || deny_warnings && self.warnings > 0
+  }
+}
+
+// like rustc, the message, where it is and the line it points into
+//
+// warning: JSX couldn't be parsed here
+//  --> docs/intro.mdx:7:6
+//   |
+// 7 | some <Foo bar={>
+//   |      ^
+pub fn render(
+  level: Level,
+  message: &str,
+  file: &str,
+  source: &str,
+  span: Option<&Span>,
+) -> String {
+  let mut out = String::new();
+  let level = match level {
+    Level::Error => "error",
+    Level::Warning => "warning",
+  };
+  let _ = writeln!(out, "{}: {}", level, message);
+  let span = match span {
+    Some(span) if span.start <= source.len() => span,
+    _ => {
+      if !file.is_empty() {
+        let _ = writeln!(out, " --> {}", file);
+      }
+      return out;
+    }
+  };
+  let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+  let line_end = source[span.start..]
+    .find('\n')
+    .map_or(source.len(), |i| span.start + i);
+  let line = source[line_start..line_end].trim_end_matches('\r');
+  let number = source[..span.start].matches('\n').count() + 1;
+  let column = source[line_start..span.start].chars().count() + 1;
+  let gutter = " ".repeat(number.to_string().len());
+  // tabs are kept so the carets line up with the source line
+  let indent: String = source[line_start..span.start]
+    .chars()
+    .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+    .collect();
+  let width = source[span.start..span.end.min(line_end).max(span.start)]
+    .chars()
+    .count()
+    .max(1);
+  let _ = writeln!(out, "{}--> {}:{}:{}", gutter, file, number, column);
+  let _ = writeln!(out, "{} |", gutter);
+  let _ = writeln!(out, "{} | {}", number, line);
+  let _ = writeln!(out, "{} | {}{}", gutter, indent, "^".repeat(width));
+  out
+}
+
+#[test]
+fn test_render() {
+  let source = "# Title\n\n\tsome <Foo bar={>\n";
+  let span = Span { start: 15, end: 16 };
+  assert_eq!(
+    render(Level::Warning, "JSX couldn't be parsed here", "docs/a.mdx", source, Some(&span)),
+    "warning: JSX couldn't be parsed here\n --> docs/a.mdx:3:7\n  |\n3 | \tsome <Foo bar={>\n  | \t     ^\n"
+  );
+  // the column counts characters, not bytes
+  let source = "# Title\n\nsome é <Foo\n";
+  let span = Span { start: 17, end: 18 };
+  assert!(render(Level::Warning, "", "a.mdx", source, Some(&span)).contains(" --> a.mdx:3:8\n"));
+  assert_eq!(
+    render(Level::Error, "could not read a.mdx", "", "", None),
+    "error: could not read a.mdx\n"
+  );
+}
@@ -0,0 +1,63 @@
+use crate::config::Config;
+use crate::report::Report;
+use crate::{compile_path, sources};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// how often the input is checked, events from the OS aren't used so it works the same
+// on network drives and in containers
+const INTERVAL: Duration = Duration::from_millis(300);
+
+fn modified_times(config: &Config) -> HashMap<PathBuf, SystemTime> {
+  let input = config.input.as_ref().expect("`--watch` needs an input");
+  sources(input)
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|path| {
+      let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+      Some((path, modified))
+    })
+    .collect()
+}
+
+// compiles everything, then the files that were added or changed since, until killed
+pub fn watch(config: &Config) -> ! {
+  let mut seen = HashMap::new();
+  loop {
+    let current = modified_times(config);
+    let mut changed: Vec<_> = current
+      .iter()
+      .filter(|(path, modified)| seen.get(*path) != Some(*modified))
+      .map(|(path, _)| path)
+      .collect();
+    if !changed.is_empty() {
+      changed.sort();
+      let mut report = Report::default();
+      for path in &changed {
+        compile_path(config, path, &mut report);
+      }
+      eprintln!(
+        "compiled {} file{}, {} error{}, {} warning{}; watching for changes",
+        changed.len(),
+        plural(changed.len()),
+        report.errors,
+        plural(report.errors),
+        report.warnings,
+        plural(report.warnings),
+      );
+    }
+    seen = current;
+    thread::sleep(INTERVAL);
+  }
+}
+
+fn plural(count: usize) -> &'static str {
+  if count == 1 {
+    ""
+  } else {
+    "s"
+  }
+}
@@ -0,0 +1,22 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// `echo '# a' | mdxor -o out.js`
+#[test]
+fn test_stdin_to_out() {
+  let dir = tempfile::tempdir().unwrap();
+  let out = dir.path().join("out.js");
+  let mut child = Command::new(env!("CARGO_BIN_EXE_mdxor"))
+    .arg("-o")
+    .arg(&out)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child.stdin.take().unwrap().write_all(b"# a\n").unwrap();
+  let output = child.wait_with_output().unwrap();
+  assert!(output.status.success());
+  assert!(output.stdout.is_empty());
+  assert!(fs::read_to_string(&out).unwrap().contains("\"a\""));
+}
@@ -2,6 +2,7 @@ use crate::document::*;
 use crate::jsx_parser::*;
 use crate::lexer::*;
 use crate::md_lexer::*;
+use crate::options::*;
 use crate::token::*;
 use std::collections::VecDeque;
 use std::mem::replace;
@@ -9,15 +10,39 @@ use std::str;
 
 pub struct BlockParser<'source> {
   source: &'source str,
+  options: &'source CompileOptions,
   document: Document<'source>,
   spine: Vec<ContainerBlock>,
   last_leaf_end: usize,
   tmp_tokens: VecDeque<Token<BlockToken>>,
 }
+// the YAML between a `---` first line and the next `---` line, and the end of that line
+pub fn frontmatter(source: &str) -> Option<(Span, usize)> {
+  let rest = source
+    .strip_prefix("---\n")
+    .or_else(|| source.strip_prefix("---\r\n"))?;
+  let start = source.len() - rest.len();
+  let mut line_start = start;
+  for line in rest.split_inclusive('\n') {
+    let line_end = line_start + line.len();
+    if line.trim_end() == "---" {
+      let value = source[start..line_start].trim_end_matches(['\r', '\n']);
+      let value = Span {
+        start,
+        end: start + value.len(),
+      };
+      return Some((value, line_end));
+    }
+    line_start = line_end;
+  }
+  None
+}
+
 impl<'source> BlockParser<'source> {
-  pub fn new(source: &'source str) -> Self {
+  pub fn new(source: &'source str, options: &'source CompileOptions) -> Self {
     BlockParser {
       source,
+      options,
       spine: Vec::new(),
       document: Document::new(source),
       last_leaf_end: 0,
@@ -26,12 +51,16 @@ impl<'source> BlockParser<'source> {
   }
 
   pub fn parse(&mut self) -> AST<Token<BlockToken>> {
+    let start = match self.options.frontmatter {
+      true => frontmatter(self.source).map_or(0, |(_, end)| end),
+      false => 0,
+    };
     let spans = VecDeque::from(vec![Span {
-      start: 0,
+      start,
       end: self.source.len(),
     }]);
     let mut parser = JSXParser::new(self.source, self.document.bytes, &spans);
-    let import_export_size = parser.js_import_export();
+    let import_export_size = parser.js_import_export().max(start);
     self.document.forward_to(import_export_size);
     let mut blocks = self.scan_blocks();
     AST {
@@ -148,11 +177,17 @@ impl<'source> BlockParser<'source> {
         let indent = start_indent + marker_size + end_indent;
         self.spine.push(ContainerBlock::ListItem(indent));
 
-        let item_blocks = self.scan_blocks();
+        let mut item_blocks = self.scan_blocks();
+        let checked = if self.options.task_lists {
+          self.take_task_marker(&mut item_blocks)
+        } else {
+          None
+        };
         blocks.push(Token {
           value: BlockToken::ListItem {
             blocks: item_blocks,
             indent: start_indent + marker_size + end_indent,
+            checked,
           },
           span: Span {
             start,
@@ -233,6 +268,33 @@ impl<'source> BlockParser<'source> {
     })
   }
 
+  fn scan_fenced_math(&mut self) -> Option<Token<BlockToken>> {
+    let bytes = self.document.bytes();
+    let start = self.document.start();
+    let (size, repeat) = open_fenced_math(bytes)?;
+    let mut math_spans = vec![];
+    self.document.forward(size);
+    while let Some(size) = self.continue_container() {
+      self.document.forward(size);
+      if let Some(size) = close_fenced_code(self.document.bytes(), b'$', repeat) {
+        self.document.forward(size);
+        break;
+      }
+      let (size, _) = one_line(self.document.bytes());
+      math_spans.push(Span {
+        start: self.document.start(),
+        end: self.document.forward(size),
+      });
+    }
+    Some(Token {
+      value: BlockToken::Math(math_spans),
+      span: Span {
+        start,
+        end: self.document.start(),
+      },
+    })
+  }
+
   fn scan_indented_code(&mut self) -> Token<BlockToken> {
     let start = self.document.start();
     let mut spans = vec![];
@@ -268,16 +330,115 @@ impl<'source> BlockParser<'source> {
     }
   }
 
-  // list and block quote
+  fn scan_container_directive(&mut self) -> Option<Token<BlockToken>> {
+    let start = self.document.start();
+    let offset = start + self.document.spaces();
+    let (size, fence, directive) = open_container_directive(self.document.bytes(), offset)?;
+    self.document.forward(size);
+    let level = self.spine.len();
+    self.spine.push(ContainerBlock::Directive(fence));
+    self.finish_leaf_block();
+    let blocks = if self.spine.len() > level {
+      self.scan_blocks()
+    } else {
+      vec![]
+    };
+    let mut end = self.last_leaf_end;
+    // the closing fence belongs to this directive only if no outer container ended with it
+    if self.spine.len() == level {
+      self.document.spaces0();
+      if let Some(size) = close_container_directive(self.document.bytes(), fence) {
+        end = self.document.forward(size);
+        self.finish_leaf_block();
+      }
+    }
+    Some(Token {
+      value: BlockToken::ContainerDirective { directive, blocks },
+      span: Span { start, end },
+    })
+  }
+
+  // strip a `[ ]` / `[x]` marker from the start of the first paragraph
+  fn take_task_marker(&self, blocks: &mut [Token<BlockToken>]) -> Option<bool> {
+    if let Some(Token {
+      value: BlockToken::Paragraph { raws },
+      span,
+    }) = blocks.first_mut()
+    {
+      let lines = raws.len();
+      let raw = raws.first_mut()?;
+      let (size, checked) = task_list_marker(&self.document.bytes[raw.start..raw.end])?;
+      // an item of only the marker stays a plain item
+      let rest = &self.document.bytes[raw.start + size..raw.end];
+      if lines == 1 && rest.iter().all(u8::is_ascii_whitespace) {
+        return None;
+      }
+      raw.start += size;
+      span.start = raw.start;
+      return Some(checked);
+    }
+    None
+  }
+
+  // strip a GitHub `[!NOTE]` marker line from the first paragraph
+  fn take_alert_marker(&self, blocks: &mut Vec<Token<BlockToken>>) -> Option<Alert> {
+    if let Some(Token {
+      value: BlockToken::Paragraph { raws },
+      span,
+    }) = blocks.first_mut()
+    {
+      let raw = raws.first()?;
+      let alert = alert_marker(&self.document.bytes[raw.start..raw.end])?;
+      raws.remove(0);
+      if let Some(raw) = raws.first() {
+        span.start = raw.start;
+      } else {
+        blocks.remove(0);
+      }
+      return Some(alert);
+    }
+    None
+  }
+
+  // list, block quote, container directive and footnote definition
   fn scan_container_block(&mut self) -> Option<Token<BlockToken>> {
+    if self.options.directives {
+      if let Some(block) = self.scan_container_directive() {
+        return Some(block);
+      }
+    }
     let bytes = self.document.bytes();
     let start = self.document.start();
+    if self.options.footnotes {
+      let offset = start + self.document.spaces();
+      if let Some((size, label)) = footnote_definition_start(bytes, offset) {
+        self.document.forward(size);
+        self.spine.push(ContainerBlock::FootnoteDefinition);
+        let blocks = self.scan_blocks();
+        return Some(Token {
+          value: BlockToken::FootnoteDefinition { label, blocks },
+          span: Span {
+            start,
+            end: self.last_leaf_end,
+          },
+        });
+      }
+    }
     if let Some((size, level)) = block_quote(bytes) {
       self.document.forward(size);
       self.spine.push(ContainerBlock::BlockQuote(level));
-      let blocks = self.scan_blocks();
+      let mut blocks = self.scan_blocks();
+      let alert = if self.options.alerts {
+        self.take_alert_marker(&mut blocks)
+      } else {
+        None
+      };
       return Some(Token {
-        value: BlockToken::BlockQuote { blocks, level },
+        value: BlockToken::BlockQuote {
+          blocks,
+          level,
+          alert,
+        },
         span: Span {
           start,
           end: self.last_leaf_end,
@@ -319,15 +480,29 @@ impl<'source> BlockParser<'source> {
         },
       };
     }
+    if self.options.directives {
+      if let Some((size, directive)) = leaf_directive(bytes, start + self.document.spaces()) {
+        return Token {
+          value: BlockToken::LeafDirective(directive),
+          span: Span {
+            start,
+            end: self.document.forward(size),
+          },
+        };
+      }
+    }
     if let Some((start_size, level)) = atx_heading_start(bytes) {
-      let (line_size, _) = one_line(bytes);
+      let (line_size, content_size) = one_line(bytes);
+      let raw_start = start + self.document.spaces() + start_size;
+      let raw_end =
+        raw_start + atx_heading_content(&bytes[start_size..content_size.max(start_size)]);
       let end = self.document.forward(line_size);
       return Token {
         value: BlockToken::ATXHeading {
           level: HeadingLevel::new(level).unwrap(),
           raws: vec![Span {
-            start: start + start_size,
-            end,
+            start: raw_start,
+            end: raw_end,
           }],
         },
         span: Span { start, end },
@@ -347,6 +522,11 @@ impl<'source> BlockParser<'source> {
     if let Some(block) = self.scan_fenced_code() {
       return block;
     }
+    if self.options.math {
+      if let Some(block) = self.scan_fenced_math() {
+        return block;
+      }
+    }
     // TODO: lind definition, table
     self.scan_paragraph_like()
   }
@@ -453,6 +633,24 @@ impl<'source> BlockParser<'source> {
             }
           }
         }
+      } else if let ContainerBlock::FootnoteDefinition = container_block {
+        // continued by blank lines and lines indented by four spaces
+        let (line, line_spaces) = spaces0(&self.document.bytes()[size..]);
+        if eol(line).is_some() {
+          spine_level += 1;
+          continue;
+        }
+        if line_spaces >= 4 {
+          size += 4;
+          spine_level += 1;
+          continue;
+        }
+      } else if let ContainerBlock::Directive(fence) = container_block {
+        let (line, _) = spaces0(&self.document.bytes()[size..]);
+        if close_container_directive(line, *fence).is_none() {
+          spine_level += 1;
+          continue;
+        }
       } else if let ContainerBlock::List(ch) = container_block {
         if let Some(ContainerBlock::ListItem(indent)) = self.spine.get(spine_level + 1) {
           if spaces >= *indent {
@@ -505,6 +703,9 @@ impl<'source> BlockParser<'source> {
       && thematic_break(bytes).is_none()
       && setext_heading(bytes).is_none()
       && open_fenced_code(bytes).is_none()
+      && !(self.options.math && open_fenced_math(bytes).is_some())
+      && !(self.options.directives
+        && (open_container_directive(bytes, 0).is_some() || leaf_directive(bytes, 0).is_some()))
     {
       return false;
     }
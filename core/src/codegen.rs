@@ -1,19 +1,70 @@
-use crate::block::*;
-use crate::document::*;
-use crate::inline::*;
+use crate::esm::*;
+use crate::footnote::*;
+use crate::mdast::*;
+use crate::options::*;
+use crate::source_map::*;
+use crate::target::*;
+use crate::toc::*;
 use crate::token::*;
+use std::collections::{HashMap, HashSet};
+// tag of fragments, written as the runtime's fragment
+const FRAGMENT: &str = "<>";
+// the component of `export default`
+const LAYOUT: &str = "MDXLayout";
+
 pub struct Codegen<'a> {
   pub code: String,
-  source: &'a str,
-  bytes: &'a [u8],
+  pub toc: Toc,
+  // filled when a source map is asked for
+  pub mappings: Vec<Mapping>,
+  pub frontmatter: Option<String>,
+  // what was left out of a tree from the host
+  pub diagnostics: Vec<Diagnostic>,
+  options: &'a CompileOptions,
+  footnotes: Footnotes<'a>,
+  // url and title by label, for the references of trees from the host
+  definitions: HashMap<String, (&'a str, &'a Option<String>)>,
+  // start of the node being generated, the `__source` of development calls
+  location: Option<Point>,
+  // the source's, for `__source` columns in UTF-16 code units like JS counts them
+  pub utf16: Option<Utf16Index>,
+  target: Box<dyn Target + 'a>,
+  // open element calls
+  calls: Vec<Element>,
+  // open elements of JSX output, none once closed by `/>`
+  tags: Vec<Option<String>>,
+  // entries of the exports object outside of ES modules, e.g. `a`, `b: c`, `..._reexport0`
+  exports: Vec<String>,
+  reexports: usize,
+  // `export default` gave a layout, `MDXLayout`
+  layout: bool,
+  // names the ESM binds, components used under other names come from `_components`
+  bindings: HashSet<&'a str>,
+  // host elements the markdown gave, the defaults of `_components`
+  intrinsics: Vec<String>,
 }
 
 impl<'a> Codegen<'a> {
-  pub fn new(source: &'a str, bytes: &'a [u8]) -> Self {
+  pub fn new(options: &'a CompileOptions) -> Self {
     Codegen {
       code: String::new(),
-      source,
-      bytes,
+      toc: Toc::new(options.toc_min_depth, options.toc_max_depth),
+      mappings: vec![],
+      frontmatter: None,
+      diagnostics: vec![],
+      options,
+      footnotes: Footnotes::new(),
+      definitions: HashMap::new(),
+      location: None,
+      utf16: None,
+      target: target(options),
+      calls: vec![],
+      tags: vec![],
+      exports: vec![],
+      reexports: 0,
+      layout: false,
+      bindings: HashSet::new(),
+      intrinsics: vec![],
     }
   }
 
@@ -21,302 +72,2160 @@ impl<'a> Codegen<'a> {
     self.code.push_str(str);
   }
 
-  fn write_jsx_start(&mut self, tag: &str, attrs: &Vec<JSXAttr>, jsxs: bool) {
-    if jsxs {
-      self.code.push_str("_jsxRuntime.jsxs(\"");
-    } else {
-      self.code.push_str("_jsxRuntime.jsx(\"");
+  // the code written next comes from `position`
+  fn map(&mut self, position: &Option<Position>) {
+    if let (Some(_), Some(position)) = (&self.options.source_map, position) {
+      self.mappings.push(Mapping {
+        generated: self.code.len(),
+        line: position.start.line,
+        column: position.start.column,
+      });
     }
-    self.code.push_str(tag);
-    self.code.push_str("\",{");
-    self.gen_jsx_attrs(attrs);
-    if jsxs {
-      self.code.push_str("children:[");
+  }
+
+  // user-authored JS copied as it is, every line maps back to its source line
+  fn write_verbatim(&mut self, value: &str, position: &Option<Position>) {
+    self.map(position);
+    if let (Some(_), Some(position)) = (&self.options.source_map, position) {
+      let generated = self.code.len();
+      for (line, (i, _)) in value.match_indices('\n').enumerate() {
+        self.mappings.push(Mapping {
+          generated: generated + i + 1,
+          line: position.start.line + line + 1,
+          column: 1,
+        });
+      }
+    }
+    self.write(value);
+  }
+
+  fn write_js_string(&mut self, str: &str) {
+    push_js_string(&mut self.code, str);
+  }
+
+  // text children, as JS strings or JSX text
+  fn write_text(&mut self, str: &str) {
+    if !self.options.jsx {
+      self.write_js_string(str);
+    } else if str.contains(['\n', '\r']) {
+      // JSX text would fold the line endings
+      self.code.push('{');
+      self.write_js_string(str);
+      self.code.push('}');
     } else {
-      self.code.push_str("children:");
+      for ch in str.chars() {
+        match ch {
+          '{' => self.code.push_str("&#123;"),
+          '}' => self.code.push_str("&#125;"),
+          '<' => self.code.push_str("&lt;"),
+          '>' => self.code.push_str("&gt;"),
+          '&' => self.code.push_str("&amp;"),
+          _ => self.code.push(ch),
+        }
+      }
     }
   }
-  fn write_jsx_end(&mut self, jsxs: bool) {
-    if jsxs {
-      self.code.push_str("]})");
+
+  // user-authored expression children, in braces in JSX
+  fn write_expression(&mut self, value: &str, position: &Option<Position>) {
+    if self.options.jsx {
+      self.code.push('{');
+      self.write_verbatim(value, position);
+      self.code.push('}');
     } else {
-      self.code.push_str("})");
+      self.write_verbatim(value, position);
+    }
+  }
+
+  // between props and between children, JSX needs none
+  fn write_separator(&mut self) {
+    if !self.options.jsx {
+      self.code.push(',');
+    }
+  }
+
+  fn write_jsx_start(&mut self, tag: &str, attrs: &[JsxAttribute], jsxs: bool) {
+    self.write_tag_start(tag, jsxs, false);
+    self.gen_jsx_attrs(attrs);
+    self.write_children_start();
+  }
+  fn write_jsx_end(&mut self) {
+    if self.options.jsx {
+      if let Some(tag) = self.tags.pop().flatten() {
+        self.code.push_str("</");
+        self.code.push_str(&tag);
+        self.code.push('>');
+      }
+      return;
+    }
+    if let Some(element) = self.calls.pop() {
+      self.target.element_end(&mut self.code, &element);
+    }
+  }
+  // closes elements without children, `/>` in JSX
+  fn write_call_end(&mut self) {
+    if self.options.jsx {
+      self.tags.pop();
+      self.code.push_str(" />");
+      return;
+    }
+    if let Some(element) = self.calls.pop() {
+      self.target.empty_element_end(&mut self.code, &element);
+    }
+  }
+  // the call up to the opening of the props object, or the JSX tag up to its props
+  fn write_element_start(&mut self, tag: &str, jsxs: bool) {
+    self.write_tag_start(tag, jsxs, true);
+  }
+  // host elements of the markdown go through `_components` too, the ones written as JSX don't
+  fn write_tag_start(&mut self, tag: &str, jsxs: bool, markdown: bool) {
+    let provided;
+    let tag = match tag.split('.').next() {
+      Some(name)
+        if self.options.provider_import_source.is_some()
+          && matches!(Tag::new(name), Tag::Component(_))
+          && !self.bindings.contains(name) =>
+      {
+        provided = format!("_components.{}", tag);
+        provided.as_str()
+      }
+      Some(name)
+        if markdown
+          && self.options.provider_import_source.is_some()
+          && matches!(Tag::new(name), Tag::Host(_))
+          && tag
+            .bytes()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == b'_' || ch == b'$') =>
+      {
+        if !self.intrinsics.iter().any(|intrinsic| intrinsic == tag) {
+          self.intrinsics.push(tag.to_string());
+        }
+        provided = format!("_components.{}", tag);
+        provided.as_str()
+      }
+      _ => tag,
+    };
+    if self.options.jsx {
+      let tag = if tag == FRAGMENT { "" } else { tag };
+      self.code.push('<');
+      self.code.push_str(tag);
+      self.tags.push(Some(tag.to_string()));
+      return;
+    }
+    let element = Element {
+      tag: if tag == FRAGMENT {
+        Tag::Fragment
+      } else {
+        Tag::new(tag)
+      },
+      many: jsxs,
+      location: self.location.clone(),
+    };
+    self.target.element_start(&mut self.code, &element);
+    self.calls.push(element);
+  }
+  fn write_children_start(&mut self) {
+    if self.options.jsx {
+      self.code.push('>');
+    } else if let Some(element) = self.calls.last() {
+      self.target.children_start(&mut self.code, element);
     }
   }
   fn write_non_attrs_jsx_start(&mut self, tag: &str, jsxs: bool) {
-    if jsxs {
-      self.code.push_str("_jsxRuntime.jsxs(\"");
+    self.write_element_start(tag, jsxs);
+    self.write_children_start();
+  }
+  // in place of the children, JSX elements close themselves instead
+  fn write_no_children(&mut self) {
+    if !self.options.jsx {
+      if let Some(element) = self.calls.last() {
+        self.target.no_children(&mut self.code, element);
+      }
+    } else if let Some(Some(tag)) = self.tags.last() {
+      // fragments stay `<></>`
+      if !tag.is_empty() {
+        self.code.pop();
+        self.code.push_str(" />");
+        *self.tags.last_mut().unwrap() = None;
+      }
+    }
+  }
+  fn write_prop_key(&mut self, key: &str) {
+    let key = self.target.prop_name(key);
+    let key = key.as_ref();
+    if self.options.jsx {
+      self.code.push(' ');
+      self.write(key);
+      self.code.push('=');
+    } else if key
+      .bytes()
+      .all(|ch| ch.is_ascii_alphanumeric() || ch == b'_' || ch == b'$')
+    {
+      self.write(key);
+      self.code.push(':');
     } else {
-      self.code.push_str("_jsxRuntime.jsx(\"");
+      self.write_js_string(key);
+      self.code.push(':');
     }
-    self.code.push_str(tag);
-    if jsxs {
-      self.code.push_str("\",{children:[");
+  }
+  // a string prop, quoted in JSX unless the value needs escapes
+  fn write_prop(&mut self, key: &str, value: &str) {
+    self.write_prop_key(key);
+    if self.options.jsx && !value.contains(['"', '&', '\n', '\r']) {
+      self.code.push('"');
+      self.code.push_str(value);
+      self.code.push('"');
+    } else if self.options.jsx {
+      self.code.push('{');
+      self.write_js_string(value);
+      self.code.push('}');
     } else {
-      self.code.push_str("\",{children:");
+      self.write_js_string(value);
     }
   }
-  fn write_span(&mut self, span: &Span) {
-    self.write(&self.source[span.start..span.end]);
+  // a JS value prop, in braces in JSX
+  fn write_js_prop(&mut self, key: &str, value: &str, position: &Option<Position>) {
+    self.write_prop_key(key);
+    self.write_expression(value, position);
   }
 
-  pub fn gen(&mut self, ast: &AST<Token<BlockToken>>) {
-    let block_start = ast.span.start;
-    if block_start > 0 {
-      self.write(&self.source[..block_start]);
+  // string literal props, for elements the compiler creates itself
+  fn write_props_jsx_start(&mut self, tag: &str, props: &[(&str, &str)], jsxs: bool) {
+    self.write_element_start(tag, jsxs);
+    for (key, value) in props {
+      self.write_prop(key, value);
+      self.write_separator();
     }
-    self.write("export default () => ");
-    self.gen_blocks("_jsxRuntime.Fragment", &ast.children);
+    self.write_children_start();
+  }
+  fn write_directive_jsx_start(
+    &mut self,
+    name: &str,
+    attributes: &[(String, Option<String>)],
+    jsxs: bool,
+  ) {
+    self.write_element_start(name, jsxs);
+    // attributes without a value in the host's tree are left out
+    for (key, value) in attributes {
+      let Some(value) = value else {
+        continue;
+      };
+      let key = if key == "class" { "className" } else { key };
+      self.write_prop(key, value);
+      self.write_separator();
+    }
+    self.write_children_start();
   }
 
-  pub fn gen_blocks(&mut self, tag: &str, blocks: &Vec<Token<BlockToken>>) {
-    let jsxs = blocks.len() > 1;
-    self.write_non_attrs_jsx_start(tag, jsxs);
-    for block in blocks {
-      self.gen_block(block, jsxs);
+  pub fn gen(&mut self, ast: &'a Node) {
+    let children = match ast {
+      Node::Root { children, .. } => children.as_slice(),
+      _ => std::slice::from_ref(ast),
+    };
+    // the classic pragma is in scope already, JSX is compiled later
+    let classic = self.options.jsx_runtime == JsxRuntime::Classic
+      && matches!(self.options.target, TargetKind::React | TargetKind::Preact);
+    let runtime = match self.options.jsx {
+      true => None,
+      false => self
+        .target
+        .runtime_module(self.options.jsx_import_source.as_deref()),
+    };
+    match (self.options.output_format, runtime) {
+      (OutputFormat::Program, Some(runtime)) => {
+        self.write("import * as _jsxRuntime from ");
+        self.write_js_string(&runtime);
+        self.write(";\n");
+      }
+      (OutputFormat::CommonJs, Some(runtime)) => {
+        self.write("const _jsxRuntime = require(");
+        self.write_js_string(&runtime);
+        self.write(");\n");
+      }
+      (OutputFormat::FunctionBody, _) if !self.options.jsx && !classic => {
+        self.write("const _jsxRuntime = arguments[0];\n")
+      }
+      _ => {}
+    }
+    if let Some(provider) = &self.options.provider_import_source {
+      match self.options.output_format {
+        OutputFormat::Program => {
+          self.write("import {useMDXComponents as _provideComponents} from ");
+          self.write_js_string(provider);
+          self.write(";\n");
+        }
+        OutputFormat::CommonJs => {
+          self.write("const {useMDXComponents: _provideComponents} = require(");
+          self.write_js_string(provider);
+          self.write(");\n");
+        }
+        OutputFormat::FunctionBody => {
+          self.write("const {useMDXComponents: _provideComponents} = arguments[0];\n")
+        }
+      }
+    }
+    self.gen_esm(children);
+    match self.options.output_format {
+      OutputFormat::Program => self.write("export default "),
+      _ => self.write("const MDXContent = "),
+    }
+    // components from the provider and the `components` prop, for the ones not imported
+    let provider = self.options.provider_import_source.is_some();
+    let mut defaults = None;
+    if provider {
+      self.write("(props = {}) => {\nconst _components = {");
+      defaults = Some(self.code.len());
+      self.write("..._provideComponents(), ...props.components};\nreturn ");
+    } else if self.layout {
+      self.write("(props = {}) => ");
+    } else {
+      self.write("() => ");
     }
-    self.write_jsx_end(jsxs);
+    // the layout gets the props and the content as children
+    if self.layout {
+      let props = JsxAttribute::MdxJsxExpressionAttribute {
+        value: "...props".to_string(),
+        position: None,
+      };
+      self.write_jsx_start(LAYOUT, &[props], false);
+    }
+    (self.footnotes, self.definitions) = collect_definitions(children);
+    self.diagnose_html(children);
+    if self.footnotes.has_definitions() {
+      // the footnotes section follows the content
+      self.write_non_attrs_jsx_start(FRAGMENT, true);
+      for child in rendered(children) {
+        self.gen_node(child, true);
+      }
+      self.location = None;
+      self.gen_footnotes();
+      self.write_jsx_end();
+    } else {
+      self.gen_children(FRAGMENT, children);
+    }
+    if self.layout {
+      self.write_jsx_end();
+    }
+    if let Some(at) = defaults {
+      self.write_component_defaults(at);
+      self.write(";\n}");
+    }
+    if self.options.output_format == OutputFormat::Program {
+      if self.options.toc_export {
+        self.write(";\nexport const toc = ");
+        self.gen_toc_entries();
+      }
+      return;
+    }
+    // the exports object, the content last so that `export *` doesn't replace it
+    match self.options.output_format {
+      OutputFormat::CommonJs => self.write(";\nmodule.exports = {"),
+      _ => self.write(";\nreturn {"),
+    }
+    for export in std::mem::take(&mut self.exports) {
+      self.write(&export);
+      self.write(", ");
+    }
+    self.write("default: MDXContent");
+    if self.options.toc_export {
+      self.write(", toc: ");
+      self.gen_toc_entries();
+    }
+    self.write("};");
   }
 
-  fn gen_raws(&mut self, tag: &str, raws: &Vec<Span>) {
-    let mut inline_parser = InlineParser::new(self.source, self.bytes, raws);
-    let inlines = inline_parser.parse();
-    self.gen_inlines_with_tag(tag, &inlines.children);
+  // `h1: "h1", ` for the host elements met in the content, written once it's generated
+  fn write_component_defaults(&mut self, at: usize) {
+    let mut defaults = String::new();
+    for intrinsic in std::mem::take(&mut self.intrinsics) {
+      defaults.push_str(&intrinsic);
+      defaults.push_str(": \"");
+      defaults.push_str(&intrinsic);
+      defaults.push_str("\", ");
+    }
+    self.code.insert_str(at, &defaults);
+    for mapping in &mut self.mappings {
+      if mapping.generated >= at {
+        mapping.generated += defaults.len();
+      }
+    }
   }
 
-  fn gen_inlines_with_tag(&mut self, tag: &str, inlines: &Vec<Token<InlineToken>>) {
-    let jsxs = inlines.len() > 1;
-    self.write_non_attrs_jsx_start(tag, jsxs);
-    self.gen_inlines(inlines);
-    self.write_jsx_end(jsxs);
+  fn gen_toc_entries(&mut self) {
+    let entries = std::mem::take(&mut self.toc.entries);
+    self.gen_toc(&entries);
+    self.toc.entries = entries;
   }
 
-  fn gen_inlines(&mut self, inlines: &Vec<Token<InlineToken>>) {
-    for inline in inlines {
-      self.gen_inline(inline, inlines.len() > 1);
+  // import/export statements are hoisted out of the component, imports first
+  fn gen_esm(&mut self, children: &'a [Node]) {
+    let mut imports = vec![];
+    let mut statements = vec![];
+    for child in children {
+      if let Node::Yaml { value, .. } = child {
+        self.frontmatter = Some(value.clone());
+      }
+      if let Node::MdxjsEsm { value, position } = child {
+        for statement in parse_esm(value) {
+          self.bindings.extend(statement.kind.bindings());
+          let position = offset_position(position, value, statement.offset);
+          match statement.kind {
+            EsmKind::Import { .. } => imports.push((statement, position)),
+            _ => statements.push((statement, position)),
+          }
+        }
+      }
+    }
+    for (statement, position) in imports.iter().chain(&statements) {
+      self.gen_esm_statement(statement, position);
     }
   }
 
-  fn gen_span(&mut self, span: &Span) {
-    let span_start = span.start;
-    let span_end = span.end;
-    let bytes = &self.bytes[span_start..span_end];
-    let mut start = 0;
-    for (i, ch) in bytes.iter().enumerate() {
-      match ch {
-        b'\'' => {
-          self.write_by_start_end(span_start + start, span_start + i);
-          self.write("\\'");
-          start = i + 1;
+  // ES modules keep the statements, the other formats load modules with `require` /
+  // `await import` and collect the exports
+  fn gen_esm_statement(&mut self, statement: &EsmStatement, position: &Option<Position>) {
+    let load = match self.options.output_format {
+      OutputFormat::Program => "",
+      OutputFormat::CommonJs => "require",
+      OutputFormat::FunctionBody => "await import",
+    };
+    match &statement.kind {
+      EsmKind::Import {
+        default,
+        namespace,
+        named,
+        source,
+      } if !load.is_empty() => {
+        let mut bindings: Vec<String> = default
+          .iter()
+          .map(|default| format!("default: {}", default))
+          .collect();
+        for (imported, local) in named {
+          if imported == local {
+            bindings.push(local.to_string());
+          } else {
+            bindings.push(format!("{}: {}", imported, local));
+          }
+        }
+        let call = self.load_call(load, source, statement.attributes);
+        self.map(position);
+        match namespace {
+          Some(namespace) => {
+            self.write(&format!("const {} = {};\n", namespace, call));
+            if !bindings.is_empty() {
+              self.write(&format!(
+                "const {{{}}} = {};\n",
+                bindings.join(", "),
+                namespace
+              ));
+            }
+          }
+          None if bindings.is_empty() => self.write(&format!("{};\n", call)),
+          None => self.write(&format!("const {{{}}} = {};\n", bindings.join(", "), call)),
         }
-        b'\n' => {
-          self.write_by_start_end(span_start + start, span_start + i);
-          self.write("\\n");
-          start = i + 1;
+      }
+      EsmKind::ExportDeclaration { declaration, names } if !load.is_empty() => {
+        let offset = statement.text.len() - declaration.len();
+        let position = offset_position(position, statement.text, offset);
+        self.write_verbatim(declaration, &position);
+        self.write("\n");
+        self
+          .exports
+          .extend(names.iter().map(|name| name.to_string()));
+      }
+      EsmKind::ExportNamed { specifiers, source } if !load.is_empty() => {
+        let object = match source {
+          Some(source) => format!(
+            "{}.",
+            self.write_reexport(load, source, statement.attributes, position)
+          ),
+          None => String::new(),
+        };
+        for (local, exported) in specifiers {
+          if object.is_empty() && local == exported {
+            self.exports.push(local.to_string());
+          } else {
+            self
+              .exports
+              .push(format!("{}: {}{}", exported, object, local));
+          }
         }
-        _ => {}
+      }
+      EsmKind::ExportAll { name, source } if !load.is_empty() => {
+        let reexport = self.write_reexport(load, source, statement.attributes, position);
+        match name {
+          Some(name) => self.exports.push(format!("{}: {}", name, reexport)),
+          None => self.exports.push(format!("...{}", reexport)),
+        }
+      }
+      // a second layout would have nothing to wrap
+      EsmKind::ExportDefault { .. } if self.layout => {
+        self.diagnostics.push(Diagnostic {
+          message: "only the first `export default` is the layout, this one is left out"
+            .to_string(),
+          position: position.clone(),
+        });
+      }
+      EsmKind::ExportDefault { expression } => {
+        let offset = expression.as_ptr() as usize - statement.text.as_ptr() as usize;
+        let position = offset_position(position, statement.text, offset);
+        self.write(&format!("const {} = ", LAYOUT));
+        self.write_verbatim(expression, &position);
+        self.write(";\n");
+        self.layout = true;
+        self.bindings.insert(LAYOUT);
+      }
+      _ if !load.is_empty() => {
+        self.diagnostics.push(Diagnostic {
+          message: format!(
+            "this statement can't be rewritten for the `{}` output format and is left out",
+            self.options.output_format.to_str()
+          ),
+          position: position.clone(),
+        });
+      }
+      _ => {
+        self.write_verbatim(statement.text, position);
+        self.write("\n");
       }
     }
-    self.write_by_start_end(span_start + start, span_end);
   }
 
-  fn write_by_start_end(&mut self, start: usize, end: usize) {
-    if end > start {
-      self.write(&self.source[start..end]);
+  // `require(source)` or `await import(source)`, import attributes are left to `import()`
+  fn load_call(&self, load: &str, source: &str, attributes: Option<&str>) -> String {
+    match attributes {
+      Some(attributes) if self.options.output_format == OutputFormat::FunctionBody => {
+        format!("{}({}, {{with: {}}})", load, source, attributes)
+      }
+      _ => format!("{}({})", load, source),
     }
   }
 
-  fn gen_spans(&mut self, spans: &Vec<Span>) {
-    for span in spans {
-      self.gen_span(span);
+  // the module of an `export ... from`, bound to a name for the exports object
+  fn write_reexport(
+    &mut self,
+    load: &str,
+    source: &str,
+    attributes: Option<&str>,
+    position: &Option<Position>,
+  ) -> String {
+    let name = format!("_reexport{}", self.reexports);
+    self.reexports += 1;
+    let call = self.load_call(load, source, attributes);
+    self.map(position);
+    self.write(&format!("const {} = {};\n", name, call));
+    name
+  }
+
+  fn gen_footnote_reference(&mut self, label: &str) {
+    let (number, count) = match self.footnotes.call(label) {
+      Some(call) => call,
+      None => {
+        self.write_text(&format!("[^{}]", label));
+        return;
+      }
+    };
+    let prefix = &self.options.footnote_id_prefix;
+    let id = footnote_id(&normalize_label(label));
+    let href = format!("#{}fn-{}", prefix, id);
+    let mut ref_id = format!("{}fnref-{}", prefix, id);
+    if count > 1 {
+      ref_id.push_str(&format!("-{}", count));
     }
+    let described_by = format!("{}footnote-label", prefix);
+    self.write_non_attrs_jsx_start("sup", false);
+    self.write_props_jsx_start(
+      "a",
+      &[
+        ("href", &href),
+        ("id", &ref_id),
+        ("data-footnote-ref", ""),
+        ("aria-describedby", &described_by),
+      ],
+      false,
+    );
+    self.write_text(&number.to_string());
+    self.write_jsx_end();
+    self.write_jsx_end();
   }
 
-  fn gen_inline(&mut self, inline: &Token<InlineToken>, jsxs: bool) {
-    let span = &inline.span;
-    match &inline.value {
-      InlineToken::Text(text_spans) => {
-        self.write("\"");
-        for text_span in text_spans {
-          self.write(&self.source[text_span.start..text_span.end]);
-        }
-        self.write("\"");
+  // `section` with an ordered list of the called footnotes, in order of first reference
+  fn gen_footnotes(&mut self) {
+    if self.footnotes.called.is_empty() {
+      return;
+    }
+    let prefix = self.options.footnote_id_prefix.clone();
+    // definitions may call further footnotes, so items are rendered before the list is opened
+    let code = std::mem::take(&mut self.code);
+    let mappings = std::mem::take(&mut self.mappings);
+    let mut items = vec![];
+    let mut index = 0;
+    while index < self.footnotes.called.len() {
+      self.gen_footnote_item(&prefix, index);
+      items.push((
+        std::mem::take(&mut self.code),
+        std::mem::take(&mut self.mappings),
+      ));
+      index += 1;
+    }
+    self.code = code;
+    self.mappings = mappings;
+    self.write_props_jsx_start(
+      "section",
+      &[("data-footnotes", ""), ("className", "footnotes")],
+      true,
+    );
+    let label_id = format!("{}footnote-label", prefix);
+    self.write_props_jsx_start("h2", &[("className", "sr-only"), ("id", &label_id)], false);
+    self.write_text("Footnotes");
+    self.write_jsx_end();
+    self.write_separator();
+    let jsxs = items.len() > 1;
+    self.write_non_attrs_jsx_start("ol", jsxs);
+    for (item, mappings) in items {
+      let generated = self.code.len();
+      self
+        .mappings
+        .extend(mappings.into_iter().map(|mapping| Mapping {
+          generated: generated + mapping.generated,
+          ..mapping
+        }));
+      self.write(&item);
+      if jsxs {
+        self.write_separator();
       }
-      InlineToken::HardBreak => {
-        self.write("_jsxRuntime.jsx(\"br\")");
+    }
+    self.write_jsx_end();
+    self.write_separator();
+    self.write_jsx_end();
+    self.write_separator();
+  }
+
+  // back references go at the end of the last paragraph, or after the blocks without one
+  fn gen_footnote_item(&mut self, prefix: &str, index: usize) {
+    let (label, count) = self.footnotes.called[index].clone();
+    let blocks = match self.footnotes.definition(&label) {
+      Node::FootnoteDefinition { children, .. } => rendered(children),
+      _ => vec![],
+    };
+    let id = footnote_id(&label);
+    let last_inlines = match blocks.last() {
+      Some(Node::Paragraph { children, .. }) => Some(children),
+      _ => None,
+    };
+    // a definition can call itself, the count grows while it is rendered but stays
+    // at least one, which is all `jsxs` depends on
+    let children = blocks.len() + if last_inlines.is_some() { 0 } else { count };
+    let jsxs = children > 1;
+    let item_id = format!("{}fn-{}", prefix, id);
+    self.write_props_jsx_start("li", &[("id", &item_id)], jsxs);
+    for block in &blocks[..blocks.len() - last_inlines.iter().count()] {
+      self.gen_node(block, jsxs);
+    }
+    if let Some(inlines) = last_inlines {
+      self.write_non_attrs_jsx_start("p", true);
+      for inline in inlines {
+        self.gen_node(inline, true);
       }
-      InlineToken::Emphasis(children) => {
-        let tag = if self.bytes[span.start] == b'~' {
-          "del"
-        } else if span.end - span.start >= 2 {
-          "strong"
-        } else {
-          "em"
-        };
-        self.gen_inlines_with_tag(tag, children);
+      self.write_text(" ");
+      self.write_separator();
+      let count = self.footnotes.called[index].1;
+      self.gen_footnote_backrefs(prefix, &id, index + 1, count);
+      self.write_jsx_end();
+      if jsxs {
+        self.write_separator();
       }
-      InlineToken::Link {
-        text_children,
-        url,
-        title,
-      } => {
-        // TODO: url title
-        self.gen_inlines_with_tag("a", text_children);
+    } else {
+      let count = self.footnotes.called[index].1;
+      self.gen_footnote_backrefs(prefix, &id, index + 1, count);
+    }
+    self.write_jsx_end();
+  }
+
+  // separated by spaces, each followed by a comma as they always sit in a children array
+  fn gen_footnote_backrefs(&mut self, prefix: &str, id: &str, number: usize, count: usize) {
+    for i in 1..=count {
+      let (href, label) = backref(prefix, id, number, i);
+      if i > 1 {
+        self.write_text(" ");
+        self.write_separator();
       }
-      InlineToken::Code(code_spans) => {
-        self.write_non_attrs_jsx_start("code", false);
-        self.write("\"");
-        self.gen_spans(code_spans);
-        self.write("\"");
-        self.write_jsx_end(false);
+      self.write_props_jsx_start(
+        "a",
+        &[
+          ("href", &href),
+          ("data-footnote-backref", ""),
+          ("aria-label", &label),
+          ("className", "data-footnote-backref"),
+        ],
+        i > 1,
+      );
+      self.write_text("↩");
+      if i > 1 {
+        self.write_separator();
+        self.write_non_attrs_jsx_start("sup", false);
+        self.write_text(&i.to_string());
+        self.write_jsx_end();
+        self.write_separator();
       }
-      InlineToken::JSX(element) => {
-        self.gen_jsx_element(element);
+      self.write_jsx_end();
+      self.write_separator();
+    }
+  }
+
+  fn diagnose_html(&mut self, nodes: &[Node]) {
+    for node in nodes {
+      if let Node::Html { position, .. } = node {
+        self.diagnostics.push(Diagnostic {
+          message: "raw HTML is left out, MDX writes elements as JSX".to_string(),
+          position: position.clone(),
+        });
       }
-      _ => {
-        return;
+      self.diagnose_html(node.children());
+    }
+  }
+
+  fn gen_toc(&mut self, entries: &[TocEntry]) {
+    self.write("[");
+    for (i, entry) in entries.iter().enumerate() {
+      if i > 0 {
+        self.write(",");
       }
+      self.write("{level:");
+      self.write(&entry.level.to_string());
+      self.write(",text:");
+      self.write_js_string(&entry.text);
+      self.write(",slug:");
+      self.write_js_string(&entry.slug);
+      self.write(",children:");
+      self.gen_toc(&entry.children);
+      self.write("}");
     }
-    if jsxs {
-      self.write(",");
+    self.write("]");
+  }
+
+  pub fn gen_children(&mut self, tag: &str, nodes: &[Node]) {
+    let nodes = rendered(nodes);
+    let jsxs = nodes.len() > 1;
+    self.write_non_attrs_jsx_start(tag, jsxs);
+    self.gen_rendered_children(&nodes);
+  }
+
+  // children and closing of an element opened with `jsxs = nodes.len() > 1`
+  fn gen_rendered_children(&mut self, nodes: &[&Node]) {
+    let jsxs = nodes.len() > 1;
+    if nodes.is_empty() {
+      self.write_no_children();
+    }
+    for node in nodes {
+      self.gen_node(node, jsxs);
     }
+    self.write_jsx_end();
   }
 
-  fn gen_jsx_element(&mut self, element: &JSXElement) {
-    let JSXElement {
-      tag,
-      attributes,
-      children,
-    } = element;
-    let jsxs = children.len() > 1;
-    self.write_jsx_start(
-      if tag.is_empty() {
-        "_jsxRuntime.Fragment"
+  // the checkbox leads the first paragraph, as GitHub renders it
+  fn gen_task_list_item(&mut self, checked: bool, children: &[Node]) {
+    let blocks = rendered(children);
+    let jsxs = blocks.len() > 1;
+    self.write_props_jsx_start("li", &[("className", "task-list-item")], jsxs);
+    for (i, block) in blocks.iter().enumerate() {
+      if let (0, Node::Paragraph { children, .. }) = (i, block) {
+        self.write_non_attrs_jsx_start("p", !children.is_empty());
+        self.gen_task_checkbox(checked);
+        if !children.is_empty() {
+          self.write_separator();
+          self.write_text(" ");
+          self.write_separator();
+          self.gen_inlines(children);
+        }
+        self.write_jsx_end();
+        if jsxs {
+          self.write_separator();
+        }
       } else {
-        &tag
+        self.gen_node(block, jsxs);
+      }
+    }
+    if blocks.is_empty() {
+      self.gen_task_checkbox(checked);
+    }
+    self.write_jsx_end();
+  }
+
+  fn gen_task_checkbox(&mut self, checked: bool) {
+    if let Some(component) = &self.options.checkbox_component {
+      self.write_element_start(component, false);
+    } else {
+      self.write_element_start("input", false);
+      self.write_prop("type", "checkbox");
+      self.write_separator();
+      self.write_js_prop("disabled", "true", &None);
+      self.write_separator();
+    }
+    self.write_js_prop("checked", if checked { "true" } else { "false" }, &None);
+    self.write_call_end();
+  }
+
+  fn gen_heading(&mut self, depth: usize, children: &[Node], position: &Option<Position>) {
+    let span = match position {
+      Some(position) => Span {
+        start: position.start.offset,
+        end: position.end.offset,
       },
-      attributes,
-      jsxs,
-    );
-    if children.is_empty() {
-      self.write("null");
+      None => Span { start: 0, end: 0 },
+    };
+    let slug = self.toc.push(depth, nodes_text(children), span);
+    let tag = format!("h{}", depth);
+    // with the toc export, headings get the `id` its entries link to
+    if self.options.toc_export {
+      self.gen_inlines_with_props(&tag, &[("id", &slug)], children);
+    } else {
+      self.gen_inlines_with_tag(&tag, children);
     }
-    for child in children {
-      match child {
-        JSX::Text(span) => {
-          self.write("'");
-          self.write(&self.source[span.start..span.end]);
-          self.write("'");
-        }
-        JSX::Element(element) => {
-          self.gen_jsx_element(element);
-        }
-        JSX::Expression(segments) => {
-          self.gen_expression_segments(segments);
-        }
+  }
+
+  fn gen_directive(
+    &mut self,
+    name: &str,
+    attributes: &[(String, Option<String>)],
+    children: &[Node],
+  ) {
+    let children = rendered(children);
+    let jsxs = children.len() > 1;
+    self.write_directive_jsx_start(name, attributes, jsxs);
+    self.gen_rendered_children(&children);
+  }
+
+  fn gen_alert(&mut self, alert: &Alert, children: &[Node]) {
+    let blocks = rendered(children);
+    let jsxs = blocks.len() > 1;
+    let kind = alert.to_str();
+    if let Some(component) = &self.options.alert_component {
+      self.write_props_jsx_start(component, &[("type", kind)], jsxs);
+    } else {
+      let class_name = format!("markdown-alert markdown-alert-{}", kind);
+      self.write_props_jsx_start(
+        "blockquote",
+        &[("data-alert", kind), ("className", &class_name)],
+        jsxs,
+      );
+    }
+    self.gen_rendered_children(&blocks);
+  }
+
+  fn gen_inlines_with_tag(&mut self, tag: &str, inlines: &[Node]) {
+    self.gen_inlines_with_props(tag, &[], inlines);
+  }
+
+  // `children: null` without inlines, e.g. of an empty heading
+  fn gen_inlines_with_props(&mut self, tag: &str, props: &[(&str, &str)], inlines: &[Node]) {
+    let inlines = rendered(inlines);
+    self.write_props_jsx_start(tag, props, inlines.len() > 1);
+    self.gen_rendered_children(&inlines);
+  }
+
+  fn gen_inlines(&mut self, inlines: &[Node]) {
+    for inline in inlines {
+      self.gen_node(inline, inlines.len() > 1);
+    }
+  }
+
+  // url and optional title props of links and images
+  fn link_props(
+    url_key: &'static str,
+    url: &str,
+    title: &Option<String>,
+  ) -> Vec<(&'static str, String)> {
+    let mut props = vec![(url_key, url.to_string())];
+    if let Some(title) = title {
+      props.push(("title", title.clone()));
+    }
+    props
+  }
+
+  fn gen_link(&mut self, url: &str, title: &Option<String>, children: &[Node]) {
+    let props = Self::link_props("href", url, title);
+    let props: Vec<(&str, &str)> = props.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    self.gen_inlines_with_props("a", &props, children);
+  }
+
+  fn gen_image(&mut self, url: &str, title: &Option<String>, alt: &Option<String>) {
+    let mut props = Self::link_props("src", url, title);
+    if let Some(alt) = alt {
+      props.insert(1, ("alt", alt.clone()));
+    }
+    self.write_element_start("img", false);
+    for (i, (key, value)) in props.iter().enumerate() {
+      if i > 0 {
+        self.write_separator();
       }
-      self.write(",");
+      self.write_prop(key, value);
     }
-    self.write_jsx_end(jsxs);
+    self.write_call_end();
   }
 
-  fn gen_expression_segments(&mut self, segments: &Vec<JSXExpressionSegment>) {
-    for segment in segments {
-      match segment {
-        JSXExpressionSegment::Element(element) => {
-          self.gen_jsx_element(element);
-        }
-        JSXExpressionSegment::JS(span) => {
-          self.write(&self.source[span.start..span.end]);
-        }
+  // references without a definition stay the text they were written as
+  fn gen_link_reference(
+    &mut self,
+    identifier: &str,
+    label: &str,
+    reference_type: ReferenceType,
+    children: &[Node],
+  ) {
+    if let Some(&(url, title)) = self.definitions.get(&normalize_label(identifier)) {
+      self.gen_link(url, title, children);
+      return;
+    }
+    let mut nodes = vec![Node::Text {
+      value: "[".to_string(),
+      position: None,
+    }];
+    nodes.extend(children.iter().cloned());
+    nodes.push(Node::Text {
+      value: reference_type.suffix(identifier, label),
+      position: None,
+    });
+    self.gen_inlines_with_tag(FRAGMENT, &nodes);
+  }
+
+  fn gen_image_reference(
+    &mut self,
+    identifier: &str,
+    label: &str,
+    reference_type: ReferenceType,
+    alt: &Option<String>,
+  ) {
+    match self.definitions.get(&normalize_label(identifier)) {
+      Some(&(url, title)) => self.gen_image(url, title, alt),
+      None => {
+        let alt = alt.as_deref().unwrap_or_default();
+        self.write_text(&format!(
+          "![{}{}",
+          alt,
+          reference_type.suffix(identifier, label)
+        ));
+      }
+    }
+  }
+
+  // the first row goes in `thead`, the others in `tbody`
+  fn gen_table(&mut self, align: &[Option<String>], rows: &[Node]) {
+    let (head, body) = match rows.split_first() {
+      Some(rows) => rows,
+      None => {
+        self.write_non_attrs_jsx_start("table", false);
+        self.write_no_children();
+        self.write_jsx_end();
+        return;
+      }
+    };
+    let jsxs = !body.is_empty();
+    self.write_non_attrs_jsx_start("table", jsxs);
+    self.write_non_attrs_jsx_start("thead", false);
+    self.gen_table_row("th", align, head.children(), false);
+    self.write_jsx_end();
+    if jsxs {
+      self.write_separator();
+      self.write_non_attrs_jsx_start("tbody", body.len() > 1);
+      for row in body {
+        self.gen_table_row("td", align, row.children(), body.len() > 1);
       }
+      self.write_jsx_end();
+      self.write_separator();
     }
+    self.write_jsx_end();
   }
 
-  fn gen_jsx_attrs(&mut self, attrs: &Vec<JSXAttr>) {
+  fn gen_table_row(&mut self, tag: &str, align: &[Option<String>], cells: &[Node], jsxs: bool) {
+    self.write_non_attrs_jsx_start("tr", cells.len() > 1);
+    if cells.is_empty() {
+      self.write_no_children();
+    }
+    for (i, cell) in cells.iter().enumerate() {
+      let props: Vec<(&str, &str)> = match align.get(i) {
+        Some(Some(align)) => vec![("align", align)],
+        _ => vec![],
+      };
+      self.gen_inlines_with_props(tag, &props, cell.children());
+      if cells.len() > 1 {
+        self.write_separator();
+      }
+    }
+    self.write_jsx_end();
+    if jsxs {
+      self.write_separator();
+    }
+  }
+
+  fn gen_math(&mut self, tag: &str, class_name: &str, tex: &str) {
+    self.write_props_jsx_start(tag, &[("className", class_name)], false);
+    self.write_text(tex);
+    self.write_jsx_end();
+  }
+
+  fn gen_code(&mut self, value: &str) {
+    self.write_non_attrs_jsx_start("pre", false);
+    self.write_non_attrs_jsx_start("code", false);
+    if value.is_empty() {
+      self.write_no_children();
+    } else {
+      self.write_text(&format!("{}\n", value));
+    }
+    self.write_jsx_end();
+    self.write_jsx_end();
+  }
+
+  fn gen_jsx_element(
+    &mut self,
+    name: &Option<String>,
+    attributes: &[JsxAttribute],
+    children: &[Node],
+  ) {
+    let children = rendered(children);
+    let jsxs = children.len() > 1;
+    self.write_jsx_start(name.as_deref().unwrap_or(FRAGMENT), attributes, jsxs);
+    if children.is_empty() {
+      self.write_no_children();
+    }
+    for child in children {
+      self.gen_node(child, false);
+      self.write_separator();
+    }
+    self.write_jsx_end();
+  }
+
+  fn gen_jsx_attrs(&mut self, attrs: &[JsxAttribute]) {
     for attr in attrs {
       match attr {
-        JSXAttr::KeyLiteralValue { key, value } => {
-          self.write_span(key);
-          self.write(":");
-          self.write_span(value);
-        }
-        JSXAttr::KeyTrueValue { key } => {
-          self.write_span(key);
-          self.write(":true");
+        JsxAttribute::MdxJsxAttribute {
+          name,
+          value,
+          position,
+        } => {
+          self.map(position);
+          match value {
+            Some(JsxAttributeValue::Literal(value)) => self.write_prop(name, value),
+            Some(JsxAttributeValue::Expression(expression)) => {
+              self.write_js_prop(name, &expression.value, &expression.position)
+            }
+            None => self.write_js_prop(name, "true", &None),
+          }
         }
-        JSXAttr::Spread(segments) => {
-          self.write("...");
-          self.gen_expression_segments(segments);
-        }
-        JSXAttr::KeyValue { key, value } => {
-          self.write_span(key);
-          self.write(":");
-          self.gen_expression_segments(value);
+        // `...spread`, `{...spread}` in JSX
+        JsxAttribute::MdxJsxExpressionAttribute { value, position } => {
+          if self.options.jsx {
+            self.code.push(' ');
+          }
+          self.write_expression(value, position);
         }
       }
-      self.write(",");
+      self.write_separator();
     }
   }
 
-  fn gen_block(&mut self, block: &Token<BlockToken>, jsxs: bool) {
-    match &block.value {
-      BlockToken::ATXHeading { level, raws } => {
-        self.gen_raws(level.to_str(), raws);
-      }
-      BlockToken::SetextHeading { level, raws } => {
-        self.gen_raws(level.to_str(), raws);
+  fn gen_node(&mut self, node: &Node, jsxs: bool) {
+    self.map(node.position());
+    if let Some(position) = node.position() {
+      self.location = Some(match &self.utf16 {
+        Some(index) => index.point(&position.start),
+        None => position.start.clone(),
+      });
+    }
+    match node {
+      Node::Root { children, .. } => {
+        self.gen_children(FRAGMENT, children);
       }
-      BlockToken::Paragraph { raws } => {
-        self.gen_raws("p", raws);
+      Node::Heading {
+        depth,
+        children,
+        position,
+      } => {
+        self.gen_heading(*depth, children, position);
       }
-      BlockToken::BlockQuote { level, blocks } => {
-        self.gen_blocks("blockquote", blocks);
+      Node::Paragraph { children, .. } => {
+        self.gen_inlines_with_tag("p", children);
       }
-      BlockToken::List { blocks, .. } => {
-        self.gen_blocks("ul", blocks);
+      Node::Blockquote { children, .. } => {
+        match self.options.alerts.then(|| alert(children)).flatten() {
+          Some((alert, children)) => self.gen_alert(&alert, &children),
+          None => self.gen_children("blockquote", children),
+        }
       }
-      BlockToken::ListItem { blocks, .. } => {
-        self.gen_blocks("li", blocks);
+      Node::List {
+        ordered,
+        start,
+        children,
+        ..
+      } => {
+        let tag = if *ordered { "ol" } else { "ul" };
+        let start = start
+          .filter(|start| *ordered && *start != 1)
+          .map(|start| start.to_string());
+        let is_task_list = children.iter().any(|child| {
+          matches!(
+            child,
+            Node::ListItem {
+              checked: Some(_),
+              ..
+            }
+          )
+        });
+        let mut props = vec![];
+        if is_task_list {
+          props.push(("className", "contains-task-list"));
+        }
+        if let Some(start) = &start {
+          props.push(("start", start.as_str()));
+        }
+        let items = rendered(children);
+        self.write_props_jsx_start(tag, &props, items.len() > 1);
+        self.gen_rendered_children(&items);
       }
-      BlockToken::FencedCode {
-        meta_span,
-        code_spans,
+      Node::ListItem {
+        checked: Some(checked),
+        children,
+        ..
       } => {
-        self.write_non_attrs_jsx_start("pre", false);
-        self.write_non_attrs_jsx_start("code", false);
-        if code_spans.is_empty() {
-          self.write("null");
+        self.gen_task_list_item(*checked, children);
+      }
+      Node::ListItem { children, .. } => {
+        self.gen_children("li", children);
+      }
+      Node::Code { value, .. } => {
+        self.gen_code(value);
+      }
+      Node::Math { value, .. } => {
+        // display math keeps its final line ending, like code
+        let tex = if value.is_empty() {
+          String::new()
         } else {
-          self.write("'");
-          self.gen_spans(code_spans);
-          self.write("'");
-        }
-        self.write_jsx_end(false);
-        self.write_jsx_end(false);
+          format!("{}\n", value)
+        };
+        self.gen_math("div", "math math-display", &tex);
+      }
+      Node::ContainerDirective {
+        name,
+        attributes,
+        children,
+        ..
+      }
+      | Node::LeafDirective {
+        name,
+        attributes,
+        children,
+        ..
+      }
+      | Node::TextDirective {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        self.gen_directive(name, attributes, children);
       }
-      BlockToken::IndentedCode(code_spans) => {
-        self.write_non_attrs_jsx_start("pre", false);
+      Node::MdxJsxFlowElement {
+        name,
+        attributes,
+        children,
+        ..
+      }
+      | Node::MdxJsxTextElement {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        self.gen_jsx_element(name, attributes, children);
+      }
+      Node::MdxFlowExpression { value, position } | Node::MdxTextExpression { value, position } => {
+        self.write_expression(value, position);
+      }
+      Node::Text { value, .. } => {
+        self.write_text(value);
+      }
+      Node::Emphasis { children, .. } => {
+        self.gen_inlines_with_tag("em", children);
+      }
+      Node::Strong { children, .. } => {
+        self.gen_inlines_with_tag("strong", children);
+      }
+      Node::Delete { children, .. } => {
+        self.gen_inlines_with_tag("del", children);
+      }
+      Node::InlineCode { value, .. } => {
         self.write_non_attrs_jsx_start("code", false);
-        if code_spans.is_empty() {
-          self.write("null");
-        } else {
-          self.write("'");
-          self.gen_spans(code_spans);
-          self.write("'");
-        }
-        self.write_jsx_end(false);
-        self.write_jsx_end(false);
+        self.write_text(value);
+        self.write_jsx_end();
       }
-      BlockToken::JSX(element) => {
-        self.gen_jsx_element(element);
+      Node::InlineMath { value, .. } => {
+        self.gen_math("span", "math math-inline", value);
       }
-      _ => {
+      Node::Break { .. } => {
+        self.write_element_start("br", false);
+        self.write_call_end();
+      }
+      Node::Link {
+        url,
+        title,
+        children,
+        ..
+      } => {
+        self.gen_link(url, title, children);
+      }
+      Node::Image {
+        url, title, alt, ..
+      } => {
+        self.gen_image(url, title, alt);
+      }
+      Node::LinkReference {
+        identifier,
+        label,
+        reference_type,
+        children,
+        ..
+      } => {
+        self.gen_link_reference(identifier, label, *reference_type, children);
+      }
+      Node::ImageReference {
+        identifier,
+        label,
+        reference_type,
+        alt,
+        ..
+      } => {
+        self.gen_image_reference(identifier, label, *reference_type, alt);
+      }
+      Node::Table {
+        align, children, ..
+      } => {
+        self.gen_table(align, children);
+      }
+      // rows and cells outside of a table
+      Node::TableRow { children, .. } => {
+        self.gen_table_row("td", &[], children, false);
+      }
+      Node::TableCell { children, .. } => {
+        self.gen_inlines_with_tag("td", children);
+      }
+      Node::FootnoteReference {
+        identifier, label, ..
+      } => {
+        let label = if label.is_empty() { identifier } else { label };
+        self.gen_footnote_reference(label);
+      }
+      // hoisted, rendered in the footnotes section or not rendered
+      Node::MdxjsEsm { .. }
+      | Node::Yaml { .. }
+      | Node::FootnoteDefinition { .. }
+      | Node::ThematicBreak { .. }
+      | Node::Html { .. }
+      | Node::Definition { .. } => {
         return;
       }
     }
     if jsxs {
-      self.write(",");
+      self.write_separator();
     }
   }
 }
+
+// nodes that end up in a children array
+fn rendered(nodes: &[Node]) -> Vec<&Node> {
+  nodes
+    .iter()
+    .filter(|node| {
+      !matches!(
+        node,
+        Node::MdxjsEsm { .. }
+          | Node::Yaml { .. }
+          | Node::FootnoteDefinition { .. }
+          | Node::ThematicBreak { .. }
+          | Node::Html { .. }
+          | Node::Definition { .. }
+      )
+    })
+    .collect()
+}
+
+// position of `offset` into the value of a node at `position`
+fn offset_position(position: &Option<Position>, value: &str, offset: usize) -> Option<Position> {
+  let position = position.as_ref()?;
+  let before = &value[..offset];
+  let column = match before.rfind('\n') {
+    Some(i) => offset - i,
+    None => position.start.column + offset,
+  };
+  let start = Point {
+    line: position.start.line + before.matches('\n').count(),
+    column,
+    offset: position.start.offset + offset,
+  };
+  Some(Position {
+    start,
+    end: position.end.clone(),
+  })
+}
+
+#[test]
+fn test_gen_math() {
+  let source = "Euler: $e^{i\\pi} + 1 = 0$ costs $$5$$ and `$x$`\n\n$$\n\\frac{a}{b}\n$$\n";
+  let options = CompileOptions {
+    math: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    "export default () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsxs(\"p\",{children:[\"Euler: \",_jsxRuntime.jsx(\"span\",{className:\"math math-inline\",children:\"e^{i\\\\pi} + 1 = 0\"}),\" costs \",_jsxRuntime.jsx(\"span\",{className:\"math math-inline\",children:\"5\"}),\" and \",_jsxRuntime.jsx(\"code\",{children:\"$x$\"}),]}),_jsxRuntime.jsx(\"div\",{className:\"math math-display\",children:\"\\\\frac{a}{b}\\n\"}),]})"
+  );
+  let result = crate::compile(source, &CompileOptions::default());
+  assert!(result
+    .code
+    .contains("\"Euler: $e^{i\\\\pi} + 1 = 0$ costs $$5$$ and \""));
+}
+
+#[test]
+fn test_gen_directives() {
+  let source = r#"::::tabs{.wide}
+:::tab[First *tab*]{#one title="One" data-x=1 hidden}
+Press :kbd[Ctrl]{.key} then :abbr[HTML]{title='Hyper Text'}.
+:::
+
+::youtube{id=abc}
+::::
+"#;
+  let options = CompileOptions {
+    directives: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsxs("tabs",{className:"wide",children:[_jsxRuntime.jsxs("tab",{id:"one",title:"One","data-x":"1",hidden:"",children:[_jsxRuntime.jsxs("p",{children:["First ",_jsxRuntime.jsx("em",{children:"tab"}),]}),_jsxRuntime.jsxs("p",{children:["Press ",_jsxRuntime.jsx("kbd",{className:"key",children:"Ctrl"})," then ",_jsxRuntime.jsx("abbr",{title:"Hyper Text",children:"HTML"}),".",]}),]}),_jsxRuntime.jsx("youtube",{id:"abc",children:null}),]})})"#
+  );
+}
+
+#[test]
+fn test_gen_alerts() {
+  let source = "> [!NOTE]\n> Useful *info*\n\n> [!warning]\n\n> [!NOPE]\n> x\n";
+  let mut options = CompileOptions {
+    alerts: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsx("blockquote",{"data-alert":"note",className:"markdown-alert markdown-alert-note",children:_jsxRuntime.jsxs("p",{children:["Useful ",_jsxRuntime.jsx("em",{children:"info"}),]})}),_jsxRuntime.jsx("blockquote",{"data-alert":"warning",className:"markdown-alert markdown-alert-warning",children:null}),_jsxRuntime.jsx("blockquote",{children:_jsxRuntime.jsx("p",{children:"[!NOPE]\nx"})}),]})"#
+  );
+  options.alert_component = Some("Callout".to_string());
+  let result = crate::compile("> [!TIP]\n> x\n", &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsx(Callout,{type:"tip",children:_jsxRuntime.jsx("p",{children:"x"})})})"#
+  );
+}
+
+#[test]
+fn test_gen_alert_markers() {
+  let options = CompileOptions {
+    alerts: true,
+    ..Default::default()
+  };
+  let tip = r#"_jsxRuntime.jsx("blockquote",{"data-alert":"tip",className:"markdown-alert markdown-alert-tip",children:_jsxRuntime.jsx("p",{children:"x"})})"#;
+  // spaces and tabs around the marker
+  for source in [
+    ">  [!TIP]  \n> x\n",
+    "> [!TIP]\t\n> x\n",
+    "> [!TIP] \r\n> x\r\n",
+  ] {
+    let code = crate::compile(source, &options).code;
+    assert!(code.contains(tip), "{:?}: {}", source, code);
+  }
+  // the marker has to be alone on its line
+  let code = crate::compile("> [!TIP] x\n", &options).code;
+  assert!(code.contains(r#"_jsxRuntime.jsx("blockquote",{children:"#));
+  // without the option it is text
+  let code = crate::compile("> [!TIP]\n> x\n", &CompileOptions::default()).code;
+  assert!(code.contains(r#"_jsxRuntime.jsx("p",{children:"[!TIP]\nx"})"#));
+}
+
+#[test]
+fn test_gen_task_list() {
+  let source = "- [ ] todo *now*\n- [x] done\n";
+  let mut options = CompileOptions {
+    task_lists: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsxs("ul",{className:"contains-task-list",children:[_jsxRuntime.jsx("li",{className:"task-list-item",children:_jsxRuntime.jsxs("p",{children:[_jsxRuntime.jsx("input",{type:"checkbox",disabled:true,checked:false})," ","todo ",_jsxRuntime.jsx("em",{children:"now"}),]})}),_jsxRuntime.jsx("li",{className:"task-list-item",children:_jsxRuntime.jsxs("p",{children:[_jsxRuntime.jsx("input",{type:"checkbox",disabled:true,checked:true})," ","done"]})}),]})})"#
+  );
+  options.checkbox_component = Some("Checkbox".to_string());
+  let result = crate::compile("- [x] done\n", &options);
+  assert!(result
+    .code
+    .contains(r#"_jsxRuntime.jsx(Checkbox,{checked:true})"#));
+  let result = crate::compile("- [x] done\n", &CompileOptions::default());
+  assert!(result.code.contains(r#""[x] done""#));
+}
+
+#[test]
+fn test_gen_ordered_list() {
+  let code = crate::compile("1. a\n2. b\n", &CompileOptions::default()).code;
+  assert_eq!(
+    code,
+    r#"export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsxs("ol",{children:[_jsxRuntime.jsx("li",{children:_jsxRuntime.jsx("p",{children:"a"})}),_jsxRuntime.jsx("li",{children:_jsxRuntime.jsx("p",{children:"b"})}),]})})"#
+  );
+  // a start other than 1 is kept
+  let code = crate::compile("3) a\n", &CompileOptions::default()).code;
+  assert!(code.contains(r#"_jsxRuntime.jsx("ol",{start:"3",children:_jsxRuntime.jsx("li","#));
+  let options = CompileOptions {
+    jsx: true,
+    ..Default::default()
+  };
+  let code = crate::compile("5. a\n", &options).code;
+  assert!(code.contains(r#"<ol start="5"><li>"#), "{}", code);
+  let code = crate::compile("- a\n", &options).code;
+  assert!(code.contains("<ul><li>"), "{}", code);
+}
+
+#[test]
+fn test_gen_task_list_markers() {
+  let options = CompileOptions {
+    task_lists: true,
+    ..Default::default()
+  };
+  // a marker without content is text of a plain item
+  for source in ["- [ ]\n", "- [x]   \n", "* [X]\t\n"] {
+    let code = crate::compile(source, &options).code;
+    assert!(!code.contains("task-list-item"), "{:?}: {}", source, code);
+  }
+  let code = crate::compile("- [ ]\n- [x] done\n", &options).code;
+  assert!(
+    code.contains(r#"_jsxRuntime.jsx("li",{children:_jsxRuntime.jsx("p",{children:"[ ]"})})"#)
+  );
+  assert!(code.contains(r#"_jsxRuntime.jsx("li",{className:"task-list-item","#));
+  // the marker needs a space or tab after it
+  let code = crate::compile("- [x]done\n- [ ]\tdone\n", &options).code;
+  assert!(code.contains(r#""[x]done""#));
+  assert!(
+    code.contains(r#"_jsxRuntime.jsx("input",{type:"checkbox",disabled:true,checked:false})"#)
+  );
+}
+
+#[test]
+fn test_gen_footnote_self_reference() {
+  let options = CompileOptions {
+    footnotes: true,
+    ..Default::default()
+  };
+  // every reference has a back reference, also one from inside the definition
+  for source in ["a[^1]\n\n[^1]: x[^1]\n", "a[^1]\n\n[^1]: - x[^1]\n"] {
+    let code = crate::compile(source, &options).code;
+    for id in ["user-content-fnref-1", "user-content-fnref-1-2"] {
+      assert!(code.contains(&format!(r#"id:"{}""#, id)), "{}", code);
+      assert!(code.contains(&format!(r##"href:"#{}""##, id)), "{}", code);
+    }
+    assert!(!code.contains("fnref-1-3"));
+  }
+  // without the option references and definitions are text
+  let code = crate::compile("a[^1]\n\n[^1]: x\n", &CompileOptions::default()).code;
+  assert!(!code.contains("data-footnote"));
+  assert!(code.contains("[^1]"));
+}
+
+#[test]
+fn test_gen_footnotes() {
+  let source = "A[^1] b[^Note] c[^1] [^none]\n\n[^note]: Two\n\n    More[^x]\n[^1]: One\n[^x]: X\n[^unused]: U\n";
+  let mut options = CompileOptions {
+    footnotes: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r##"export default () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsxs("p",{children:["A",_jsxRuntime.jsx("sup",{children:_jsxRuntime.jsx("a",{href:"#user-content-fn-1",id:"user-content-fnref-1","data-footnote-ref":"","aria-describedby":"user-content-footnote-label",children:"1"})})," b",_jsxRuntime.jsx("sup",{children:_jsxRuntime.jsx("a",{href:"#user-content-fn-note",id:"user-content-fnref-note","data-footnote-ref":"","aria-describedby":"user-content-footnote-label",children:"2"})})," c",_jsxRuntime.jsx("sup",{children:_jsxRuntime.jsx("a",{href:"#user-content-fn-1",id:"user-content-fnref-1-2","data-footnote-ref":"","aria-describedby":"user-content-footnote-label",children:"1"})})," ","[^none]",]}),_jsxRuntime.jsxs("section",{"data-footnotes":"",className:"footnotes",children:[_jsxRuntime.jsx("h2",{className:"sr-only",id:"user-content-footnote-label",children:"Footnotes"}),_jsxRuntime.jsxs("ol",{children:[_jsxRuntime.jsx("li",{id:"user-content-fn-1",children:_jsxRuntime.jsxs("p",{children:["One"," ",_jsxRuntime.jsx("a",{href:"#user-content-fnref-1","data-footnote-backref":"","aria-label":"Back to reference 1",className:"data-footnote-backref",children:"↩"})," ",_jsxRuntime.jsxs("a",{href:"#user-content-fnref-1-2","data-footnote-backref":"","aria-label":"Back to reference 1-2",className:"data-footnote-backref",children:["↩",_jsxRuntime.jsx("sup",{children:"2"}),]}),]})}),_jsxRuntime.jsxs("li",{id:"user-content-fn-note",children:[_jsxRuntime.jsx("p",{children:"Two"}),_jsxRuntime.jsxs("p",{children:["More",_jsxRuntime.jsx("sup",{children:_jsxRuntime.jsx("a",{href:"#user-content-fn-x",id:"user-content-fnref-x","data-footnote-ref":"","aria-describedby":"user-content-footnote-label",children:"3"})})," ",_jsxRuntime.jsx("a",{href:"#user-content-fnref-note","data-footnote-backref":"","aria-label":"Back to reference 2",className:"data-footnote-backref",children:"↩"}),]}),]}),_jsxRuntime.jsx("li",{id:"user-content-fn-x",children:_jsxRuntime.jsxs("p",{children:["X"," ",_jsxRuntime.jsx("a",{href:"#user-content-fnref-x","data-footnote-backref":"","aria-label":"Back to reference 3",className:"data-footnote-backref",children:"↩"}),]})}),]}),]}),]})"##
+  );
+  options.footnote_id_prefix = "doc-".to_string();
+  let result = crate::compile("x[^a]\n\n[^a]: y\n", &options);
+  assert!(result
+    .code
+    .contains(r##"href:"#doc-fn-a",id:"doc-fnref-a""##));
+  let result = crate::compile("x[^a]\n\n[^a]: y\n", &CompileOptions::default());
+  assert!(result.code.contains(r#""x[^a]""#));
+}
+
+#[test]
+fn test_gen_autolink_literals() {
+  let source = "Visit www.commonmark.org, mail foo@bar.baz or <https://x.io>\n";
+  let options = CompileOptions {
+    autolink_literals: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsxs("p",{children:["Visit ",_jsxRuntime.jsx("a",{href:"http://www.commonmark.org",children:"www.commonmark.org"}),", mail ",_jsxRuntime.jsx("a",{href:"mailto:foo@bar.baz",children:"foo@bar.baz"})," or ",_jsxRuntime.jsx("a",{href:"https://x.io",children:"https://x.io"}),]})})"#
+  );
+  let result = crate::compile(source, &CompileOptions::default());
+  assert!(result
+    .code
+    .contains(r#""Visit www.commonmark.org, mail foo@bar.baz or ""#));
+}
+
+#[test]
+fn test_gen_emphasis() {
+  let cases = [
+    (
+      "***a** b*",
+      r#"_jsxRuntime.jsxs("em",{children:[_jsxRuntime.jsx("strong",{children:"a"})," b",]})"#,
+    ),
+    (
+      "**a*b***",
+      r#"_jsxRuntime.jsxs("strong",{children:["a",_jsxRuntime.jsx("em",{children:"b"}),]})"#,
+    ),
+    (
+      "*foo**bar**baz*",
+      r#"_jsxRuntime.jsxs("em",{children:["foo",_jsxRuntime.jsx("strong",{children:"bar"}),"baz",]})"#,
+    ),
+    (
+      "***a***",
+      r#"_jsxRuntime.jsx("em",{children:_jsxRuntime.jsx("strong",{children:"a"})})"#,
+    ),
+    (
+      "*foo*bar**",
+      r#"[_jsxRuntime.jsx("em",{children:"foo"}),"bar**",]"#,
+    ),
+    ("foo_bar_baz", r#""foo_bar_baz""#),
+    ("_foo_bar_", r#"_jsxRuntime.jsx("em",{children:"foo_bar"})"#),
+    ("a*«b»*", r#""a*«b»*""#),
+    ("*«b»*", r#"_jsxRuntime.jsx("em",{children:"«b»"})"#),
+    (
+      "~~a~~ ~b~ ~~~c~~~",
+      r#"[_jsxRuntime.jsx("del",{children:"a"})," ",_jsxRuntime.jsx("del",{children:"b"})," ~~~c~~~",]"#,
+    ),
+  ];
+  for (source, children) in cases.iter() {
+    let result = crate::compile(source, &CompileOptions::default());
+    let p = if children.starts_with('[') {
+      format!("_jsxRuntime.jsxs(\"p\",{{children:{}}})", children)
+    } else {
+      format!("_jsxRuntime.jsx(\"p\",{{children:{}}})", children)
+    };
+    assert_eq!(
+      result.code,
+      format!(
+        "export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{{children:{}}})",
+        p
+      ),
+      "{}",
+      source
+    );
+  }
+}
+
+#[test]
+fn test_gen_links() {
+  // CommonMark spec examples, paragraph children
+  let cases = [
+    (
+      r#"[link](/uri "title")"#,
+      r#"_jsxRuntime.jsx("a",{href:"/uri",title:"title",children:"link"})"#,
+    ),
+    (
+      "[link]()",
+      r#"_jsxRuntime.jsx("a",{href:"",children:"link"})"#,
+    ),
+    (
+      "[link](<>)",
+      r#"_jsxRuntime.jsx("a",{href:"",children:"link"})"#,
+    ),
+    ("[link](/my uri)", r#""[link](/my uri)""#),
+    (
+      "[link](foo(and(bar)))",
+      r#"_jsxRuntime.jsx("a",{href:"foo(and(bar))",children:"link"})"#,
+    ),
+    (
+      r"[link](\(foo\))",
+      r#"_jsxRuntime.jsx("a",{href:"(foo)",children:"link"})"#,
+    ),
+    (
+      "[link](   /uri\n  'title'  )",
+      r#"_jsxRuntime.jsx("a",{href:"/uri",title:"title",children:"link"})"#,
+    ),
+    (
+      "[link [foo [bar]]](/uri)",
+      r#"_jsxRuntime.jsx("a",{href:"/uri",children:"link [foo [bar]]"})"#,
+    ),
+    (
+      "[foo [bar](/uri)](/uri)",
+      r#"["[foo ",_jsxRuntime.jsx("a",{href:"/uri",children:"bar"}),"](/uri)",]"#,
+    ),
+    (
+      "*[foo*](/uri)",
+      r#"["*",_jsxRuntime.jsx("a",{href:"/uri",children:"foo*"}),]"#,
+    ),
+    (
+      "[foo`](/uri)`",
+      r#"["[foo",_jsxRuntime.jsx("code",{children:"](/uri)"}),]"#,
+    ),
+    (
+      "[foo<http://a.b/?q=](uri)>",
+      r#"["[foo",_jsxRuntime.jsx("a",{href:"http://a.b/?q=](uri)",children:"http://a.b/?q=](uri)"}),]"#,
+    ),
+    (
+      "![foo [bar](/url)](/img \"T\")",
+      r#"_jsxRuntime.jsx("img",{src:"/img",alt:"foo bar",title:"T"})"#,
+    ),
+    ("[a](b 'c", r#""[a](b 'c""#),
+  ];
+  for (source, children) in cases.iter() {
+    let result = crate::compile(source, &CompileOptions::default());
+    let p = if children.starts_with('[') {
+      format!("_jsxRuntime.jsxs(\"p\",{{children:{}}})", children)
+    } else {
+      format!("_jsxRuntime.jsx(\"p\",{{children:{}}})", children)
+    };
+    assert_eq!(
+      result.code,
+      format!(
+        "export default () => _jsxRuntime.jsx(_jsxRuntime.Fragment,{{children:{}}})",
+        p
+      ),
+      "{}",
+      source
+    );
+  }
+}
+
+#[test]
+fn test_gen_mdast() {
+  // a remark-style tree, without positions and with fields the compiler ignores
+  let ast = r#"{
+    "type": "root",
+    "children": [
+      {"type": "mdxjsEsm", "value": "import Chart from './chart'", "data": {"estree": null}},
+      {"type": "heading", "depth": 2, "children": [{"type": "text", "value": "Say \"hi\""}]},
+      {"type": "mdxJsxFlowElement", "name": "Chart", "attributes": [
+        {"type": "mdxJsxAttribute", "name": "kind", "value": "bar"},
+        {"type": "mdxJsxAttribute", "name": "data", "value": {"type": "mdxJsxAttributeValueExpression", "value": "[1, 2]"}}
+      ], "children": []},
+      {"type": "paragraph", "children": [
+        {"type": "text", "value": "See "},
+        {"type": "link", "url": "/docs", "title": null, "children": [{"type": "inlineCode", "value": "docs"}]}
+      ]}
+    ]
+  }"#;
+  let ast: Node = serde_json::from_str(ast).unwrap();
+  let options = CompileOptions::default();
+  let result = crate::compile_ast(&ast, &options);
+  assert_eq!(
+    result.code,
+    r#"import Chart from './chart'
+export default () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsx("h2",{children:"Say \"hi\""}),_jsxRuntime.jsx(Chart,{kind:"bar",data:[1, 2],children:null}),_jsxRuntime.jsxs("p",{children:["See ",_jsxRuntime.jsx("a",{href:"/docs",children:_jsxRuntime.jsx("code",{children:"docs"})}),]}),]})"#
+  );
+  assert_eq!(result.toc[0].text, "Say \"hi\"");
+
+  let source = "import Chart from './chart'\n\n## Say \"hi\"\n\n<Chart kind=\"bar\" data={[1, 2]} />\n\nSee [`docs`](/docs)\n";
+  assert_eq!(result.code, crate::compile(source, &options).code);
+}
+
+#[test]
+fn test_gen_remark_nodes() {
+  // nodes of plain markdown trees, which this parser doesn't build
+  let ast = r#"{
+    "type": "root",
+    "children": [
+      {"type": "definition", "identifier": "docs", "label": "Docs", "url": "/docs", "title": null},
+      {"type": "html", "value": "<hr>", "position": {
+        "start": {"line": 2, "column": 1, "offset": 20},
+        "end": {"line": 2, "column": 5, "offset": 24}
+      }},
+      {"type": "paragraph", "children": [
+        {"type": "linkReference", "identifier": "docs", "label": "Docs", "referenceType": "shortcut", "children": [{"type": "text", "value": "Docs"}]},
+        {"type": "linkReference", "identifier": "nope", "label": "Nope", "referenceType": "full", "children": [{"type": "text", "value": "x"}]},
+        {"type": "imageReference", "identifier": "docs", "referenceType": "collapsed", "alt": null},
+        {"type": "image", "url": "a.png", "alt": null},
+        {"type": "textDirective", "name": "abbr", "attributes": {"title": "T", "hidden": null}, "children": []}
+      ]},
+      {"type": "table", "align": ["left", null], "children": [
+        {"type": "tableRow", "children": [
+          {"type": "tableCell", "children": [{"type": "text", "value": "a"}]},
+          {"type": "tableCell", "children": []}
+        ]},
+        {"type": "tableRow", "children": [
+          {"type": "tableCell", "children": [{"type": "text", "value": "1"}]},
+          {"type": "tableCell", "children": [{"type": "text", "value": "2"}]}
+        ]}
+      ]}
+    ]
+  }"#;
+  let ast: Node = serde_json::from_str(ast).unwrap();
+  let options = CompileOptions {
+    jsx: true,
+    ..Default::default()
+  };
+  let result = crate::compile_ast(&ast, &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => <><p><a href="/docs">Docs</a><>[x][Nope]</><img src="/docs" /><img src="a.png" /><abbr title="T" /></p><table><thead><tr><th align="left">a</th><th /></tr></thead><tbody><tr><td align="left">1</td><td>2</td></tr></tbody></table></>"#
+  );
+  // raw HTML is left out with a diagnostic
+  assert_eq!(result.diagnostics.len(), 1);
+  assert_eq!(
+    result.diagnostics[0].position.as_ref().unwrap().start.line,
+    2
+  );
+}
+
+#[test]
+fn test_gen_empty_inlines() {
+  let result = crate::compile(
+    "#
+
+[](/a)
+",
+    &CompileOptions::default(),
+  );
+  assert_eq!(
+    result.code,
+    r#"export default () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsx("h1",{children:null}),_jsxRuntime.jsx("p",{children:_jsxRuntime.jsx("a",{href:"/a",children:null})}),]})"#
+  );
+}
+
+#[test]
+fn test_gen_development() {
+  let source = "# Hi *there*\n\n<Note>hello</Note>\n";
+  let mut options = CompileOptions {
+    development: true,
+    filepath: Some("docs/intro.mdx".to_string()),
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r#"import * as _jsxRuntime from "react/jsx-dev-runtime";
+export default () => _jsxRuntime.jsxDEV(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsxDEV("h1",{children:["Hi ",_jsxRuntime.jsxDEV("em",{children:"there"},undefined,false,{fileName:"docs/intro.mdx",lineNumber:1,columnNumber:6},this),]},undefined,true,{fileName:"docs/intro.mdx",lineNumber:1,columnNumber:1},this),_jsxRuntime.jsxDEV(Note,{children:"hello",},undefined,false,{fileName:"docs/intro.mdx",lineNumber:3,columnNumber:1},this),]},undefined,true,undefined,this)"#
+  );
+  options.filepath = None;
+  let result = crate::compile("a  \nb\n", &options);
+  assert!(result.code.contains(
+    r#"_jsxRuntime.jsxDEV("br",{},undefined,false,{fileName:"input.mdx",lineNumber:1,columnNumber:2},this)"#
+  ));
+  // columns count UTF-16 code units, the emphasis starts at the 4th after the emoji
+  let result = crate::compile("😀 *a*\n", &options);
+  assert!(result.code.contains(
+    r#"_jsxRuntime.jsxDEV("em",{children:"a"},undefined,false,{fileName:"input.mdx",lineNumber:1,columnNumber:4},this)"#
+  ));
+  // the runtime comes from the arguments of a function body
+  options.output_format = OutputFormat::FunctionBody;
+  assert_eq!(
+    crate::compile("# a\n", &options).code,
+    r#"const _jsxRuntime = arguments[0];
+const MDXContent = () => _jsxRuntime.jsxDEV(_jsxRuntime.Fragment,{children:_jsxRuntime.jsxDEV("h1",{children:"a"},undefined,false,{fileName:"input.mdx",lineNumber:1,columnNumber:1},this)},undefined,false,undefined,this);
+return {default: MDXContent};"#
+  );
+  // JSX output has no calls to locate
+  options.output_format = OutputFormat::Program;
+  options.jsx = true;
+  assert_eq!(
+    crate::compile("# a\n", &options).code,
+    "export default () => <><h1>a</h1></>"
+  );
+}
+
+#[test]
+fn test_gen_output_formats() {
+  // ESM anywhere in a remark tree, the imports go first
+  let ast = r#"{
+    "type": "root",
+    "children": [
+      {"type": "mdxjsEsm", "value": "import Chart, {data as rows} from './chart'\nexport const meta = {title: 'Hi'}"},
+      {"type": "mdxJsxFlowElement", "name": "Chart", "attributes": [
+        {"type": "mdxJsxAttribute", "name": "rows", "value": {"type": "mdxJsxAttributeValueExpression", "value": "rows"}}
+      ], "children": []},
+      {"type": "mdxjsEsm", "value": "import * as icons from 'icons';\nexport {icons as set}\nexport * from './shared'"}
+    ]
+  }"#;
+  let ast: Node = serde_json::from_str(ast).unwrap();
+  let content = r#"_jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsx(Chart,{rows:rows,children:null})})"#;
+  let mut options = CompileOptions::default();
+  assert_eq!(
+    crate::compile_ast(&ast, &options).code,
+    format!(
+      "import Chart, {{data as rows}} from './chart'
+import * as icons from 'icons';
+export const meta = {{title: 'Hi'}}
+export {{icons as set}}
+export * from './shared'
+export default () => {}",
+      content
+    )
+  );
+  options.output_format = OutputFormat::CommonJs;
+  assert_eq!(
+    crate::compile_ast(&ast, &options).code,
+    format!(
+      "const {{default: Chart, data: rows}} = require('./chart');
+const icons = require('icons');
+const meta = {{title: 'Hi'}}
+const _reexport0 = require('./shared');
+const MDXContent = () => {};
+module.exports = {{meta, set: icons, ..._reexport0, default: MDXContent}};",
+      content
+    )
+  );
+  options.output_format = OutputFormat::FunctionBody;
+  options.toc_export = true;
+  assert_eq!(
+    crate::compile_ast(&ast, &options).code,
+    format!(
+      "const _jsxRuntime = arguments[0];
+const {{default: Chart, data: rows}} = await import('./chart');
+const icons = await import('icons');
+const meta = {{title: 'Hi'}}
+const _reexport0 = await import('./shared');
+const MDXContent = () => {};
+return {{meta, set: icons, ..._reexport0, default: MDXContent, toc: []}};",
+      content
+    )
+  );
+}
+
+#[test]
+fn test_gen_esm_layout_and_attributes() {
+  let ast = r#"{
+    "type": "root",
+    "children": [
+      {"type": "mdxjsEsm", "value": "import data from './a.json' with {type: 'json'}\nexport default Layout\nexport default Other\nimport defer * as lazy from './lazy'", "position": {
+        "start": {"line": 1, "column": 1, "offset": 0},
+        "end": {"line": 4, "column": 37, "offset": 128}
+      }},
+      {"type": "paragraph", "children": [{"type": "text", "value": "Hi"}]}
+    ]
+  }"#;
+  let ast: Node = serde_json::from_str(ast).unwrap();
+  let content = r#"(props = {}) => _jsxRuntime.jsx(MDXLayout,{...props,children:_jsxRuntime.jsx(_jsxRuntime.Fragment,{children:_jsxRuntime.jsx("p",{children:"Hi"})})})"#;
+  let mut options = CompileOptions::default();
+  let result = crate::compile_ast(&ast, &options);
+  assert_eq!(
+    result.code,
+    format!(
+      "import data from './a.json' with {{type: 'json'}}
+const MDXLayout = Layout;
+import defer * as lazy from './lazy'
+export default {}",
+      content
+    )
+  );
+  // the second layout is left out, in every format
+  let lines: Vec<usize> = result
+    .diagnostics
+    .iter()
+    .map(|diagnostic| diagnostic.position.as_ref().unwrap().start.line)
+    .collect();
+  assert_eq!(lines, [3]);
+
+  options.output_format = OutputFormat::CommonJs;
+  let result = crate::compile_ast(&ast, &options);
+  assert_eq!(
+    result.code,
+    format!(
+      "const {{default: data}} = require('./a.json');
+const MDXLayout = Layout;
+const MDXContent = {};
+module.exports = {{default: MDXContent}};",
+      content
+    )
+  );
+  // so is what can't be rewritten
+  let lines: Vec<usize> = result
+    .diagnostics
+    .iter()
+    .map(|diagnostic| diagnostic.position.as_ref().unwrap().start.line)
+    .collect();
+  assert_eq!(lines, [3, 4]);
+  assert!(result.diagnostics[1].message.contains("`commonjs`"));
+
+  options.output_format = OutputFormat::FunctionBody;
+  let result = crate::compile_ast(&ast, &options);
+  assert_eq!(
+    result.code,
+    format!(
+      "const _jsxRuntime = arguments[0];
+const {{default: data}} = await import('./a.json', {{with: {{type: 'json'}}}});
+const MDXLayout = Layout;
+const MDXContent = {};
+return {{default: MDXContent}};",
+      content
+    )
+  );
+  assert_eq!(result.diagnostics.len(), 2);
+
+  // the layout from MDX, in JSX
+  let options = CompileOptions {
+    jsx: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    crate::compile("export default Layout;\n\n# Hi\n", &options).code,
+    "const MDXLayout = Layout;\nexport default (props = {}) => <MDXLayout {...props}><><h1>Hi</h1></></MDXLayout>"
+  );
+  let source = "import data from './a.json' with {type: 'json'}\n\n# Hi\n";
+  assert!(crate::compile(source, &options)
+    .code
+    .starts_with("import data from './a.json' with {type: 'json'}\n"));
+}
+
+#[test]
+fn test_gen_classic_runtime() {
+  let source = "# Hi *there*\n\n<Note kind=\"tip\">hello</Note>\n\nA  \nb ![c](d.png)\n";
+  let mut options = CompileOptions {
+    jsx_runtime: JsxRuntime::Classic,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(
+    result.code,
+    r#"export default () => React.createElement(React.Fragment,{},React.createElement("h1",{},"Hi ",React.createElement("em",{},"there"),),React.createElement(Note,{kind:"tip",},"hello",),React.createElement("p",{},"A",React.createElement("br",{}),"b ",React.createElement("img",{src:"d.png",alt:"c"}),),)"#
+  );
+  options.pragma = "h".to_string();
+  options.pragma_frag = "Fragment".to_string();
+  options.development = true;
+  let result = crate::compile("", &options);
+  assert_eq!(result.code, "export default () => h(Fragment,{})");
+  // the pragma is in scope, a function body doesn't take a runtime from its arguments
+  let mut options = CompileOptions {
+    jsx_runtime: JsxRuntime::Classic,
+    output_format: OutputFormat::FunctionBody,
+    ..Default::default()
+  };
+  assert_eq!(
+    crate::compile("<X {...p} b />\n", &options).code,
+    r#"const MDXContent = () => React.createElement(React.Fragment,{},React.createElement(X,{...p,b:true,}));
+return {default: MDXContent};"#
+  );
+  options.output_format = OutputFormat::Program;
+  options.provider_import_source = Some("@mdx-js/react".to_string());
+  assert_eq!(
+    crate::compile("# a\n", &options).code,
+    r#"import {useMDXComponents as _provideComponents} from "@mdx-js/react";
+export default (props = {}) => {
+const _components = {h1: "h1", ..._provideComponents(), ...props.components};
+return React.createElement(React.Fragment,{},React.createElement(_components.h1,{},"a"));
+}"#
+  );
+  // Preact's classic calls end after the props too
+  options.target = TargetKind::Preact;
+  options.provider_import_source = None;
+  assert!(crate::compile("<X />\n", &options)
+    .code
+    .contains("React.createElement(React.Fragment,{},React.createElement(X,{}))"));
+}
+
+#[test]
+fn test_gen_jsx() {
+  let source = "import Chart from './chart'\n\n# Hi *there* <b> & {a}\n\n<Chart kind=\"bar\" data={[1, 2]} />\n\nA  \nb ![c & \"d\"](d.png) `x`\n\n```js\nlet a = 1;\n```\n";
+  let options = CompileOptions {
+    jsx: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    crate::compile(source, &options).code,
+    r#"import Chart from './chart'
+export default () => <><h1>Hi <em>there</em> &lt;b&gt; &amp; &#123;a&#125;</h1><Chart kind="bar" data={[1, 2]} /><p>A<br />b <img src="d.png" alt={"c & \"d\""} /> <code>x</code></p><pre><code>{"let a = 1;\n"}</code></pre></>"#
+  );
+  let ast = r#"{
+    "type": "root",
+    "children": [
+      {"type": "mdxJsxFlowElement", "name": "Card", "attributes": [
+        {"type": "mdxJsxExpressionAttribute", "value": "...props"},
+        {"type": "mdxJsxAttribute", "name": "open", "value": null}
+      ], "children": [{"type": "mdxFlowExpression", "value": "title"}]},
+      {"type": "mdxJsxFlowElement", "name": null, "attributes": [], "children": []}
+    ]
+  }"#;
+  let ast: Node = serde_json::from_str(ast).unwrap();
+  assert_eq!(
+    crate::compile_ast(&ast, &options).code,
+    "export default () => <><Card {...props} open={true}>{title}</Card><></></>"
+  );
+}
+
+#[test]
+fn test_gen_jsx_extensions() {
+  let options = CompileOptions::builder()
+    .jsx(true)
+    .math(true)
+    .directives(true)
+    .task_lists(true)
+    .alerts(true)
+    .build();
+  let cases = [
+    // attribute values that aren't plain JSX strings become expressions
+    (
+      ":abbr[x]{title=\"a {c} <d> & 'e'\"}\n",
+      r#"<p><abbr title={"a {c} <d> & 'e'"}>x</abbr></p>"#,
+    ),
+    (
+      ":abbr[x]{title='say \"hi\"'}\n",
+      r#"<p><abbr title={"say \"hi\""}>x</abbr></p>"#,
+    ),
+    (
+      "$a<b$\n",
+      r#"<p><span className="math math-inline">a&lt;b</span></p>"#,
+    ),
+    (
+      "- [ ] t\n",
+      r#"<ul className="contains-task-list"><li className="task-list-item"><p><input type="checkbox" disabled={true} checked={false} /> t</p></li></ul>"#,
+    ),
+    (
+      "> [!TIP]\n> x\n",
+      r#"<blockquote data-alert="tip" className="markdown-alert markdown-alert-tip"><p>x</p></blockquote>"#,
+    ),
+    ("a {b} c\n", "<p>a &#123;b&#125; c</p>"),
+    ("#\n", "<h1 />"),
+  ];
+  for (source, jsx) in cases {
+    assert_eq!(
+      crate::compile(source, &options).code,
+      format!("export default () => <>{}</>", jsx),
+      "{}",
+      source
+    );
+  }
+}
+
+#[test]
+fn test_gen_targets() {
+  let source = "# Hi *there*\n\n<Chart kind=\"bar\" onClick={go}>hello</Chart>\n\n> [!NOTE]\n> n\n";
+  let mut options = CompileOptions {
+    alerts: true,
+    target: TargetKind::Preact,
+    ..Default::default()
+  };
+  assert_eq!(
+    crate::compile(source, &options).code,
+    r#"export default () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsxs("h1",{children:["Hi ",_jsxRuntime.jsx("em",{children:"there"}),]}),_jsxRuntime.jsx(Chart,{kind:"bar",onClick:go,children:"hello",}),_jsxRuntime.jsx("blockquote",{"data-alert":"note",class:"markdown-alert markdown-alert-note",children:_jsxRuntime.jsx("p",{children:"n"})}),]})"#
+  );
+  options.target = TargetKind::Vue;
+  assert_eq!(
+    crate::compile(source, &options).code,
+    r#"export default () => _jsxRuntime.h(_jsxRuntime.Fragment,{},[_jsxRuntime.h("h1",{},["Hi ",_jsxRuntime.h("em",{},["there"]),]),_jsxRuntime.h(Chart,{kind:"bar",onClick:go,},{default:()=>["hello",]}),_jsxRuntime.h("blockquote",{"data-alert":"note",class:"markdown-alert markdown-alert-note",},[_jsxRuntime.h("p",{},["n"])]),])"#
+  );
+  options.target = TargetKind::Solid;
+  assert_eq!(
+    crate::compile(source, &options).code,
+    r#"export default () => [_jsxRuntime.h("h1",{},"Hi ",_jsxRuntime.h("em",{},"there"),),_jsxRuntime.h(Chart,{kind:"bar",onClick:go,},"hello",),_jsxRuntime.h("blockquote",{"data-alert":"note",class:"markdown-alert markdown-alert-note",},_jsxRuntime.h("p",{},"n")),]"#
+  );
+  options.target = TargetKind::Hyperscript;
+  assert_eq!(
+    crate::compile(source, &options).code,
+    r#"export default () => [_jsxRuntime.h("h1",{},["Hi ",_jsxRuntime.h("em",{},["there"]),]),_jsxRuntime.h(Chart,{kind:"bar",onclick:go,},["hello",]),_jsxRuntime.h("blockquote",{"data-alert":"note",className:"markdown-alert markdown-alert-note",},[_jsxRuntime.h("p",{},["n"])]),]"#
+  );
+  // prop names follow the target in JSX output
+  options.target = TargetKind::Solid;
+  options.jsx = true;
+  assert_eq!(
+    crate::compile("> [!NOTE]\n", &options).code,
+    r#"export default () => <><blockquote data-alert="note" class="markdown-alert markdown-alert-note" /></>"#
+  );
+}
+
+#[test]
+fn test_gen_provided_intrinsics() {
+  let options = CompileOptions::builder()
+    .jsx(true)
+    .provider_import_source("@mdx-js/react")
+    .build();
+  // markdown elements take the defaults, `components: {h1: Title}` replaces the heading
+  assert_eq!(
+    crate::compile("# Hi\n\n<h1>*a*</h1>\n\nb *c*\n", &options).code,
+    r#"import {useMDXComponents as _provideComponents} from "@mdx-js/react";
+export default (props = {}) => {
+const _components = {h1: "h1", p: "p", em: "em", ..._provideComponents(), ...props.components};
+return <><_components.h1>Hi</_components.h1><h1>*a*</h1><_components.p>b <_components.em>c</_components.em></_components.p></>;
+}"#
+  );
+  // without a provider the elements stay as they are
+  let options = CompileOptions::builder().jsx(true).build();
+  assert_eq!(
+    crate::compile("# Hi\n", &options).code,
+    "export default () => <><h1>Hi</h1></>"
+  );
+}
+
+#[test]
+fn test_gen_target_runtimes() {
+  let source = "<X />\n\n<label for=\"a\" class=\"b\" data-x=\"c\" />\n";
+  let cases = [
+    (
+      TargetKind::Preact,
+      "preact",
+      r#"const _jsxRuntime = require("preact/jsx-runtime");
+const MDXContent = () => _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsx(X,{children:null}),_jsxRuntime.jsx("label",{for:"a",class:"b","data-x":"c",children:null}),]});"#,
+    ),
+    (
+      TargetKind::Vue,
+      "vue",
+      r#"const _jsxRuntime = require("vue");
+const MDXContent = () => _jsxRuntime.h(_jsxRuntime.Fragment,{},[_jsxRuntime.h(X,{},{default:()=>[]}),_jsxRuntime.h("label",{for:"a",class:"b","data-x":"c",},[]),]);"#,
+    ),
+    (
+      TargetKind::Solid,
+      "solid-js/h",
+      r#"const _jsxRuntime = require("solid-js/h");
+const MDXContent = () => [_jsxRuntime.h(X,{},),_jsxRuntime.h("label",{for:"a",class:"b","data-x":"c",},),];"#,
+    ),
+  ];
+  // attributes written as JSX keep their names, reserved words and dashes included
+  for (target, import_source, code) in cases {
+    let options = CompileOptions::builder()
+      .target(target)
+      .jsx_import_source(import_source)
+      .output_format(OutputFormat::CommonJs)
+      .build();
+    assert_eq!(
+      crate::compile(source, &options).code,
+      format!("{}\nmodule.exports = {{default: MDXContent}};", code),
+      "{:?}",
+      target
+    );
+  }
+  // development calls come from the target's dev runtime
+  let options = CompileOptions::builder()
+    .target(TargetKind::Preact)
+    .development(true)
+    .build();
+  assert!(crate::compile("# a\n", &options)
+    .code
+    .starts_with("import * as _jsxRuntime from \"preact/jsx-dev-runtime\";\n"));
+}
+
+#[test]
+fn test_gen_import_sources() {
+  let source = "import Chart from './chart'\n\n<Chart />\n\n<Note>hello</Note>\n";
+  let options = CompileOptions::builder()
+    .target(TargetKind::Preact)
+    .jsx_import_source("preact")
+    .provider_import_source("@mdx-js/preact")
+    .build();
+  assert_eq!(
+    crate::compile(source, &options).code,
+    r#"import * as _jsxRuntime from "preact/jsx-runtime";
+import {useMDXComponents as _provideComponents} from "@mdx-js/preact";
+import Chart from './chart'
+export default (props = {}) => {
+const _components = {..._provideComponents(), ...props.components};
+return _jsxRuntime.jsxs(_jsxRuntime.Fragment,{children:[_jsxRuntime.jsx(Chart,{children:null}),_jsxRuntime.jsx(_components.Note,{children:"hello",}),]});
+}"#
+  );
+  let options = CompileOptions::builder()
+    .output_format(OutputFormat::CommonJs)
+    .development(true)
+    .build();
+  assert!(crate::compile(source, &options)
+    .code
+    .starts_with("const _jsxRuntime = require(\"react/jsx-dev-runtime\");\n"));
+}
@@ -0,0 +1,177 @@
+use crate::inline::{special_bytes, SpecialBytes};
+use crate::mdast::Node;
+use crate::options::CompileOptions;
+use crate::{generate, parse_with, CompileResult};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+thread_local! {
+  // code is generated here and copied out, the capacity is kept for the next document
+  static CODE: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+// compiles many documents with the same options, the inline lookup table derived from
+// them is built once. It can be shared between threads, each keeps its own code buffer
+#[derive(Debug, Clone)]
+pub struct Compiler {
+  options: CompileOptions,
+  special_bytes: SpecialBytes,
+}
+
+impl Compiler {
+  pub fn new(options: CompileOptions) -> Self {
+    Compiler {
+      special_bytes: special_bytes(&options),
+      options,
+    }
+  }
+
+  pub fn options(&self) -> &CompileOptions {
+    &self.options
+  }
+
+  pub fn compile(&self, source: &str) -> CompileResult {
+    let (ast, diagnostics) = parse_with(source, &self.options, &self.special_bytes);
+    CODE.with(|buffer| {
+      let code = std::mem::take(&mut *buffer.borrow_mut());
+      let mut result = generate(&ast, Some(source), &self.options, diagnostics, code);
+      let copy = result.code.as_str().to_owned();
+      let mut code = std::mem::replace(&mut result.code, copy);
+      code.clear();
+      *buffer.borrow_mut() = code;
+      result
+    })
+  }
+
+  // results in the order of the sources, compiled on as many threads as there are cores
+  pub fn compile_many<S: AsRef<str> + Sync>(&self, sources: &[S]) -> Vec<CompileResult> {
+    self
+      .compile_many_until(sources, &AtomicBool::new(false))
+      .unwrap_or_default()
+  }
+
+  // `compile_many` that stops before the next document once `cancelled` is set, `None` then
+  pub fn compile_many_until<S: AsRef<str> + Sync>(
+    &self,
+    sources: &[S],
+    cancelled: &AtomicBool,
+  ) -> Option<Vec<CompileResult>> {
+    let threads = thread::available_parallelism()
+      .map_or(1, usize::from)
+      .min(sources.len());
+    if threads <= 1 {
+      let mut results = vec![];
+      for source in sources {
+        if cancelled.load(Ordering::Relaxed) {
+          return None;
+        }
+        results.push(self.compile(source.as_ref()));
+      }
+      return Some(results);
+    }
+    // workers take the next source until none are left
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<CompileResult>> = sources.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+      let workers: Vec<_> = (0..threads)
+        .map(|_| {
+          scope.spawn(|| {
+            let mut compiled = vec![];
+            while !cancelled.load(Ordering::Relaxed) {
+              let index = next.fetch_add(1, Ordering::Relaxed);
+              let Some(source) = sources.get(index) else {
+                break;
+              };
+              compiled.push((index, self.compile(source.as_ref())));
+            }
+            compiled
+          })
+        })
+        .collect();
+      for worker in workers {
+        for (index, result) in worker.join().unwrap() {
+          results[index] = Some(result);
+        }
+      }
+    });
+    results.into_iter().collect()
+  }
+
+  pub fn compile_ast(&self, ast: &Node) -> CompileResult {
+    generate(ast, None, &self.options, vec![], String::new())
+  }
+
+  pub fn parse_to_ast(&self, source: &str) -> Node {
+    parse_with(source, &self.options, &self.special_bytes).0
+  }
+}
+
+impl Default for Compiler {
+  fn default() -> Self {
+    Compiler::new(CompileOptions::default())
+  }
+}
+
+#[test]
+fn test_compiler() {
+  fn assert_send_sync<T: Send + Sync>() {}
+  assert_send_sync::<Compiler>();
+
+  let options = CompileOptions::builder()
+    .math(true)
+    .directives(true)
+    .toc_export(true)
+    .build();
+  let compiler = Compiler::new(options.clone());
+  let sources = [
+    "# Title\n\nsome *emphasis* and $x^2$\n",
+    ":abbr[HTML]{title=\"HyperText\"}\n\n## Second\n",
+    "- a\n- b\n\n<Chart data={[1, 2]} />\n",
+  ];
+  for source in sources {
+    assert_eq!(
+      compiler.compile(source).code,
+      crate::compile(source, &options).code
+    );
+  }
+  let many: Vec<_> = compiler
+    .compile_many(&sources)
+    .into_iter()
+    .map(|result| result.code)
+    .collect();
+  let expected: Vec<_> = sources
+    .iter()
+    .map(|source| crate::compile(source, &options).code)
+    .collect();
+  assert_eq!(many, expected);
+  assert!(compiler.compile_many::<&str>(&[]).is_empty());
+  // a cancelled batch gives nothing, not the documents done so far
+  let cancelled = AtomicBool::new(true);
+  assert!(compiler.compile_many_until(&sources, &cancelled).is_none());
+
+  // the buffer keeps the capacity of a large document, the next result is only its size
+  let large = compiler.compile(&sources[0].repeat(100)).code;
+  let small = compiler.compile(sources[1]).code;
+  assert_eq!(small.capacity(), small.len());
+  assert!(CODE.with(|buffer| buffer.borrow().capacity()) >= large.len());
+
+  // the same compiler from several threads, the buffers of one don't leak into another
+  let compiler = &compiler;
+  std::thread::scope(|scope| {
+    let handles: Vec<_> = sources
+      .iter()
+      .map(|source| {
+        scope.spawn(move || {
+          (0..4)
+            .map(|_| compiler.compile(source).code)
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect();
+    for (handle, source) in handles.into_iter().zip(sources) {
+      let expected = crate::compile(source, &options).code;
+      assert!(handle.join().unwrap().iter().all(|code| *code == expected));
+    }
+  });
+}
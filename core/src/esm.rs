@@ -0,0 +1,528 @@
+// import/export statements of the ESM blocks, enough of them understood to
+// rewrite them for the CommonJS and function body output formats
+#[derive(Debug, PartialEq, Eq)]
+pub struct EsmStatement<'a> {
+  // the statement, from its first character up to and with its `;`
+  pub text: &'a str,
+  // of the text in the ESM block
+  pub offset: usize,
+  pub kind: EsmKind<'a>,
+  // `{type: "json"}` of `with {type: "json"}` after the source
+  pub attributes: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EsmKind<'a> {
+  // `import a, {b, c as d} from "e"`, `import * as f from "e"` and `import "e"`;
+  // sources keep their quotes
+  Import {
+    default: Option<&'a str>,
+    namespace: Option<&'a str>,
+    // imported, local
+    named: Vec<(&'a str, &'a str)>,
+    source: &'a str,
+  },
+  // `export const a = 1`, `export function b() {}`, the declaration and its names
+  ExportDeclaration {
+    declaration: &'a str,
+    names: Vec<&'a str>,
+  },
+  // `export {a, b as c}` with an optional `from "d"`
+  ExportNamed {
+    // local, exported
+    specifiers: Vec<(&'a str, &'a str)>,
+    source: Option<&'a str>,
+  },
+  // `export * from "a"` and `export * as b from "a"`
+  ExportAll {
+    name: Option<&'a str>,
+    source: &'a str,
+  },
+  // `export default Layout`, the layout wrapping the content; without the `;`
+  ExportDefault {
+    expression: &'a str,
+  },
+  // anything else, left as it is
+  Other,
+}
+
+impl<'a> EsmKind<'a> {
+  // names the statement declares in the module
+  pub fn bindings(&self) -> Vec<&'a str> {
+    match self {
+      EsmKind::Import {
+        default,
+        namespace,
+        named,
+        ..
+      } => default
+        .iter()
+        .chain(namespace)
+        .copied()
+        .chain(named.iter().map(|(_, local)| *local))
+        .collect(),
+      EsmKind::ExportDeclaration { names, .. } => names.clone(),
+      _ => vec![],
+    }
+  }
+
+  // the module loaded, with its quotes
+  pub fn source(&self) -> Option<&'a str> {
+    match self {
+      EsmKind::Import { source, .. } | EsmKind::ExportAll { source, .. } => Some(source),
+      EsmKind::ExportNamed { source, .. } => *source,
+      _ => None,
+    }
+  }
+}
+
+// pairs of the names before and after `as`
+type Specifiers<'a> = Vec<(&'a str, &'a str)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+  Word(&'a str),
+  // with its quotes
+  Str(&'a str),
+  Punct(char),
+}
+
+pub fn parse_esm(value: &str) -> Vec<EsmStatement<'_>> {
+  split_statements(value)
+    .into_iter()
+    .map(|(offset, text)| {
+      let kind = statement_kind(text);
+      let attributes = kind
+        .source()
+        .and_then(|source| import_attributes(text, source));
+      EsmStatement {
+        text,
+        offset,
+        kind,
+        attributes,
+      }
+    })
+    .collect()
+}
+
+// the braces after `with` or `assert`, `source` is a slice of `text`
+fn import_attributes<'a>(text: &'a str, source: &str) -> Option<&'a str> {
+  let end = source.as_ptr() as usize - text.as_ptr() as usize + source.len();
+  let rest = text[end..].trim_start();
+  let rest = rest
+    .strip_prefix("with")
+    .or_else(|| rest.strip_prefix("assert"))?;
+  Some(
+    rest
+      .trim_end_matches(|ch: char| ch == ';' || ch.is_whitespace())
+      .trim_start(),
+  )
+}
+
+fn is_word_char(ch: char) -> bool {
+  ch.is_alphanumeric() || ch == '_' || ch == '$'
+}
+
+// length of a string, template or comment starting at `rest`, none for other code
+fn skip_literal(rest: &str) -> Option<usize> {
+  let mut chars = rest.char_indices();
+  let (_, first) = chars.next()?;
+  match first {
+    '"' | '\'' | '`' => {
+      let mut escaped = false;
+      for (i, ch) in chars {
+        if escaped {
+          escaped = false;
+        } else if ch == '\\' {
+          escaped = true;
+        } else if ch == first {
+          return Some(i + 1);
+        } else if ch == '\n' && first != '`' {
+          return Some(i);
+        }
+      }
+      Some(rest.len())
+    }
+    '/' if rest.starts_with("//") => Some(rest.find('\n').unwrap_or(rest.len())),
+    '/' if rest.starts_with("/*") => Some(rest[2..].find("*/").map_or(rest.len(), |end| end + 4)),
+    _ => None,
+  }
+}
+
+// a statement ends with a `;` outside of brackets, or before a line starting
+// with `import`/`export` when semicolons are left out
+fn split_statements(value: &str) -> Vec<(usize, &str)> {
+  let mut statements = vec![];
+  let mut start = None;
+  let mut depth = 0usize;
+  let mut i = 0;
+  while i < value.len() {
+    let rest = &value[i..];
+    let ch = rest.chars().next().unwrap();
+    if start.is_none() {
+      if ch.is_whitespace() {
+        i += ch.len_utf8();
+        continue;
+      }
+      start = Some(i);
+    }
+    if let Some(size) = skip_literal(rest) {
+      i += size;
+      continue;
+    }
+    match ch {
+      '(' | '[' | '{' => depth += 1,
+      ')' | ']' | '}' => depth = depth.saturating_sub(1),
+      ';' if depth == 0 => {
+        let statement_start = start.take().unwrap();
+        statements.push((statement_start, &value[statement_start..=i]));
+      }
+      '\n' if depth == 0 => {
+        let next = rest.trim_start();
+        if starts_with_keyword(next, "import") || starts_with_keyword(next, "export") {
+          let statement_start = start.take().unwrap();
+          statements.push((statement_start, value[statement_start..i].trim_end()));
+        }
+      }
+      _ => {}
+    }
+    i += ch.len_utf8();
+  }
+  if let Some(start) = start {
+    let text = value[start..].trim_end();
+    if !text.is_empty() {
+      statements.push((start, text));
+    }
+  }
+  statements
+}
+
+fn starts_with_keyword(text: &str, keyword: &str) -> bool {
+  text.starts_with(keyword)
+    && !text[keyword.len()..]
+      .chars()
+      .next()
+      .is_some_and(is_word_char)
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < text.len() {
+    let rest = &text[i..];
+    let ch = rest.chars().next().unwrap();
+    if ch.is_whitespace() {
+      i += ch.len_utf8();
+    } else if let Some(size) = skip_literal(rest) {
+      if !rest.starts_with('/') {
+        tokens.push(Token::Str(&rest[..size]));
+      }
+      i += size;
+    } else if is_word_char(ch) {
+      let size = rest.find(|ch| !is_word_char(ch)).unwrap_or(rest.len());
+      tokens.push(Token::Word(&rest[..size]));
+      i += size;
+    } else {
+      tokens.push(Token::Punct(ch));
+      i += ch.len_utf8();
+    }
+  }
+  tokens
+}
+
+fn statement_kind(text: &str) -> EsmKind<'_> {
+  let tokens = tokenize(text);
+  let kind = match tokens.first() {
+    Some(Token::Word("import")) => import_kind(&tokens[1..]),
+    Some(Token::Word("export")) => export_kind(text, &tokens[1..]),
+    _ => None,
+  };
+  kind.unwrap_or(EsmKind::Other)
+}
+
+// `from "a"` and what may follow the source
+fn from_source<'a>(tokens: &[Token<'a>]) -> Option<&'a str> {
+  match tokens {
+    [Token::Word("from"), Token::Str(source), rest @ ..] if is_source_end(rest) => Some(source),
+    _ => None,
+  }
+}
+
+// optional import attributes, `with {type: "json"}`, and `;` closing the statement
+fn is_source_end(tokens: &[Token]) -> bool {
+  let tokens = match tokens {
+    [Token::Word("with" | "assert"), Token::Punct('{'), rest @ ..] => {
+      match rest.iter().position(|token| *token == Token::Punct('}')) {
+        Some(end) => &rest[end + 1..],
+        None => return false,
+      }
+    }
+    _ => tokens,
+  };
+  matches!(tokens, [] | [Token::Punct(';')])
+}
+
+// `{a, b as c}` and the tokens after `}`
+fn specifiers<'a, 't>(tokens: &'t [Token<'a>]) -> Option<(Specifiers<'a>, &'t [Token<'a>])> {
+  let mut tokens = match tokens.first() {
+    Some(Token::Punct('{')) => &tokens[1..],
+    _ => return None,
+  };
+  let mut specifiers = vec![];
+  loop {
+    match tokens {
+      [Token::Punct('}'), rest @ ..] => return Some((specifiers, rest)),
+      [Token::Word(name) | Token::Str(name), Token::Word("as"), Token::Word(alias), rest @ ..] => {
+        specifiers.push((*name, *alias));
+        tokens = rest;
+      }
+      [Token::Word(name), rest @ ..] => {
+        specifiers.push((*name, *name));
+        tokens = rest;
+      }
+      _ => return None,
+    }
+    match tokens.first() {
+      Some(Token::Punct(',')) => tokens = &tokens[1..],
+      Some(Token::Punct('}')) => {}
+      _ => return None,
+    }
+  }
+}
+
+fn import_kind<'a>(tokens: &[Token<'a>]) -> Option<EsmKind<'a>> {
+  let mut tokens = tokens;
+  if let [Token::Str(source), rest @ ..] = tokens {
+    if !is_source_end(rest) {
+      return None;
+    }
+    return Some(EsmKind::Import {
+      default: None,
+      namespace: None,
+      named: vec![],
+      source,
+    });
+  }
+  let mut default = None;
+  if let [Token::Word(name), rest @ ..] = tokens {
+    if *name != "from" || matches!(rest.first(), Some(Token::Word("from"))) {
+      default = Some(*name);
+      tokens = match rest {
+        [Token::Punct(','), rest @ ..] => rest,
+        [Token::Word("from"), ..] => rest,
+        // e.g. `import defer * as a from "b"`
+        _ => return None,
+      };
+    }
+  }
+  let mut namespace = None;
+  let mut named = vec![];
+  if let [Token::Punct('*'), Token::Word("as"), Token::Word(name), rest @ ..] = tokens {
+    namespace = Some(*name);
+    tokens = rest;
+  } else if let Some((specifiers, rest)) = specifiers(tokens) {
+    named = specifiers;
+    tokens = rest;
+  }
+  let source = from_source(tokens)?;
+  Some(EsmKind::Import {
+    default,
+    namespace,
+    named,
+    source,
+  })
+}
+
+fn export_kind<'a>(text: &'a str, tokens: &[Token<'a>]) -> Option<EsmKind<'a>> {
+  match tokens {
+    [Token::Word("const" | "let" | "var"), ..] => Some(EsmKind::ExportDeclaration {
+      declaration: declaration_text(text),
+      names: declarator_names(&tokens[1..]),
+    }),
+    [Token::Word("function" | "class"), Token::Word(name), ..]
+    | [Token::Word("function"), Token::Punct('*'), Token::Word(name), ..]
+    | [Token::Word("async"), Token::Word("function"), Token::Word(name), ..]
+    | [Token::Word("async"), Token::Word("function"), Token::Punct('*'), Token::Word(name), ..] => {
+      Some(EsmKind::ExportDeclaration {
+        declaration: declaration_text(text),
+        names: vec![name],
+      })
+    }
+    [Token::Punct('*'), Token::Word("as"), Token::Word(name), rest @ ..] => {
+      Some(EsmKind::ExportAll {
+        name: Some(name),
+        source: from_source(rest)?,
+      })
+    }
+    [Token::Punct('*'), rest @ ..] => Some(EsmKind::ExportAll {
+      name: None,
+      source: from_source(rest)?,
+    }),
+    [Token::Word("default"), ..] => Some(EsmKind::ExportDefault {
+      expression: declaration_text(text)["default".len()..]
+        .trim()
+        .trim_end_matches(';')
+        .trim_end(),
+    }),
+    [Token::Punct('{'), ..] => {
+      let (specifiers, rest) = specifiers(tokens)?;
+      let source = match rest {
+        [] | [Token::Punct(';')] => None,
+        _ => Some(from_source(rest)?),
+      };
+      Some(EsmKind::ExportNamed { specifiers, source })
+    }
+    _ => None,
+  }
+}
+
+// the statement without its `export` keyword
+fn declaration_text(text: &str) -> &str {
+  text["export".len()..].trim_start()
+}
+
+// bound names of `a = 1, {b, c: d} = e, [f = g]`: words starting a declarator or
+// inside a pattern, but not keys, defaults or initializers
+fn declarator_names<'a>(tokens: &[Token<'a>]) -> Vec<&'a str> {
+  let mut names = vec![];
+  let mut depth = 0usize;
+  // in a pattern or at the start of a declarator
+  let mut binding = true;
+  for (i, token) in tokens.iter().enumerate() {
+    match token {
+      Token::Punct('{' | '[' | '(') => depth += 1,
+      Token::Punct('}' | ']' | ')') => depth = depth.saturating_sub(1),
+      Token::Punct('=') => binding = false,
+      Token::Punct(',') if !binding && depth == 0 => binding = true,
+      Token::Punct(',') if binding => {}
+      Token::Word(word) if binding => {
+        let next = tokens.get(i + 1);
+        if !matches!(next, Some(Token::Punct(':'))) {
+          names.push(*word);
+        }
+        if depth == 0 {
+          binding = false;
+        }
+      }
+      _ => {}
+    }
+  }
+  names
+}
+
+#[test]
+fn test_parse_esm() {
+  let value = "import a, {b, c as d} from './e'\nimport * as f from \"f\";import 'g'\nexport const h = {i: 1}, j = [1, 2]\nexport const {k, l: m} = n;\nexport function o() {}\nexport {p, q as r} from './s'\nexport * from './t'\nexport default u";
+  let statements: Vec<(&str, EsmKind)> = parse_esm(value)
+    .into_iter()
+    .map(|statement| (statement.text, statement.kind))
+    .collect();
+  assert_eq!(
+    statements,
+    vec![
+      (
+        "import a, {b, c as d} from './e'",
+        EsmKind::Import {
+          default: Some("a"),
+          namespace: None,
+          named: vec![("b", "b"), ("c", "d")],
+          source: "'./e'"
+        }
+      ),
+      (
+        "import * as f from \"f\";",
+        EsmKind::Import {
+          default: None,
+          namespace: Some("f"),
+          named: vec![],
+          source: "\"f\""
+        }
+      ),
+      (
+        "import 'g'",
+        EsmKind::Import {
+          default: None,
+          namespace: None,
+          named: vec![],
+          source: "'g'"
+        }
+      ),
+      (
+        "export const h = {i: 1}, j = [1, 2]",
+        EsmKind::ExportDeclaration {
+          declaration: "const h = {i: 1}, j = [1, 2]",
+          names: vec!["h", "j"]
+        }
+      ),
+      (
+        "export const {k, l: m} = n;",
+        EsmKind::ExportDeclaration {
+          declaration: "const {k, l: m} = n;",
+          names: vec!["k", "m"]
+        }
+      ),
+      (
+        "export function o() {}",
+        EsmKind::ExportDeclaration {
+          declaration: "function o() {}",
+          names: vec!["o"]
+        }
+      ),
+      (
+        "export {p, q as r} from './s'",
+        EsmKind::ExportNamed {
+          specifiers: vec![("p", "p"), ("q", "r")],
+          source: Some("'./s'")
+        }
+      ),
+      (
+        "export * from './t'",
+        EsmKind::ExportAll {
+          name: None,
+          source: "'./t'"
+        }
+      ),
+      (
+        "export default u",
+        EsmKind::ExportDefault { expression: "u" }
+      ),
+    ]
+  );
+}
+
+#[test]
+fn test_parse_esm_attributes() {
+  let value = "import a from './a.json' with {type: 'json'};
+import './b.css' assert {type: 'css'}
+export {c} from './c.json' with {type: 'json'}
+export default function Layout() {};
+export enum D {}";
+  let statements: Vec<(&str, Option<&str>)> = parse_esm(value)
+    .iter()
+    .map(|statement| (statement.kind.source().unwrap_or(""), statement.attributes))
+    .collect();
+  assert_eq!(
+    statements,
+    vec![
+      ("'./a.json'", Some("{type: 'json'}")),
+      ("'./b.css'", Some("{type: 'css'}")),
+      ("'./c.json'", Some("{type: 'json'}")),
+      ("", None),
+      ("", None),
+    ]
+  );
+  let kinds: Vec<EsmKind> = parse_esm(value)
+    .into_iter()
+    .skip(3)
+    .map(|statement| statement.kind)
+    .collect();
+  assert_eq!(
+    kinds,
+    vec![
+      EsmKind::ExportDefault {
+        expression: "function Layout() {}"
+      },
+      EsmKind::Other
+    ]
+  );
+}
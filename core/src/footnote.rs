@@ -0,0 +1,130 @@
+use crate::mdast::*;
+use std::collections::HashMap;
+
+pub struct Footnotes<'a> {
+  definitions: HashMap<String, &'a Node>,
+  // label, reference count; in order of first reference
+  pub called: Vec<(String, usize)>,
+  indexes: HashMap<String, usize>,
+}
+
+impl<'a> Footnotes<'a> {
+  pub fn new() -> Self {
+    Self {
+      definitions: HashMap::new(),
+      called: vec![],
+      indexes: HashMap::new(),
+    }
+  }
+
+  // number and reference count of a call, none for undefined labels
+  pub fn call(&mut self, label: &str) -> Option<(usize, usize)> {
+    let label = normalize_label(label);
+    if !self.definitions.contains_key(&label) {
+      return None;
+    }
+    if let Some(&index) = self.indexes.get(&label) {
+      self.called[index].1 += 1;
+      return Some((index + 1, self.called[index].1));
+    }
+    self.indexes.insert(label.clone(), self.called.len());
+    self.called.push((label, 1));
+    Some((self.called.len(), 1))
+  }
+
+  pub fn has_definitions(&self) -> bool {
+    !self.definitions.is_empty()
+  }
+
+  pub fn definition(&self, label: &str) -> &'a Node {
+    self.definitions[label]
+  }
+}
+
+// url and title of the link definitions and the footnote definitions of a tree, in one
+// pass. The first definition of a label wins
+pub fn collect_definitions<'a>(
+  nodes: &'a [Node],
+) -> (
+  Footnotes<'a>,
+  HashMap<String, (&'a str, &'a Option<String>)>,
+) {
+  let mut footnotes = Footnotes::new();
+  let mut links = HashMap::new();
+  push_definitions(nodes, true, &mut footnotes, &mut links);
+  (footnotes, links)
+}
+
+// footnote definitions are only looked for in block containers
+fn push_definitions<'a>(
+  nodes: &'a [Node],
+  containers: bool,
+  footnotes: &mut Footnotes<'a>,
+  links: &mut HashMap<String, (&'a str, &'a Option<String>)>,
+) {
+  for node in nodes {
+    match node {
+      Node::Definition {
+        identifier,
+        url,
+        title,
+        ..
+      } => {
+        links
+          .entry(normalize_label(identifier))
+          .or_insert((url.as_str(), title));
+      }
+      Node::FootnoteDefinition { identifier, .. } if containers => {
+        footnotes
+          .definitions
+          .entry(normalize_label(identifier))
+          .or_insert(node);
+      }
+      _ => {}
+    }
+    let containers = containers
+      && matches!(
+        node,
+        Node::FootnoteDefinition { .. }
+          | Node::Blockquote { .. }
+          | Node::List { .. }
+          | Node::ListItem { .. }
+          | Node::ContainerDirective { .. }
+      );
+    push_definitions(node.children(), containers, footnotes, links);
+  }
+}
+
+// href and aria label of the `i`th back reference to footnote `number`, from 1
+pub fn backref(prefix: &str, id: &str, number: usize, i: usize) -> (String, String) {
+  let mut href = format!("#{}fnref-{}", prefix, id);
+  let mut label = format!("Back to reference {}", number);
+  if i > 1 {
+    href.push_str(&format!("-{}", i));
+    label.push_str(&format!("-{}", i));
+  }
+  (href, label)
+}
+
+// labels match case-insensitively with collapsed whitespace
+pub fn normalize_label(label: &str) -> String {
+  label
+    .split_whitespace()
+    .collect::<Vec<&str>>()
+    .join(" ")
+    .to_lowercase()
+}
+
+// label as used in element ids
+pub fn footnote_id(label: &str) -> String {
+  label
+    .chars()
+    .map(|ch| {
+      if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+        ch
+      } else {
+        '-'
+      }
+    })
+    .collect()
+}
@@ -0,0 +1,1178 @@
+use crate::footnote::*;
+use crate::mdast::*;
+use crate::options::*;
+use crate::toc::*;
+use crate::token::*;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsxStrategy {
+  // JSX is left out, children included
+  Drop,
+  // lowercase tags become HTML elements, components go through their registered
+  // template and render only their children without one
+  Render,
+}
+
+impl JsxStrategy {
+  pub fn new(name: &str) -> Option<JsxStrategy> {
+    match name {
+      "drop" => Some(JsxStrategy::Drop),
+      "render" => Some(JsxStrategy::Render),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpressionStrategy {
+  // `{expressions}` and attributes with expression values are left out
+  Drop,
+  // the first expression fails the render
+  Error,
+}
+
+impl ExpressionStrategy {
+  pub fn new(name: &str) -> Option<ExpressionStrategy> {
+    match name {
+      "drop" => Some(ExpressionStrategy::Drop),
+      "error" => Some(ExpressionStrategy::Error),
+      _ => None,
+    }
+  }
+}
+
+// a JSX component handed to its template
+pub struct Component<'a> {
+  pub name: &'a str,
+  // literal attributes without quotes, `true` attributes have an empty value
+  pub props: Vec<(String, String)>,
+  // rendered HTML
+  pub children: String,
+}
+
+pub type ComponentTemplate = Box<dyn Fn(&Component) -> String + Send + Sync>;
+
+pub struct HtmlOptions {
+  pub jsx: JsxStrategy,
+  pub expressions: ExpressionStrategy,
+  templates: HashMap<String, ComponentTemplate>,
+}
+
+impl Default for HtmlOptions {
+  fn default() -> Self {
+    Self {
+      jsx: JsxStrategy::Render,
+      expressions: ExpressionStrategy::Drop,
+      templates: HashMap::new(),
+    }
+  }
+}
+
+impl HtmlOptions {
+  // the template returns the HTML that replaces the component
+  pub fn register_template<F>(&mut self, name: &str, template: F)
+  where
+    F: Fn(&Component) -> String + Send + Sync + 'static,
+  {
+    self.templates.insert(name.to_string(), Box::new(template));
+  }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct HtmlError {
+  pub message: String,
+  pub span: Span,
+}
+
+impl fmt::Display for HtmlError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} at {}..{}",
+      self.message, self.span.start, self.span.end
+    )
+  }
+}
+
+impl std::error::Error for HtmlError {}
+
+// CommonMark reference renderer output for the mdast tree codegen uses, JSX is
+// handled by `HtmlOptions`
+pub struct HtmlRenderer<'a> {
+  pub html: String,
+  options: &'a CompileOptions,
+  html_options: &'a HtmlOptions,
+  footnotes: Footnotes<'a>,
+  // url and title by label, for the references of trees from the host
+  definitions: HashMap<String, (&'a str, &'a Option<String>)>,
+  // heading ids, with the toc export
+  slugger: Slugger,
+  // elements nested in JSX stay on the line of their parent
+  jsx_depth: usize,
+  error: Option<HtmlError>,
+}
+
+impl<'a> HtmlRenderer<'a> {
+  pub fn new(options: &'a CompileOptions, html_options: &'a HtmlOptions) -> Self {
+    HtmlRenderer {
+      html: String::new(),
+      options,
+      html_options,
+      footnotes: Footnotes::new(),
+      definitions: HashMap::new(),
+      slugger: Slugger::default(),
+      jsx_depth: 0,
+      error: None,
+    }
+  }
+
+  fn write(&mut self, str: &str) {
+    self.html.push_str(str);
+  }
+
+  fn write_escaped(&mut self, str: &str) {
+    for ch in str.chars() {
+      match ch {
+        '&' => self.html.push_str("&amp;"),
+        '<' => self.html.push_str("&lt;"),
+        '>' => self.html.push_str("&gt;"),
+        '"' => self.html.push_str("&quot;"),
+        _ => self.html.push(ch),
+      }
+    }
+  }
+
+  // ` key="value"` for each attribute
+  fn write_attributes(&mut self, attributes: &[(&str, &str)]) {
+    for (key, value) in attributes {
+      self.write(" ");
+      self.write(key);
+      self.write("=\"");
+      self.write_escaped(value);
+      self.write("\"");
+    }
+  }
+
+  fn write_start_tag(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+    self.write("<");
+    self.write(tag);
+    self.write_attributes(attributes);
+    self.write(">");
+  }
+
+  fn write_end_tag(&mut self, tag: &str) {
+    self.write("</");
+    self.write(tag);
+    self.write(">");
+  }
+
+  // a line break unless at the start or after one
+  fn cr(&mut self) {
+    if !self.html.is_empty() && !self.html.ends_with('\n') {
+      self.html.push('\n');
+    }
+  }
+
+  pub fn render(&mut self, ast: &'a Node) -> Result<(), HtmlError> {
+    let children = match ast {
+      Node::Root { children, .. } => children.as_slice(),
+      _ => std::slice::from_ref(ast),
+    };
+    let (footnotes, definitions) = collect_definitions(children);
+    if self.options.footnotes {
+      self.footnotes = footnotes;
+    }
+    self.definitions = definitions;
+    self.render_nodes(children, false);
+    self.render_footnotes();
+    match self.error.take() {
+      Some(error) => Err(error),
+      None => Ok(()),
+    }
+  }
+
+  // `tight` leaves out the `p` of paragraphs, for the items of tight lists
+  fn render_nodes(&mut self, nodes: &[Node], tight: bool) {
+    for node in nodes {
+      self.render_node(node, tight);
+    }
+  }
+
+  fn render_block(&mut self, tag: &str, attributes: &[(&str, &str)], children: &[Node]) {
+    self.cr();
+    self.write_start_tag(tag, attributes);
+    self.cr();
+    self.render_nodes(children, false);
+    self.cr();
+    self.write_end_tag(tag);
+    self.cr();
+  }
+
+  fn render_with_tag(&mut self, tag: &str, attributes: &[(&str, &str)], children: &[Node]) {
+    self.write_start_tag(tag, attributes);
+    self.render_nodes(children, false);
+    self.write_end_tag(tag);
+  }
+
+  fn render_node(&mut self, node: &Node, tight: bool) {
+    match node {
+      Node::Root { children, .. } => {
+        self.render_nodes(children, false);
+      }
+      Node::Paragraph { children, .. } => {
+        if tight {
+          self.render_nodes(children, false);
+        } else {
+          self.cr();
+          self.render_with_tag("p", &[], children);
+          self.cr();
+        }
+      }
+      Node::Heading {
+        depth, children, ..
+      } => {
+        let tag = format!("h{}", depth);
+        // the same ids as codegen, which the toc entries link to
+        let slug = match self.options.toc_export {
+          true => Some(self.slugger.slug(&nodes_text(children))),
+          false => None,
+        };
+        let attributes: Vec<(&str, &str)> = slug.iter().map(|slug| ("id", slug.as_str())).collect();
+        self.cr();
+        self.render_with_tag(&tag, &attributes, children);
+        self.cr();
+      }
+      Node::ThematicBreak { .. } => {
+        self.cr();
+        self.write("<hr />");
+        self.cr();
+      }
+      Node::Blockquote { children, .. } => {
+        match self.options.alerts.then(|| alert(children)).flatten() {
+          Some((alert, children)) => self.render_alert(&alert, &children),
+          None => self.render_block("blockquote", &[], children),
+        }
+      }
+      Node::List {
+        ordered,
+        start,
+        spread,
+        children,
+        ..
+      } => {
+        let tag = if *ordered { "ol" } else { "ul" };
+        let start = start
+          .filter(|start| *ordered && *start != 1)
+          .map(|start| start.to_string());
+        let attributes: Vec<(&str, &str)> = start
+          .iter()
+          .map(|start| ("start", start.as_str()))
+          .collect();
+        self.cr();
+        self.write_start_tag(tag, &attributes);
+        self.cr();
+        for child in children {
+          self.render_node(child, !spread);
+        }
+        self.cr();
+        self.write_end_tag(tag);
+        self.cr();
+      }
+      Node::ListItem {
+        checked, children, ..
+      } => {
+        self.cr();
+        self.write("<li>");
+        if let Some(checked) = checked {
+          self.render_checkbox(*checked);
+        }
+        self.render_nodes(children, tight);
+        self.write("</li>");
+        self.cr();
+      }
+      Node::Code { lang, value, .. } => {
+        self.cr();
+        self.write("<pre><code");
+        if let Some(lang) = lang {
+          let class_name = format!("language-{}", lang);
+          self.write_attributes(&[("class", &class_name)]);
+        }
+        self.write(">");
+        self.write_final_line(value);
+        self.write("</code></pre>");
+        self.cr();
+      }
+      Node::Math { value, .. } => {
+        self.cr();
+        self.write("<div class=\"math math-display\">");
+        self.write_final_line(value);
+        self.write("</div>");
+        self.cr();
+      }
+      Node::ContainerDirective {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        let attributes = directive_attributes(attributes);
+        self.render_block(name, &attributes, children);
+      }
+      Node::LeafDirective {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        let attributes = directive_attributes(attributes);
+        self.cr();
+        self.render_with_tag(name, &attributes, children);
+        self.cr();
+      }
+      Node::TextDirective {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        let attributes = directive_attributes(attributes);
+        self.render_with_tag(name, &attributes, children);
+      }
+      Node::MdxJsxFlowElement {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        if self.jsx_depth > 0 {
+          self.render_jsx_element(name, attributes, children);
+        } else {
+          self.cr();
+          self.render_jsx_element(name, attributes, children);
+          self.cr();
+        }
+      }
+      Node::MdxJsxTextElement {
+        name,
+        attributes,
+        children,
+        ..
+      } => {
+        self.render_jsx_element(name, attributes, children);
+      }
+      Node::MdxFlowExpression { position, .. } | Node::MdxTextExpression { position, .. } => {
+        self.expression(position);
+      }
+      Node::Text { value, .. } => {
+        self.write_escaped(value);
+      }
+      Node::Emphasis { children, .. } => {
+        self.render_with_tag("em", &[], children);
+      }
+      Node::Strong { children, .. } => {
+        self.render_with_tag("strong", &[], children);
+      }
+      Node::Delete { children, .. } => {
+        self.render_with_tag("del", &[], children);
+      }
+      Node::InlineCode { value, .. } => {
+        self.write("<code>");
+        self.write_escaped(value);
+        self.write("</code>");
+      }
+      Node::InlineMath { value, .. } => {
+        self.write("<span class=\"math math-inline\">");
+        self.write_escaped(value);
+        self.write("</span>");
+      }
+      Node::Break { .. } => {
+        self.write("<br />\n");
+      }
+      Node::Link {
+        url,
+        title,
+        children,
+        ..
+      } => {
+        self.render_link(url, title, children);
+      }
+      Node::Image {
+        url, title, alt, ..
+      } => {
+        self.render_image(url, title, alt);
+      }
+      Node::LinkReference {
+        identifier,
+        label,
+        reference_type,
+        children,
+        ..
+      } => match self.definitions.get(&normalize_label(identifier)) {
+        Some(&(url, title)) => self.render_link(url, title, children),
+        None => {
+          self.write("[");
+          self.render_nodes(children, false);
+          self.write_escaped(&reference_type.suffix(identifier, label));
+        }
+      },
+      Node::ImageReference {
+        identifier,
+        label,
+        reference_type,
+        alt,
+        ..
+      } => match self.definitions.get(&normalize_label(identifier)) {
+        Some(&(url, title)) => self.render_image(url, title, alt),
+        None => {
+          self.write("![");
+          self.write_escaped(alt.as_deref().unwrap_or_default());
+          self.write_escaped(&reference_type.suffix(identifier, label));
+        }
+      },
+      Node::Table {
+        align, children, ..
+      } => {
+        self.render_table(align, children);
+      }
+      // rows and cells outside of a table
+      Node::TableRow { children, .. } => {
+        self.render_table_row("td", &[], children);
+      }
+      Node::TableCell { children, .. } => {
+        self.render_with_tag("td", &[], children);
+      }
+      Node::Html { value, .. } => {
+        self.write(value);
+      }
+      Node::FootnoteReference {
+        identifier, label, ..
+      } => {
+        let label = if label.is_empty() { identifier } else { label };
+        self.render_footnote_reference(label);
+      }
+      // not content, or rendered in the footnotes section
+      Node::MdxjsEsm { .. }
+      | Node::Yaml { .. }
+      | Node::FootnoteDefinition { .. }
+      | Node::Definition { .. } => {}
+    }
+  }
+
+  fn render_link(&mut self, url: &str, title: &Option<String>, children: &[Node]) {
+    self.write("<a href=\"");
+    self.write_url(url);
+    self.write("\"");
+    self.write_title(title);
+    self.write(">");
+    self.render_nodes(children, false);
+    self.write("</a>");
+  }
+
+  fn render_image(&mut self, url: &str, title: &Option<String>, alt: &Option<String>) {
+    self.write("<img src=\"");
+    self.write_url(url);
+    self.write("\"");
+    if let Some(alt) = alt {
+      self.write(" alt=\"");
+      self.write_escaped(alt);
+      self.write("\"");
+    }
+    self.write_title(title);
+    self.write(" />");
+  }
+
+  // GFM table, the `tbody` is left out without rows after the head
+  fn render_table(&mut self, align: &[Option<String>], rows: &[Node]) {
+    self.cr();
+    self.write("<table>\n");
+    if let Some((head, body)) = rows.split_first() {
+      self.write("<thead>\n");
+      self.render_table_row("th", align, head.children());
+      self.write("</thead>\n");
+      if !body.is_empty() {
+        self.write("<tbody>\n");
+        for row in body {
+          self.render_table_row("td", align, row.children());
+        }
+        self.write("</tbody>\n");
+      }
+    }
+    self.write("</table>\n");
+  }
+
+  fn render_table_row(&mut self, tag: &str, align: &[Option<String>], cells: &[Node]) {
+    self.cr();
+    self.write("<tr>\n");
+    for (i, cell) in cells.iter().enumerate() {
+      let attributes: Vec<(&str, &str)> = match align.get(i) {
+        Some(Some(align)) => vec![("align", align)],
+        _ => vec![],
+      };
+      self.render_with_tag(tag, &attributes, cell.children());
+      self.write("\n");
+    }
+    self.write("</tr>\n");
+  }
+
+  // code keeps its final line ending, an empty block has none
+  fn write_final_line(&mut self, value: &str) {
+    if !value.is_empty() {
+      self.write_escaped(value);
+      self.write("\n");
+    }
+  }
+
+  fn render_alert(&mut self, alert: &Alert, children: &[Node]) {
+    let kind = alert.to_str();
+    match &self.options.alert_component {
+      Some(component) => {
+        self.cr();
+        self.render_component(
+          component,
+          vec![("type".to_string(), kind.to_string())],
+          children,
+        );
+        self.cr();
+      }
+      None => {
+        let class_name = format!("markdown-alert markdown-alert-{}", kind);
+        self.render_block(
+          "blockquote",
+          &[("data-alert", kind), ("class", &class_name)],
+          children,
+        );
+      }
+    }
+  }
+
+  // a checked checkbox component gets an empty `checked` prop, like a `true` attribute
+  fn render_checkbox(&mut self, checked: bool) {
+    match &self.options.checkbox_component {
+      Some(component) => {
+        let props = match checked {
+          true => vec![("checked".to_string(), String::new())],
+          false => vec![],
+        };
+        self.render_component(component, props, &[]);
+        self.write(" ");
+      }
+      None if checked => self.write("<input checked=\"\" disabled=\"\" type=\"checkbox\"> "),
+      None => self.write("<input disabled=\"\" type=\"checkbox\"> "),
+    }
+  }
+
+  fn render_footnote_reference(&mut self, label: &str) {
+    let (number, count) = match self.footnotes.call(label) {
+      Some(call) => call,
+      None => {
+        self.write_escaped(&format!("[^{}]", label));
+        return;
+      }
+    };
+    let prefix = &self.options.footnote_id_prefix;
+    let id = footnote_id(&normalize_label(label));
+    let href = format!("#{}fn-{}", prefix, id);
+    let mut ref_id = format!("{}fnref-{}", prefix, id);
+    if count > 1 {
+      ref_id.push_str(&format!("-{}", count));
+    }
+    let described_by = format!("{}footnote-label", prefix);
+    self.write("<sup>");
+    self.write_start_tag(
+      "a",
+      &[
+        ("href", &href),
+        ("id", &ref_id),
+        ("data-footnote-ref", ""),
+        ("aria-describedby", &described_by),
+      ],
+    );
+    self.write(&number.to_string());
+    self.write("</a></sup>");
+  }
+
+  // `section` with an ordered list of the called footnotes, in order of first reference
+  fn render_footnotes(&mut self) {
+    if self.footnotes.called.is_empty() {
+      return;
+    }
+    let prefix = self.options.footnote_id_prefix.clone();
+    let label_id = format!("{}footnote-label", prefix);
+    self.cr();
+    self.write_start_tag("section", &[("data-footnotes", ""), ("class", "footnotes")]);
+    self.write_start_tag("h2", &[("class", "sr-only"), ("id", &label_id)]);
+    self.write("Footnotes</h2>\n<ol>\n");
+    // definitions may call further footnotes, which are appended while rendering
+    let mut index = 0;
+    while index < self.footnotes.called.len() {
+      self.render_footnote_item(&prefix, index);
+      index += 1;
+    }
+    self.write("</ol>\n</section>\n");
+  }
+
+  // back references go at the end of the last paragraph, or after the blocks without one
+  fn render_footnote_item(&mut self, prefix: &str, index: usize) {
+    let label = self.footnotes.called[index].0.clone();
+    let blocks = match self.footnotes.definition(&label) {
+      Node::FootnoteDefinition { children, .. } => children.as_slice(),
+      _ => &[],
+    };
+    let id = footnote_id(&label);
+    let item_id = format!("{}fn-{}", prefix, id);
+    self.write_start_tag("li", &[("id", &item_id)]);
+    self.write("\n");
+    match blocks.split_last() {
+      Some((Node::Paragraph { children, .. }, rest)) => {
+        self.render_nodes(rest, false);
+        self.cr();
+        self.write("<p>");
+        self.render_nodes(children, false);
+        self.write(" ");
+        // read after the body, which can reference its own footnote
+        let count = self.footnotes.called[index].1;
+        self.render_footnote_backrefs(prefix, &id, index + 1, count);
+        self.write("</p>");
+      }
+      _ => {
+        self.render_nodes(blocks, false);
+        self.cr();
+        let count = self.footnotes.called[index].1;
+        self.render_footnote_backrefs(prefix, &id, index + 1, count);
+      }
+    }
+    self.write("\n</li>\n");
+  }
+
+  // separated by spaces
+  fn render_footnote_backrefs(&mut self, prefix: &str, id: &str, number: usize, count: usize) {
+    for i in 1..=count {
+      let (href, label) = backref(prefix, id, number, i);
+      if i > 1 {
+        self.write(" ");
+      }
+      self.write_start_tag(
+        "a",
+        &[
+          ("href", &href),
+          ("data-footnote-backref", ""),
+          ("aria-label", &label),
+          ("class", "data-footnote-backref"),
+        ],
+      );
+      self.write("↩");
+      if i > 1 {
+        self.write(&format!("<sup>{}</sup>", i));
+      }
+      self.write("</a>");
+    }
+  }
+
+  fn render_jsx_element(
+    &mut self,
+    name: &Option<String>,
+    attributes: &[JsxAttribute],
+    children: &[Node],
+  ) {
+    self.jsx_depth += 1;
+    self.render_jsx_tag(name, attributes, children);
+    self.jsx_depth -= 1;
+  }
+
+  fn render_jsx_tag(
+    &mut self,
+    name: &Option<String>,
+    attributes: &[JsxAttribute],
+    children: &[Node],
+  ) {
+    let tag = match name {
+      Some(tag) if self.html_options.jsx == JsxStrategy::Render => tag,
+      // fragments and dropped JSX render only their children
+      _ => {
+        self.render_nodes(children, false);
+        return;
+      }
+    };
+    let props = self.jsx_props(attributes);
+    if !is_intrinsic_tag(tag) {
+      self.render_component(tag, props, children);
+      return;
+    }
+    self.write("<");
+    self.write(tag);
+    for (key, value) in props {
+      self.write(" ");
+      self.write(match key.as_str() {
+        "className" => "class",
+        "htmlFor" => "for",
+        key => key,
+      });
+      self.write("=\"");
+      self.write_escaped(&value);
+      self.write("\"");
+    }
+    if children.is_empty() && is_void_tag(tag) {
+      self.write(" />");
+      return;
+    }
+    self.write(">");
+    self.render_nodes(children, false);
+    self.write_end_tag(tag);
+  }
+
+  // a component goes through its registered template, only its children are
+  // rendered without one
+  fn render_component(&mut self, name: &str, props: Vec<(String, String)>, children: &[Node]) {
+    let html_options = self.html_options;
+    let template = match html_options.templates.get(name) {
+      Some(template) if html_options.jsx == JsxStrategy::Render => template,
+      _ => {
+        self.render_nodes(children, false);
+        return;
+      }
+    };
+    let html = std::mem::take(&mut self.html);
+    self.render_nodes(children, false);
+    let children = std::mem::replace(&mut self.html, html);
+    self.write(&template(&Component {
+      name,
+      props,
+      children,
+    }));
+  }
+
+  fn jsx_props(&mut self, attributes: &[JsxAttribute]) -> Vec<(String, String)> {
+    let mut props = vec![];
+    for attribute in attributes {
+      match attribute {
+        JsxAttribute::MdxJsxAttribute {
+          name, value: None, ..
+        } => {
+          props.push((name.clone(), String::new()));
+        }
+        JsxAttribute::MdxJsxAttribute {
+          name,
+          value: Some(JsxAttributeValue::Literal(value)),
+          ..
+        } => {
+          props.push((name.clone(), value.clone()));
+        }
+        JsxAttribute::MdxJsxAttribute {
+          value: Some(JsxAttributeValue::Expression(expression)),
+          ..
+        } => {
+          self.expression(&expression.position);
+        }
+        JsxAttribute::MdxJsxExpressionAttribute { position, .. } => {
+          self.expression(position);
+        }
+      }
+    }
+    props
+  }
+
+  // expressions have no HTML, they are dropped or fail the render
+  fn expression(&mut self, position: &Option<Position>) {
+    if self.html_options.expressions == ExpressionStrategy::Drop || self.error.is_some() {
+      return;
+    }
+    let span = match position {
+      Some(position) => Span {
+        start: position.start.offset,
+        end: position.end.offset,
+      },
+      None => Span { start: 0, end: 0 },
+    };
+    self.error = Some(HtmlError {
+      message: "expressions can't be rendered to HTML".to_string(),
+      span,
+    });
+  }
+
+  fn write_title(&mut self, title: &Option<String>) {
+    if let Some(title) = title {
+      self.write(" title=\"");
+      self.write_escaped(title);
+      self.write("\"");
+    }
+  }
+
+  // percent-encodes what is not allowed in a URL, existing escapes are kept
+  fn write_url(&mut self, url: &str) {
+    let bytes = url.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+      let is_escape = byte == b'%'
+        && bytes.len() > i + 2
+        && bytes[i + 1].is_ascii_hexdigit()
+        && bytes[i + 2].is_ascii_hexdigit();
+      match byte {
+        b'&' => self.write("&amp;"),
+        b'%' if is_escape => self.html.push('%'),
+        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => self.html.push(byte as char),
+        b';' | b'/' | b'?' | b':' | b'@' | b'=' | b'+' | b'$' | b',' | b'-' | b'_' | b'.'
+        | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' | b'#' => self.html.push(byte as char),
+        _ => self.write(&format!("%{:02X}", byte)),
+      }
+    }
+  }
+}
+
+// attributes without a value in the host's tree are left out
+fn directive_attributes(attributes: &[(String, Option<String>)]) -> Vec<(&str, &str)> {
+  attributes
+    .iter()
+    .filter_map(|(key, value)| Some((key.as_str(), value.as_deref()?)))
+    .collect()
+}
+
+fn is_intrinsic_tag(tag: &str) -> bool {
+  tag.starts_with(|ch: char| ch.is_ascii_lowercase()) && !tag.contains('.')
+}
+
+fn is_void_tag(tag: &str) -> bool {
+  matches!(
+    tag,
+    "area"
+      | "br"
+      | "col"
+      | "embed"
+      | "hr"
+      | "img"
+      | "input"
+      | "link"
+      | "meta"
+      | "source"
+      | "track"
+      | "wbr"
+  )
+}
+
+// loose when a blank line separates items, or blocks within an item
+pub fn is_tight_list(blocks: &[Token<BlockToken>]) -> bool {
+  let items: Vec<&Vec<Token<BlockToken>>> = blocks
+    .iter()
+    .filter_map(|block| match &block.value {
+      BlockToken::ListItem { blocks, .. } => Some(blocks),
+      _ => None,
+    })
+    .collect();
+  for (i, blocks) in items.iter().enumerate() {
+    let is_content = |block: &Token<BlockToken>| block.value != BlockToken::BlankLine;
+    let first = blocks.iter().position(is_content);
+    let last = blocks.iter().rposition(is_content);
+    for (j, block) in blocks.iter().enumerate() {
+      if is_content(block) || first.is_none_or(|first| j < first) {
+        continue;
+      }
+      if last.is_some_and(|last| j < last) || i + 1 < items.len() {
+        return false;
+      }
+    }
+  }
+  true
+}
+
+#[test]
+fn test_render_html_jsx() {
+  let source = "<div className=\"note\" hidden>Hi <Badge kind=\"new\">x &</Badge>{count}</div>\n\nSome *text* <Badge kind=\"old\"/>\n";
+  let options = CompileOptions::default();
+  let mut html_options = HtmlOptions::default();
+  assert_eq!(
+    crate::render_html(source, &options, &html_options).unwrap(),
+    "<div class=\"note\" hidden=\"\">Hi x &amp;</div>\n<p>Some <em>text</em> </p>\n"
+  );
+  html_options.register_template("Badge", |component| {
+    format!(
+      "<span data-kind=\"{}\">{}</span>",
+      component.props[0].1, component.children
+    )
+  });
+  assert_eq!(
+    crate::render_html(source, &options, &html_options).unwrap(),
+    "<div class=\"note\" hidden=\"\">Hi <span data-kind=\"new\">x &amp;</span></div>\n<p>Some <em>text</em> <span data-kind=\"old\"></span></p>\n"
+  );
+  html_options.jsx = JsxStrategy::Drop;
+  assert_eq!(
+    crate::render_html(source, &options, &html_options).unwrap(),
+    "Hi x &amp;\n<p>Some <em>text</em> </p>\n"
+  );
+  html_options.expressions = ExpressionStrategy::Error;
+  assert_eq!(
+    crate::render_html(source, &options, &html_options),
+    Err(HtmlError {
+      message: "expressions can't be rendered to HTML".to_string(),
+      span: Span { start: 62, end: 67 },
+    })
+  );
+}
+
+#[cfg(test)]
+fn render_with(source: &str, options: &CompileOptions) -> String {
+  crate::render_html(source, options, &HtmlOptions::default()).unwrap()
+}
+
+#[test]
+fn test_render_html_directives() {
+  let source = ":::note{#a .b key=\"v\"}\nHi *there*\n:::\n\n::leaf[label]{x=y}\n\nSome :abbr[HTML]{title=\"x\"}\n";
+  let options = CompileOptions {
+    directives: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<note id=\"a\" key=\"v\" class=\"b\">\n<p>Hi <em>there</em></p>\n</note>\n<leaf x=\"y\">label</leaf>\n<p>Some <abbr title=\"x\">HTML</abbr></p>\n"
+  );
+  assert_eq!(
+    render_with("::leaf\n", &CompileOptions::default()),
+    "<p>::leaf</p>\n"
+  );
+}
+
+#[test]
+fn test_render_html_alerts() {
+  let source = "> [!NOTE]\n> Useful\n";
+  let mut options = CompileOptions {
+    alerts: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<blockquote data-alert=\"note\" class=\"markdown-alert markdown-alert-note\">\n<p>Useful</p>\n</blockquote>\n"
+  );
+  options.alert_component = Some("Callout".to_string());
+  let mut html_options = HtmlOptions::default();
+  assert_eq!(
+    crate::render_html(source, &options, &html_options).unwrap(),
+    "<p>Useful</p>\n"
+  );
+  html_options.register_template("Callout", |component| {
+    format!(
+      "<aside class=\"{}\">{}</aside>",
+      component.props[0].1, component.children
+    )
+  });
+  assert_eq!(
+    crate::render_html(source, &options, &html_options).unwrap(),
+    "<aside class=\"note\"><p>Useful</p>\n</aside>\n"
+  );
+  assert_eq!(
+    render_with(source, &CompileOptions::default()),
+    "<blockquote>\n<p>[!NOTE]\nUseful</p>\n</blockquote>\n"
+  );
+}
+
+#[test]
+fn test_render_html_task_lists() {
+  let source = "- [ ] todo\n- [x] done\n";
+  let mut options = CompileOptions {
+    task_lists: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<ul>\n<li><input disabled=\"\" type=\"checkbox\"> todo</li>\n<li><input checked=\"\" disabled=\"\" type=\"checkbox\"> done</li>\n</ul>\n"
+  );
+  options.checkbox_component = Some("Checkbox".to_string());
+  let mut html_options = HtmlOptions::default();
+  html_options.register_template("Checkbox", |component| {
+    let checked = component.props.iter().any(|(key, _)| key == "checked");
+    format!("<x-check checked=\"{}\"></x-check>", checked)
+  });
+  assert_eq!(
+    crate::render_html(source, &options, &html_options).unwrap(),
+    "<ul>\n<li><x-check checked=\"false\"></x-check> todo</li>\n<li><x-check checked=\"true\"></x-check> done</li>\n</ul>\n"
+  );
+  assert_eq!(
+    render_with(source, &CompileOptions::default()),
+    "<ul>\n<li>[ ] todo</li>\n<li>[x] done</li>\n</ul>\n"
+  );
+}
+
+#[test]
+fn test_render_html_footnotes() {
+  let source = "a[^1] b[^n] c[^none]\n\n[^1]: x[^1]\n[^n]: - list\n";
+  let options = CompileOptions {
+    footnotes: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<p>a<sup><a href=\"#user-content-fn-1\" id=\"user-content-fnref-1\" data-footnote-ref=\"\" aria-describedby=\"user-content-footnote-label\">1</a></sup> b<sup><a href=\"#user-content-fn-n\" id=\"user-content-fnref-n\" data-footnote-ref=\"\" aria-describedby=\"user-content-footnote-label\">2</a></sup> c[^none]</p>
+<section data-footnotes=\"\" class=\"footnotes\"><h2 class=\"sr-only\" id=\"user-content-footnote-label\">Footnotes</h2>
+<ol>
+<li id=\"user-content-fn-1\">
+<p>x<sup><a href=\"#user-content-fn-1\" id=\"user-content-fnref-1-2\" data-footnote-ref=\"\" aria-describedby=\"user-content-footnote-label\">1</a></sup> <a href=\"#user-content-fnref-1\" data-footnote-backref=\"\" aria-label=\"Back to reference 1\" class=\"data-footnote-backref\">↩</a> <a href=\"#user-content-fnref-1-2\" data-footnote-backref=\"\" aria-label=\"Back to reference 1-2\" class=\"data-footnote-backref\">↩<sup>2</sup></a></p>
+</li>
+<li id=\"user-content-fn-n\">
+<ul>
+<li>list</li>
+</ul>
+<a href=\"#user-content-fnref-n\" data-footnote-backref=\"\" aria-label=\"Back to reference 2\" class=\"data-footnote-backref\">↩</a>
+</li>
+</ol>
+</section>
+"
+  );
+  assert_eq!(
+    render_with("a[^1]\n", &CompileOptions::default()),
+    "<p>a[^1]</p>\n"
+  );
+}
+
+#[test]
+fn test_render_html_autolink_literals() {
+  let source = "see https://example.com/a. and www.x.org\n";
+  let options = CompileOptions {
+    autolink_literals: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<p>see <a href=\"https://example.com/a\">https://example.com/a</a>. and <a href=\"http://www.x.org\">www.x.org</a></p>\n"
+  );
+  assert_eq!(
+    render_with(source, &CompileOptions::default()),
+    "<p>see https://example.com/a. and www.x.org</p>\n"
+  );
+}
+
+#[test]
+fn test_render_html_emphasis() {
+  let cases = [
+    ("*foo**bar*", "<em>foo**bar</em>"),
+    ("foo***bar***baz", "foo<em><strong>bar</strong></em>baz"),
+    ("**foo*", "*<em>foo</em>"),
+    (
+      "__foo, __bar__, baz__",
+      "<strong>foo, <strong>bar</strong>, baz</strong>",
+    ),
+    ("*(**foo**)*", "<em>(<strong>foo</strong>)</em>"),
+    ("_foo_bar", "_foo_bar"),
+    // links and code spans bind tighter than emphasis
+    ("*foo [bar*](/url)", r#"*foo <a href="/url">bar*</a>"#),
+    ("*a `*`*", "<em>a <code>*</code></em>"),
+    ("~~a *b~~ c*", "<del>a *b</del> c*"),
+    // escaped delimiters are text
+    ("\\*a*", "*a*"),
+    ("*a\\*", "*a*"),
+    ("\\_a\\_", "_a_"),
+  ];
+  for (source, html) in cases {
+    assert_eq!(
+      render_with(source, &CompileOptions::default()),
+      format!("<p>{}</p>\n", html),
+      "{}",
+      source
+    );
+  }
+}
+
+#[test]
+fn test_render_html_autolink_literal_boundaries() {
+  let options = CompileOptions {
+    autolink_literals: true,
+    ..Default::default()
+  };
+  let cases = [
+    // not inside link text or code, and not after a letter
+    ("[see www.a.com](b)", r#"<a href="b">see www.a.com</a>"#),
+    ("`www.a.com`", "<code>www.a.com</code>"),
+    ("xwww.a.com", "xwww.a.com"),
+    // surrounding punctuation and delimiters stay outside
+    (
+      "(www.a.com)",
+      r#"(<a href="http://www.a.com">www.a.com</a>)"#,
+    ),
+    (
+      "*www.a.com*",
+      r#"<em><a href="http://www.a.com">www.a.com</a></em>"#,
+    ),
+    (
+      "a foo@bar.baz.",
+      r#"a <a href="mailto:foo@bar.baz">foo@bar.baz</a>."#,
+    ),
+    (
+      "https://a.b/c?d=<e>",
+      r#"<a href="https://a.b/c?d=">https://a.b/c?d=</a>&lt;e&gt;"#,
+    ),
+    ("http://a.b_c.com", "http://a.b_c.com"),
+  ];
+  for (source, html) in cases {
+    assert_eq!(
+      render_with(source, &options),
+      format!("<p>{}</p>\n", html),
+      "{}",
+      source
+    );
+  }
+}
+
+#[test]
+fn test_render_html_math() {
+  let source = "$$\nx^2\n$$\n\nand $y < 1$\n";
+  let options = CompileOptions {
+    math: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<div class=\"math math-display\">x^2\n</div>\n<p>and <span class=\"math math-inline\">y &lt; 1</span></p>\n"
+  );
+}
+
+#[test]
+fn test_render_html_toc_and_frontmatter() {
+  let source = "---\ntitle: a\n---\n\n# Hi there\n\n## Hi there\n";
+  let options = CompileOptions {
+    frontmatter: true,
+    toc_export: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    render_with(source, &options),
+    "<h1 id=\"hi-there\">Hi there</h1>\n<h2 id=\"hi-there-1\">Hi there</h2>\n"
+  );
+  assert_eq!(
+    render_with("# Hi there\n", &CompileOptions::default()),
+    "<h1>Hi there</h1>\n"
+  );
+}
+
+#[test]
+fn test_render_html_remark_nodes() {
+  let ast = r#"{
+    "type": "root",
+    "children": [
+      {"type": "definition", "identifier": "docs", "url": "/docs", "title": "Docs"},
+      {"type": "html", "value": "<hr>"},
+      {"type": "paragraph", "children": [
+        {"type": "linkReference", "identifier": "docs", "referenceType": "collapsed", "children": [{"type": "text", "value": "a"}]},
+        {"type": "text", "value": " "},
+        {"type": "linkReference", "identifier": "nope", "referenceType": "shortcut", "children": [{"type": "text", "value": "b"}]},
+        {"type": "text", "value": " "},
+        {"type": "imageReference", "identifier": "nope", "referenceType": "full", "alt": "c"}
+      ]},
+      {"type": "table", "align": [null, "right"], "children": [
+        {"type": "tableRow", "children": [
+          {"type": "tableCell", "children": [{"type": "text", "value": "a"}]},
+          {"type": "tableCell", "children": [{"type": "text", "value": "b"}]}
+        ]},
+        {"type": "tableRow", "children": [
+          {"type": "tableCell", "children": [{"type": "text", "value": "1"}]},
+          {"type": "tableCell", "children": []}
+        ]}
+      ]}
+    ]
+  }"#;
+  let ast: Node = serde_json::from_str(ast).unwrap();
+  let options = CompileOptions::default();
+  let html_options = HtmlOptions::default();
+  let mut renderer = HtmlRenderer::new(&options, &html_options);
+  renderer.render(&ast).unwrap();
+  assert_eq!(
+    renderer.html,
+    "<hr>\n<p><a href=\"/docs\" title=\"Docs\">a</a> [b] ![c][nope]</p>\n<table>\n<thead>\n<tr>\n<th>a</th>\n<th align=\"right\">b</th>\n</tr>\n</thead>\n<tbody>\n<tr>\n<td>1</td>\n<td align=\"right\"></td>\n</tr>\n</tbody>\n</table>\n"
+  );
+}
@@ -1,53 +1,101 @@
 use crate::jsx_parser::*;
 use crate::lexer::*;
 use crate::md_lexer::*;
+use crate::options::*;
 use crate::token::*;
+use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::str;
 
+struct DelimiterRun {
+  ch: u8,
+  length: usize,
+  // delimiters not matched yet
+  remaining: usize,
+  can_open: bool,
+  can_close: bool,
+  // removed from the delimiter stack
+  active: bool,
+  // sizes of matched delimiters, innermost first
+  opens: Vec<usize>,
+  closes: Vec<usize>,
+}
+
+// `[` or `![` on the bracket stack
+struct Bracket {
+  token_index: usize,
+  image: bool,
+  // links can not contain other links
+  active: bool,
+  // delimiter stack size at the bracket
+  delimiter_bottom: usize,
+}
+
+// bytes that can start inline syntax, anything else is scanned as text
+pub type SpecialBytes = [bool; 256];
+
+pub fn special_bytes(options: &CompileOptions) -> SpecialBytes {
+  let mut special_bytes = [false; 256];
+  let specials = [b'*', b'_', b'~', b'[', b']', b'`', b'<', b'!', b'\r', b'\n'];
+  for &byte in &specials {
+    special_bytes[byte as usize] = true;
+  }
+  if options.math {
+    special_bytes[b'$' as usize] = true;
+  }
+  if options.directives {
+    special_bytes[b':' as usize] = true;
+  }
+  special_bytes
+}
+
 pub struct InlineParser<'a> {
   source: &'a str,
   bytes: &'a [u8],
   raws: &'a Vec<Span>,
-  special_bytes: [bool; 256],
+  options: &'a CompileOptions,
+  special_bytes: &'a SpecialBytes,
   maybe_tokens: VecDeque<Token<InlineToken>>,
   index: usize,
   text_start: usize,
-  // token index, ch, repeat
-  open_delimiters: Vec<(usize, u8, usize)>,
-  link_delimiters: VecDeque<usize>,
-  delimiter_bottom: Option<usize>,
-  // raw index, token index,
-  open_link: Option<(usize, usize)>,
+  delimiter_runs: Vec<DelimiterRun>,
+  // delimiter run indexes
+  delimiters: Vec<usize>,
+  brackets: Vec<Bracket>,
   pos: usize,
+  // syntax that was written as text instead
+  pub diagnostics: Vec<Token<&'static str>>,
 }
 
 impl<'a> InlineParser<'a> {
-  pub fn new(source: &'a str, bytes: &'a [u8], raws: &'a Vec<Span>) -> Self {
-    let mut special_bytes = [false; 256];
-    let specials = [b'*', b'_', b'~', b'[', b']', b'`', b'<', b'!', b'\r', b'\n'];
-    for &byte in &specials {
-      special_bytes[byte as usize] = true;
-    }
+  pub fn new(
+    source: &'a str,
+    bytes: &'a [u8],
+    raws: &'a Vec<Span>,
+    options: &'a CompileOptions,
+    special_bytes: &'a SpecialBytes,
+  ) -> Self {
     let pos = if raws.len() > 0 { raws[0].start } else { 0 };
     Self {
       source,
       bytes,
       raws,
+      options,
       special_bytes,
       maybe_tokens: VecDeque::new(),
       index: 0,
       pos,
       text_start: pos,
-      open_link: None,
-      delimiter_bottom: None,
-      open_delimiters: vec![],
-      link_delimiters: VecDeque::new(),
+      brackets: vec![],
+      delimiter_runs: vec![],
+      delimiters: vec![],
+      diagnostics: vec![],
     }
   }
 
   pub fn parse(&mut self) -> AST<Token<InlineToken>> {
     self.parse_raws();
+    self.process_emphasis(0);
     let start = self.raws.first().unwrap().start;
     let (children, end) = self.parse_tokens();
     AST {
@@ -59,6 +107,8 @@ impl<'a> InlineParser<'a> {
   fn parse_raws(&mut self) {
     while self.index < self.raws.len() {
       if self.pos >= self.raws[self.index].end {
+        // literal delimiters may run up to the end of the raw
+        self.handle_text();
         self.index += 1;
         if self.index == self.raws.len() {
           break;
@@ -77,6 +127,8 @@ impl<'a> InlineParser<'a> {
         if self.handle_special_byte(&raw) {
           continue;
         }
+      } else if self.options.autolink_literals && self.scan_autolink_literal(raw.end) {
+        continue;
       } else if byte == b'\\' {
         if self.pos + 1 < raw.end {
           if self.bytes[self.pos + 1].is_ascii_punctuation() {
@@ -89,7 +141,8 @@ impl<'a> InlineParser<'a> {
               },
             });
             self.pos += 1;
-            self.text_start = self.pos;
+            // the escaped byte is its own segment, the text goes on after it
+            self.text_start = self.pos + 1;
           }
         }
       }
@@ -119,128 +172,308 @@ impl<'a> InlineParser<'a> {
     true
   }
 
-  fn scan_link_url_title(&mut self) -> Option<(Span, Vec<Span>)> {
-    let mut index = self.index;
-    if let Some((pos, url_span, title_spans)) = link_url_title(|| {
-      if let Some(span) = self.raws.get(index) {
-        let bytes = &self.bytes[span.start..span.end];
-        if index == self.index {
-          let bytes = &self.bytes[self.pos + 1..span.end];
-          return Some((bytes, self.pos + 1));
-        } else {
-          return Some((bytes, span.start));
+  // raw index and position at or after pos, raw ends continue at the next raw
+  fn next_raw_pos(&self, mut index: usize, mut pos: usize) -> Option<(usize, usize)> {
+    while pos >= self.raws[index].end {
+      index += 1;
+      pos = self.raws.get(index)?.start;
+    }
+    Some((index, pos))
+  }
+
+  fn skip_link_whitespace(&self, index: usize, pos: usize) -> Option<(usize, usize)> {
+    let (mut index, mut pos) = self.next_raw_pos(index, pos)?;
+    while matches!(self.bytes[pos], b' ' | b'\t' | b'\r' | b'\n') {
+      (index, pos) = self.next_raw_pos(index, pos + 1)?;
+    }
+    Some((index, pos))
+  }
+
+  // `(destination "title")` after the `]` at pos, the title may span raws;
+  // raw index and position after `)`, url span, title spans
+  fn scan_inline_link(&self) -> Option<(usize, usize, Span, Vec<Span>)> {
+    let (index, pos) = self.next_raw_pos(self.index, self.pos + 1)?;
+    single_char(&self.bytes[pos..], b'(')?;
+    let (mut index, mut pos) = self.skip_link_whitespace(index, pos + 1)?;
+    let (size, url) = link_destination(&self.bytes[pos..self.raws[index].end], pos)?;
+    pos += size;
+    let mut title = vec![];
+    let (title_index, title_pos) = self.skip_link_whitespace(index, pos)?;
+    let close = match self.bytes[title_pos] {
+      b'"' => b'"',
+      b'\'' => b'\'',
+      b'(' => b')',
+      _ => 0,
+    };
+    // a title is separated from the destination by whitespace
+    if close != 0 && (title_index, title_pos) != (index, pos) {
+      let open = self.bytes[title_pos];
+      index = title_index;
+      pos = title_pos + 1;
+      let mut start = pos;
+      let mut escaped = false;
+      loop {
+        if pos >= self.raws[index].end {
+          title.push(Span {
+            start,
+            end: self.raws[index].end,
+          });
+          index += 1;
+          pos = self.raws.get(index)?.start;
+          start = pos;
+          continue;
         }
-      } else {
-        return None;
+        let byte = self.bytes[pos];
+        if escaped {
+          escaped = false;
+        } else if byte == b'\\' {
+          escaped = true;
+        } else if byte == close {
+          break;
+        } else if open == b'(' && byte == b'(' {
+          return None;
+        }
+        pos += 1;
+      }
+      if start < pos {
+        title.push(Span { start, end: pos });
+      }
+      pos += 1;
+    }
+    let (index, pos) = self.skip_link_whitespace(index, pos)?;
+    single_char(&self.bytes[pos..], b')')?;
+    Some((index, pos + 1, url, title))
+  }
+
+  // GFM extended autolinks start at the beginning of a line, after whitespace or
+  // `*`, `_`, `~`, `(`; emails only need a non local part character before them
+  fn scan_autolink_literal(&mut self, raw_end: usize) -> bool {
+    if !self.bytes[self.pos].is_ascii_alphanumeric() {
+      return false;
+    }
+    if self
+      .brackets
+      .iter()
+      .any(|bracket| bracket.active && !bracket.image)
+    {
+      return false;
+    }
+    let bytes = &self.bytes[self.pos..raw_end];
+    let literal = if self.pos == self.raws[self.index].start {
+      autolink_literal(bytes)
+    } else {
+      match self.bytes[self.pos - 1] {
+        b' ' | b'\t' | b'*' | b'_' | b'~' | b'(' => autolink_literal(bytes),
+        b'.' | b'-' | b'+' | b'@' => None,
+        ch if ch.is_ascii_alphanumeric() => None,
+        _ => match autolink_literal(bytes) {
+          Some((size, AutoLinkKind::Email)) => Some((size, AutoLinkKind::Email)),
+          _ => None,
+        },
       }
-    }) {
+    };
+    if let Some((size, kind)) = literal {
+      self.handle_text();
+      let span = Span {
+        start: self.pos,
+        end: self.pos + size,
+      };
       self.maybe_tokens.push_back(Token {
-        value: InlineToken::LinkEnd,
-        span: Span {
-          start: self.pos,
-          end: pos + 1,
+        value: InlineToken::AutoLink {
+          kind,
+          url: span.clone(),
         },
+        span,
       });
-      self.index = index;
-      self.pos = pos + 1;
-      self.text_start = self.pos;
-      return Some((url_span, title_spans));
+      return self.forward_pos(size);
     }
-    None
+    false
   }
 
-  fn scan_inline_code(&mut self, repeat: usize) -> bool {
+  // code spans and inline math share the backtick run matching
+  fn scan_inline_code(&mut self, ch: u8, repeat: usize) -> bool {
     let mut index = self.index;
-    let mut pos = self.pos + repeat - 1;
-    let mut codes: Vec<Span> = vec![];
-    let mut raw = &self.raws[index];
-    let mut raw_bytes = &self.bytes[raw.start..raw.end];
     let mut code_start = self.pos + repeat;
+    let mut pos = code_start;
+    let mut codes: Vec<Span> = vec![];
     loop {
-      if pos == raw.end {
+      let raw_end = self.raws[index].end;
+      if pos >= raw_end {
+        codes.push(Span {
+          start: code_start,
+          end: raw_end,
+        });
         index += 1;
         if index == self.raws.len() {
           return false;
         }
+        pos = self.raws[index].start;
+        code_start = pos;
+        continue;
+      }
+      if self.bytes[pos] != ch {
+        pos += 1;
+        continue;
+      }
+      let (_, end_repeat) = ch_repeat(&self.bytes[pos..raw_end], ch);
+      if end_repeat == repeat {
         codes.push(Span {
           start: code_start,
-          end: raw.end,
+          end: pos,
         });
-        raw = &self.raws[index];
-        raw_bytes = &self.bytes[raw.start..raw.end];
-        pos = raw.start;
-        code_start = raw.start;
-      } else {
-        pos += 1;
-      }
-      let i = pos - raw.start;
-      // TODO
-      if raw_bytes[i] == b'`' {
-        let (_, end_repeat) = ch_repeat(&raw_bytes[i..], b'`');
-        if end_repeat == repeat {
-          codes.push(Span {
-            start: code_start,
-            end: pos,
-          });
-          pos += repeat;
-          self.maybe_tokens.push_back(Token {
-            value: InlineToken::Code(codes),
-            span: Span {
-              start: self.pos,
-              end: pos,
-            },
-          });
-          return self.forward_pos(pos - self.pos);
-        }
+        let end = pos + repeat;
+        self.maybe_tokens.push_back(Token {
+          value: if ch == b'$' {
+            InlineToken::Math(codes)
+          } else {
+            InlineToken::Code(codes)
+          },
+          span: Span {
+            start: self.pos,
+            end,
+          },
+        });
+        self.index = index;
+        return self.forward_pos(end - self.pos);
       }
+      pos += end_repeat;
     }
-    false
   }
 
-  fn match_inlink_delimiter(&mut self) {
-    while !self.link_delimiters.is_empty() {
-      let token_index = self.link_delimiters.pop_front().unwrap();
-      self.match_delimiter(token_index);
+  fn can_match(&self, opener: usize, closer: usize) -> bool {
+    let opener = &self.delimiter_runs[opener];
+    let closer = &self.delimiter_runs[closer];
+    if !opener.active || !opener.can_open || opener.ch != closer.ch || opener.remaining == 0 {
+      return false;
     }
+    if opener.ch == b'~' {
+      return opener.length == closer.length;
+    }
+    // rule of 3
+    !((opener.can_close || closer.can_open)
+      && (opener.length + closer.length).is_multiple_of(3)
+      && !(opener.length.is_multiple_of(3) && closer.length.is_multiple_of(3)))
   }
 
-  fn match_delimiter(&mut self, token_index: usize) {
-    if let Token {
-      value:
-        InlineToken::MaybeEmphasis {
-          ch,
-          repeat,
-          can_open,
-          can_close,
-        },
-      ..
-    } = self.maybe_tokens[token_index]
-    {
-      if can_close && !self.open_delimiters.is_empty() {
-        let mut delimiter_index = self.open_delimiters.len() - 1;
-        loop {
-          let (open_token_index, open_ch, open_repeat) = self.open_delimiters[delimiter_index];
-          if let Some(bottom) = self.delimiter_bottom {
-            if open_token_index < bottom {
-              break;
-            }
-          }
-          if ch == open_ch && open_repeat == repeat {
-            self.maybe_tokens[token_index].value = InlineToken::EmphasisEnd;
-            self.maybe_tokens[open_token_index].value = InlineToken::EmphasisStart;
-            self.open_delimiters.truncate(delimiter_index);
-          }
-          if delimiter_index == 0 {
-            break;
-          } else {
-            delimiter_index -= 1;
-          }
-        }
+  // CommonMark process emphasis over the delimiter stack above bottom
+  fn process_emphasis(&mut self, bottom: usize) {
+    // by ch, closer can open and closer length % 3
+    let mut openers_bottom: HashMap<(u8, bool, usize), usize> = HashMap::new();
+    let mut current = bottom;
+    while current < self.delimiters.len() {
+      let closer = self.delimiters[current];
+      let DelimiterRun {
+        ch,
+        length,
+        can_open,
+        can_close,
+        active,
+        ..
+      } = self.delimiter_runs[closer];
+      if !active || !can_close {
+        current += 1;
+        continue;
       }
-      if can_open {
-        self.open_delimiters.push((token_index, ch, repeat));
+      let key = (ch, can_open, length % 3);
+      let lower = *openers_bottom.get(&key).unwrap_or(&bottom);
+      let opener_position = (lower..current)
+        .rev()
+        .find(|&position| self.can_match(self.delimiters[position], closer));
+      if let Some(position) = opener_position {
+        // delimiters between opener and closer can no longer match
+        for &between in &self.delimiters[position + 1..current] {
+          self.delimiter_runs[between].active = false;
+        }
+        let opener = self.delimiters[position];
+        let remaining = self.delimiter_runs[opener]
+          .remaining
+          .min(self.delimiter_runs[closer].remaining);
+        let size = if ch == b'~' || remaining < 2 {
+          remaining
+        } else {
+          2
+        };
+        let opener_run = &mut self.delimiter_runs[opener];
+        opener_run.remaining -= size;
+        opener_run.opens.push(size);
+        if opener_run.remaining == 0 {
+          opener_run.active = false;
+        }
+        let closer_run = &mut self.delimiter_runs[closer];
+        closer_run.remaining -= size;
+        closer_run.closes.push(size);
+        if closer_run.remaining == 0 {
+          closer_run.active = false;
+          current += 1;
+        }
+      } else {
+        openers_bottom.insert(key, current);
+        if !can_open {
+          self.delimiter_runs[closer].active = false;
+        }
+        current += 1;
       }
     }
+    for &run in &self.delimiters[bottom..] {
+      self.delimiter_runs[run].active = false;
+    }
+    self.delimiters.truncate(bottom);
+  }
+
+  // closers, unmatched delimiters as text, then openers from the outermost
+  fn expand_delimiter_run(&mut self, run: usize, span: Span) {
+    let DelimiterRun {
+      ch,
+      length,
+      opens,
+      closes,
+      ..
+    } = &self.delimiter_runs[run];
+    let mut tokens = vec![];
+    let mut pos = span.start;
+    for &size in closes {
+      tokens.push(Token {
+        value: InlineToken::EmphasisEnd,
+        span: Span {
+          start: pos,
+          end: pos + size,
+        },
+      });
+      pos += size;
+    }
+    let text_size = length - closes.iter().sum::<usize>() - opens.iter().sum::<usize>();
+    if text_size > 0 {
+      tokens.push(Token {
+        value: InlineToken::TextSegment,
+        span: Span {
+          start: pos,
+          end: pos + text_size,
+        },
+      });
+      pos += text_size;
+    }
+    for &size in opens.iter().rev() {
+      tokens.push(Token {
+        value: InlineToken::EmphasisStart { ch: *ch, size },
+        span: Span {
+          start: pos,
+          end: pos + size,
+        },
+      });
+      pos += size;
+    }
+    for token in tokens.into_iter().rev() {
+      self.maybe_tokens.push_front(token);
+    }
+  }
+
+  fn push_bracket(&mut self, image: bool) {
+    self.brackets.push(Bracket {
+      token_index: self.maybe_tokens.len(),
+      image,
+      active: true,
+      delimiter_bottom: self.delimiters.len(),
+    });
   }
 
   fn handle_special_byte(&mut self, raw: &Span) -> bool {
@@ -249,52 +482,85 @@ impl<'a> InlineParser<'a> {
     let byte = self.bytes[self.pos];
     let start = self.pos - raw.start;
     match byte {
-      b'`' => {
+      b'`' | b'$' => {
         let (_, repeat) = ch_repeat(bytes, byte);
-        if !self.scan_inline_code(repeat) {
+        if !self.scan_inline_code(byte, repeat) {
           self.pos += repeat;
         }
         return true;
       }
       b'*' | b'_' | b'~' => {
         let (_, repeat) = ch_repeat(bytes, byte);
-        if byte == b'~' && repeat != 2 {
+        // GFM strikethrough takes one or two tildes
+        if byte == b'~' && repeat > 2 {
           self.pos += repeat;
           return true;
         }
-        let can_open = is_left_flanking_delimiter(raw_bytes, start, start + repeat);
-        let can_close = is_right_flanking_delimiter(raw_bytes, start, start + repeat);
+        let prev = self.source[raw.start..self.pos].chars().next_back();
+        let next = self.source[self.pos + repeat..raw.end].chars().next();
+        let (can_open, can_close) = emphasis_delimiter(byte, prev, next);
         if !can_open && !can_close {
           self.pos += repeat;
           return true;
         }
-
+        self.delimiters.push(self.delimiter_runs.len());
         self.maybe_tokens.push_back(Token {
-          value: InlineToken::MaybeEmphasis {
-            ch: byte,
-            repeat,
-            can_open,
-            can_close,
-          },
+          value: InlineToken::MaybeEmphasis(self.delimiter_runs.len()),
           span: Span {
             start: self.pos,
             end: self.pos + repeat,
           },
         });
-        self.forward_pos(repeat);
-        if let Some((raw_index, _)) = self.open_link {
-          if raw_index == self.index {
-            self.link_delimiters.push_back(self.maybe_tokens.len() - 1);
-            return true;
-          }
-        } else {
-          self.match_delimiter(self.maybe_tokens.len() - 1);
+        self.delimiter_runs.push(DelimiterRun {
+          ch: byte,
+          length: repeat,
+          remaining: repeat,
+          can_open,
+          can_close,
+          active: true,
+          opens: vec![],
+          closes: vec![],
+        });
+        return self.forward_pos(repeat);
+      }
+      b':' => {
+        if start > 0 && raw_bytes[start - 1] == b':' {
+          return false;
         }
-        return true;
+        if let Some((size, directive)) = directive(&bytes[1..], self.pos + 1) {
+          let children = if let Some(label) = &directive.label {
+            let raws = vec![label.clone()];
+            let mut parser = InlineParser::new(
+              self.source,
+              self.bytes,
+              &raws,
+              self.options,
+              self.special_bytes,
+            );
+            let children = parser.parse().children;
+            self.diagnostics.append(&mut parser.diagnostics);
+            children
+          } else {
+            vec![]
+          };
+          self.maybe_tokens.push_back(Token {
+            value: InlineToken::TextDirective {
+              directive,
+              children,
+            },
+            span: Span {
+              start: self.pos,
+              end: self.pos + size + 1,
+            },
+          });
+          return self.forward_pos(size + 1);
+        }
+        false
       }
       b'!' => {
         if let Some(next_byte) = raw_bytes.get(start + 1) {
           if *next_byte == b'[' {
+            self.push_bracket(true);
             self.maybe_tokens.push_back(Token {
               value: InlineToken::MaybeLinkStart,
               span: Span {
@@ -308,7 +574,19 @@ impl<'a> InlineParser<'a> {
         return false;
       }
       b'[' => {
-        self.open_link = Some((self.index, self.maybe_tokens.len()));
+        if self.options.footnotes {
+          if let Some((size, label)) = footnote_label(bytes, self.pos) {
+            self.maybe_tokens.push_back(Token {
+              value: InlineToken::FootnoteReference(label),
+              span: Span {
+                start: self.pos,
+                end: self.pos + size,
+              },
+            });
+            return self.forward_pos(size);
+          }
+        }
+        self.push_bracket(false);
         self.maybe_tokens.push_back(Token {
           value: InlineToken::MaybeLinkStart,
           span: Span {
@@ -319,24 +597,45 @@ impl<'a> InlineParser<'a> {
         return self.forward_pos(1);
       }
       b']' => {
-        if let Some((raw_index, token_index)) = self.open_link {
-          self.open_link = None;
-          if raw_index == self.index {
-            if let Some((url, title)) = self.scan_link_url_title() {
-              self.delimiter_bottom = Some(token_index);
-              self.match_inlink_delimiter();
-              self.delimiter_bottom = None;
-              self.maybe_tokens[token_index].value = InlineToken::LinkStart { url, title };
-              return true;
+        let bracket = match self.brackets.pop() {
+          Some(bracket) if bracket.active => bracket,
+          _ => return false,
+        };
+        let (index, pos, url, title) = match self.scan_inline_link() {
+          Some(link) => link,
+          None => return false,
+        };
+        self.maybe_tokens.push_back(Token {
+          value: InlineToken::LinkEnd,
+          span: Span {
+            start: self.pos,
+            end: pos,
+          },
+        });
+        self.maybe_tokens[bracket.token_index].value = if bracket.image {
+          InlineToken::ImageStart { url, title }
+        } else {
+          for bracket in &mut self.brackets {
+            if !bracket.image {
+              bracket.active = false;
             }
           }
-        }
-        return false;
+          InlineToken::LinkStart { url, title }
+        };
+        self.process_emphasis(bracket.delimiter_bottom);
+        self.index = index;
+        self.forward_pos(pos - self.pos)
       }
       b'<' => {
         if let Some(size) = uri(bytes) {
           self.maybe_tokens.push_back(Token {
-            value: InlineToken::AutoLink(false),
+            value: InlineToken::AutoLink {
+              kind: AutoLinkKind::Uri,
+              url: Span {
+                start: self.pos + 1,
+                end: self.pos + size - 1,
+              },
+            },
             span: Span {
               start: self.pos,
               end: self.pos + size,
@@ -361,6 +660,19 @@ impl<'a> InlineParser<'a> {
             });
             true
           } else {
+            // `<` followed by a name, `/` or `>` starts a tag in MDX
+            let tag = bytes
+              .get(1)
+              .is_some_and(|byte| byte.is_ascii_alphabetic() || b"/>".contains(byte));
+            if tag {
+              self.diagnostics.push(Token {
+                value: "JSX couldn't be parsed here and is written as text, `\\<` is a literal `<`",
+                span: Span {
+                  start: self.pos,
+                  end: self.pos + 1,
+                },
+              });
+            }
             false
           }
         }
@@ -440,11 +752,30 @@ impl<'a> InlineParser<'a> {
             span: Span { start, end },
           });
         }
-        InlineToken::EmphasisStart => {
+        InlineToken::ImageStart { url, title } => {
+          let start = span.start;
+          let (alt_children, end) = self.parse_tokens();
+          children.push(Token {
+            value: InlineToken::Image {
+              url,
+              title,
+              alt_children,
+            },
+            span: Span { start, end },
+          });
+        }
+        InlineToken::MaybeEmphasis(run) => {
+          self.expand_delimiter_run(run, span);
+        }
+        InlineToken::EmphasisStart { ch, size } => {
           let start = span.start;
           let (em_children, end) = self.parse_tokens();
           children.push(Token {
-            value: InlineToken::Emphasis(em_children),
+            value: match (ch, size) {
+              (b'~', _) => InlineToken::Strikethrough(em_children),
+              (_, 2) => InlineToken::Strong(em_children),
+              _ => InlineToken::Emphasis(em_children),
+            },
             span: Span { start, end },
           });
         }
@@ -122,6 +122,33 @@ impl<'a> JSXLexer<'a> {
     return None;
   }
 
+  // attribute names may be reserved words and have dashes, `class`, `aria-label`
+  pub fn read_attribute_name(&mut self) -> Option<Span> {
+    let bytes = self.skip_spaces_newlines()?;
+    let (_, size) = identifier(bytes)?;
+    let (_, rest) = take_while(&bytes[size..], |c| {
+      c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c == b'-'
+    });
+    Some(Span {
+      start: self.pos,
+      end: self.forward(size + rest),
+    })
+  }
+
+  // `word` after spaces on the current line, nothing is read otherwise
+  pub fn read_word_on_line(&mut self, word: &[u8]) -> Option<Span> {
+    let (bytes, size) = spaces0(self.cur_bytes);
+    let (_, word_size) = identifier(bytes)?;
+    if &bytes[..word_size] != word {
+      return None;
+    }
+    let start = self.pos + size;
+    Some(Span {
+      start,
+      end: self.forward(size + word_size),
+    })
+  }
+
   pub fn read_keyword(&mut self) -> Option<Span> {
     let bytes = self.skip_spaces_newlines()?;
     let (_, size) = identifier(bytes)?;
@@ -49,6 +49,13 @@ impl<'a> JSXParser<'a> {
       return None;
     }
     self.lexer.read_string_literal()?;
+    // import attributes, `with {type: "json"}`
+    if self.lexer.read_word_on_line(b"with").is_some()
+      || self.lexer.read_word_on_line(b"assert").is_some()
+    {
+      self.lexer.read_target_punctuator(b"{")?;
+      self.js_object()?;
+    }
     Some(())
   }
 
@@ -107,6 +114,11 @@ impl<'a> JSXParser<'a> {
   pub fn js_export(&mut self) -> Option<()> {
     if let Some(Span { start, end }) = self.lexer.read_keyword() {
       let word = &self.lexer.bytes[start..end];
+      // `export default Layout`
+      if word == b"default" {
+        self.js_expression()?;
+        return Some(());
+      }
       if word != b"var" && word != b"let" && word != b"const" {
         return None;
       }
@@ -125,20 +137,24 @@ impl<'a> JSXParser<'a> {
   }
 
   pub fn jsx_element(&mut self, opened: bool) -> Option<JSXElement> {
-    if !opened {
-      self.lexer.read_target_punctuator(b"<")?;
-    }
+    // an opened element had its `<` read right before
+    let start = if opened {
+      self.lexer.pos() - 1
+    } else {
+      self.lexer.read_target_punctuator(b"<")?.start
+    };
     if self.lexer.read_target_punctuator(b"/").is_some() {
       return None;
     }
     let open_tag = self.jsx_tag()?;
     let attributes = self.jsx_attributes()?;
     if self.lexer.read_target_punctuator(b"/").is_some() {
-      self.lexer.read_target_punctuator(b">")?;
+      let end = self.lexer.read_target_punctuator(b">")?.end;
       return Some(JSXElement {
         tag: open_tag,
         attributes,
         children: vec![],
+        span: Span { start, end },
       });
     }
     self.lexer.read_target_punctuator(b">")?;
@@ -148,11 +164,12 @@ impl<'a> JSXParser<'a> {
     if close_tag != open_tag {
       return None;
     }
-    self.lexer.read_target_punctuator(b">")?;
+    let end = self.lexer.read_target_punctuator(b">")?.end;
     return Some(JSXElement {
       tag: open_tag,
       attributes,
       children,
+      span: Span { start, end },
     });
     None
   }
@@ -182,7 +199,7 @@ impl<'a> JSXParser<'a> {
     loop {
       if let Some(attr) = self.jsx_spread_attr() {
         attributes.push(attr);
-      } else if let Some(id_span) = self.lexer.read_identifier() {
+      } else if let Some(id_span) = self.lexer.read_attribute_name() {
         if self.lexer.read_target_punctuator(b"=").is_none() {
           attributes.push(JSXAttr::KeyTrueValue { key: id_span });
         } else if let Some(string_span) = self.lexer.read_string_literal() {
@@ -1,21 +1,148 @@
 mod block;
 mod codegen;
+mod compiler;
 mod document;
+mod esm;
+mod footnote;
+mod html;
 mod inline;
 mod jsx_lexer;
 mod jsx_parser;
 mod lexer;
 mod md_lexer;
+mod mdast;
+mod options;
+mod source_map;
+mod target;
+mod toc;
 mod token;
 use crate::block::*;
 use crate::codegen::*;
+pub use crate::compiler::Compiler;
+use crate::html::*;
+pub use crate::html::{
+  Component, ComponentTemplate, ExpressionStrategy, HtmlError, HtmlOptions, JsxStrategy,
+};
+use crate::inline::*;
+use crate::mdast::*;
+pub use crate::mdast::{
+  Diagnostic, JsxAttribute, JsxAttributeValue, JsxAttributeValueExpression, Node, Point, Position,
+  ReferenceType, Utf16Index,
+};
+pub use crate::options::{CompileOptions, CompileOptionsBuilder, JsxRuntime, OutputFormat};
+pub use crate::source_map::SourceMapKind;
+use crate::source_map::*;
+pub use crate::target::TargetKind;
+pub use crate::toc::TocEntry;
+use crate::toc::*;
+pub use crate::token::Span;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "camelCase"))]
+pub struct CompileResult {
+  pub code: String,
+  pub toc: Vec<TocEntry>,
+  // Source Map v3 JSON, with `SourceMapKind::Separate`
+  pub source_map: Option<String>,
+  // the YAML of the frontmatter as written, with the `frontmatter` option
+  pub frontmatter: Option<String>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+pub fn compile(source: &str, options: &CompileOptions) -> CompileResult {
+  let special_bytes = special_bytes(options);
+  let (ast, diagnostics) = parse_with(source, options, &special_bytes);
+  generate(&ast, Some(source), options, diagnostics, String::new())
+}
+
+// the same module for an mdast tree handed over by the host, e.g. from remark
+pub fn compile_ast(ast: &Node, options: &CompileOptions) -> CompileResult {
+  generate(ast, None, options, vec![], String::new())
+}
+
+// the code is generated into `code`, an empty buffer that may already have capacity
+fn generate(
+  ast: &Node,
+  source: Option<&str>,
+  options: &CompileOptions,
+  mut diagnostics: Vec<Diagnostic>,
+  code: String,
+) -> CompileResult {
+  let mut codegen = Codegen::new(options);
+  if options.development {
+    codegen.utf16 = source.map(Utf16Index::new);
+  }
+  // the code runs about twice the length of the markdown, reserved to spare the regrowing
+  codegen.code = code;
+  codegen
+    .code
+    .reserve(source.map_or(0, |source| source.len() * 2));
+  codegen.gen(ast);
+  let mut code = std::mem::take(&mut codegen.code);
+  let mut source_map = None;
+  if let Some(kind) = options.source_map {
+    let json = source_map_json(&code, &codegen.mappings, source, options.source_name());
+    match kind {
+      SourceMapKind::Inline => code.push_str(&inline_source_map_comment(&json)),
+      SourceMapKind::Separate => source_map = Some(json),
+    }
+  }
+  let mut toc = codegen.toc.entries;
+  if let Some(source) = source {
+    include_line_endings(&mut toc, source);
+  }
+  diagnostics.append(&mut codegen.diagnostics);
+  CompileResult {
+    code,
+    toc,
+    source_map,
+    frontmatter: codegen.frontmatter,
+    diagnostics,
+  }
+}
+
+// static HTML for places without a JS runtime, also checked against the spec examples
+pub fn render_html(
+  source: &str,
+  options: &CompileOptions,
+  html_options: &HtmlOptions,
+) -> Result<String, HtmlError> {
+  render_html_with_diagnostics(source, options, html_options).0
+}
+
+// `render_html` and what the parse reported, like `CompileResult::diagnostics`
+pub fn render_html_with_diagnostics(
+  source: &str,
+  options: &CompileOptions,
+  html_options: &HtmlOptions,
+) -> (Result<String, HtmlError>, Vec<Diagnostic>) {
+  let (ast, diagnostics) = parse_with(source, options, &special_bytes(options));
+  let mut renderer = HtmlRenderer::new(options, html_options);
+  let html = renderer.render(&ast).map(|()| renderer.html);
+  (html, diagnostics)
+}
+
+// mdast tree with positions, for tools from the unified ecosystem
+pub fn parse_to_ast(source: &str, options: &CompileOptions) -> Node {
+  parse_with(source, options, &special_bytes(options)).0
+}
+
+fn parse_with(
+  source: &str,
+  options: &CompileOptions,
+  special_bytes: &SpecialBytes,
+) -> (Node, Vec<Diagnostic>) {
+  let mut block_parser = BlockParser::new(source, options);
+  let ast = block_parser.parse();
+  let builder = MdastBuilder::new(source, source.as_bytes(), options, special_bytes);
+  let node = builder.build(&ast);
+  (node, builder.diagnostics())
+}
 
 pub fn parse(source: &str) -> String {
-  let mut blockParser = BlockParser::new(source);
-  let mut ast = blockParser.parse();
-  let mut codegen = Codegen::new(source, source.as_bytes());
-  codegen.gen(&mut ast);
-  codegen.code
+  compile(source, &CompileOptions::default()).code
 }
 
 #[test]
@@ -7,6 +7,21 @@ pub fn atx_heading_start(bytes: &[u8]) -> Option<(usize, usize)> {
   Some((size, repeat))
 }
 
+// content size without the optional closing sequence and trailing spaces
+pub fn atx_heading_content(bytes: &[u8]) -> usize {
+  let mut size = bytes.len() - rev_spaces0(bytes);
+  let repeat = bytes[..size]
+    .iter()
+    .rev()
+    .take_while(|&&c| c == b'#')
+    .count();
+  if repeat == size || (repeat > 0 && bytes[size - repeat - 1] == b' ') {
+    size -= repeat;
+    size -= rev_spaces0(&bytes[..size]);
+  }
+  size
+}
+
 // size, repeat size, meta size
 pub fn open_fenced_code(bytes: &[u8]) -> Option<(usize, usize, usize)> {
   if let Some((bytes, repeat)) = ch_repeat_min(bytes, b'`', 3) {
@@ -20,6 +35,14 @@ pub fn open_fenced_code(bytes: &[u8]) -> Option<(usize, usize, usize)> {
   }
 }
 
+// size, repeat size
+pub fn open_fenced_math(bytes: &[u8]) -> Option<(usize, usize)> {
+  let (bytes, repeat) = ch_repeat_min(bytes, b'$', 2)?;
+  let (bytes, meta_size) = take_while(bytes, |c| c != b'$' && c != b'\r' && c != b'\n');
+  let (_, eol_size) = eol(bytes)?;
+  Some((repeat + meta_size + eol_size, repeat))
+}
+
 pub fn close_fenced_code(bytes: &[u8], ch: u8, repeat: usize) -> Option<usize> {
   let len = bytes.len();
   let (bytes, _) = ch_repeat_max(bytes, b' ', 3)?;
@@ -60,6 +83,59 @@ pub fn block_quote(bytes: &[u8]) -> Option<(usize, usize)> {
   }
 }
 
+// size, label span without `[^` and `]`
+pub fn footnote_label(bytes: &[u8], offset: usize) -> Option<(usize, Span)> {
+  let bytes = tag(bytes, b"[^")?;
+  let (bytes, size) = take_while(bytes, |ch| ch != b']' && ch != b'[' && ch > b' ');
+  single_char(bytes, b']')?;
+  if size == 0 {
+    return None;
+  }
+  Some((
+    size + 3,
+    Span {
+      start: offset + 2,
+      end: offset + 2 + size,
+    },
+  ))
+}
+
+// `[^label]:` and following spaces, size and label span
+pub fn footnote_definition_start(bytes: &[u8], offset: usize) -> Option<(usize, Span)> {
+  let (size, label) = footnote_label(bytes, offset)?;
+  let bytes = single_char(&bytes[size..], b':')?;
+  let (_, spaces) = spaces0(bytes);
+  Some((size + 1 + spaces, label))
+}
+
+// size including following spaces, checked
+pub fn task_list_marker(bytes: &[u8]) -> Option<(usize, bool)> {
+  let bytes = single_char(bytes, b'[')?;
+  let checked = match bytes.first()? {
+    b' ' => false,
+    b'x' | b'X' => true,
+    _ => return None,
+  };
+  let bytes = single_char(&bytes[1..], b']')?;
+  let (bytes, spaces) = take_while(bytes, |ch| ch == b' ' || ch == b'\t');
+  if spaces == 0 {
+    eol(bytes)?;
+  }
+  Some((3 + spaces, checked))
+}
+
+// `[!NOTE]` alone on the first line of a block quote, spaces and tabs around it
+pub fn alert_marker(bytes: &[u8]) -> Option<Alert> {
+  let (bytes, _) = take_while(bytes, |ch| ch == b' ' || ch == b'\t');
+  let kind = tag(bytes, b"[!")?;
+  let (bytes, size) = take_while(kind, |ch| ch.is_ascii_alphabetic());
+  let alert = Alert::new(&kind[..size])?;
+  let bytes = single_char(bytes, b']')?;
+  let (bytes, _) = take_while(bytes, |ch| ch == b' ' || ch == b'\t');
+  eol(bytes)?;
+  Some(alert)
+}
+
 // size, marker size, ending indent
 pub fn list_item_start(bytes: &[u8]) -> Option<(usize, usize, usize)> {
   if bytes.len() > 0 {
@@ -128,11 +204,6 @@ pub fn thematic_break(bytes: &[u8]) -> Option<usize> {
   None
 }
 
-pub fn blank_line(bytes: &[u8]) -> Option<usize> {
-  let (_, size) = spaces_eol(bytes)?;
-  Some(size)
-}
-
 pub fn uri(bytes: &[u8]) -> Option<usize> {
   let bytes = single_char(bytes, b'<')?;
   let mut size = 1;
@@ -146,203 +217,455 @@ pub fn uri(bytes: &[u8]) -> Option<usize> {
     ch != b':' && (ch.is_ascii_alphanumeric() || ch == b'+' || ch == b'-' || ch == b'.')
   });
   size += scheme_size;
-  if scheme_size < 2 || scheme_size > 32 {
+  if !(2..=32).contains(&scheme_size) {
     return None;
   }
   let bytes = single_char(bytes, b':')?;
   size += 1;
-  let (bytes, follow_size) = take_while(bytes, |ch| match ch {
-    b'<' | b'>' => false,
-    b'\0'..=b' ' => false,
-    _ => true,
-  });
+  let (bytes, follow_size) = take_while(bytes, |ch| !matches!(ch, b'<' | b'>' | b'\0'..=b' '));
   single_char(bytes, b'>')?;
   size += follow_size + 1;
   Some(size)
 }
 
-// size, url Span
-pub fn link_url(bytes: &[u8], offset: usize) -> Option<(usize, Span)> {
-  let bytes = single_char(bytes, b'(')?;
-  let (bytes, spaces) = spaces0(bytes);
+// segments of alphanumerics, `_` and `-` separated by periods, no `_` in the last two
+fn autolink_domain(bytes: &[u8], min_periods: usize) -> Option<usize> {
+  let (_, size) = take_while(bytes, |ch| {
+    ch.is_ascii_alphanumeric() || ch == b'_' || ch == b'-' || ch == b'.'
+  });
+  // trailing periods are punctuation
+  let domain_size = size
+    - bytes[..size]
+      .iter()
+      .rev()
+      .take_while(|&&ch| ch == b'.')
+      .count();
+  let segments: Vec<&[u8]> = bytes[..domain_size].split(|&ch| ch == b'.').collect();
+  if domain_size == 0 || segments.len() <= min_periods || segments.iter().any(|s| s.is_empty()) {
+    return None;
+  }
+  if segments.iter().rev().take(2).any(|s| s.contains(&b'_')) {
+    return None;
+  }
+  Some(size)
+}
+
+// the rest of an extended autolink after the domain, without trailing punctuation
+fn autolink_path_end(bytes: &[u8], domain_size: usize) -> usize {
+  let (_, path_size) = take_while(&bytes[domain_size..], |ch| {
+    ch != b'<' && !ch.is_ascii_whitespace()
+  });
+  let mut end = domain_size + path_size;
+  loop {
+    match bytes[end - 1] {
+      b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~' => end -= 1,
+      b')' => {
+        let link = &bytes[..end];
+        let open = link.iter().filter(|&&ch| ch == b'(').count();
+        let close = link.iter().filter(|&&ch| ch == b')').count();
+        if close <= open {
+          break;
+        }
+        end -= 1;
+      }
+      // entity like `&amp;`
+      b';' => {
+        let name = bytes[..end - 1]
+          .iter()
+          .rev()
+          .take_while(|ch| ch.is_ascii_alphanumeric())
+          .count();
+        if name == 0 || end - 1 - name == 0 || bytes[end - 2 - name] != b'&' {
+          break;
+        }
+        end -= name + 2;
+      }
+      _ => break,
+    }
+  }
+  end
+}
+
+// GFM extended email autolink, `foo.bar+baz@example.com`
+fn email_autolink(bytes: &[u8]) -> Option<usize> {
+  let (rest, local) = take_while(bytes, |ch| {
+    ch.is_ascii_alphanumeric() || ch == b'.' || ch == b'-' || ch == b'_' || ch == b'+'
+  });
+  if local == 0 {
+    return None;
+  }
+  let rest = single_char(rest, b'@')?;
+  let (_, size) = take_while(rest, |ch| {
+    ch.is_ascii_alphanumeric() || ch == b'.' || ch == b'-' || ch == b'_'
+  });
+  let size = size
+    - rest[..size]
+      .iter()
+      .rev()
+      .take_while(|&&ch| ch == b'.')
+      .count();
+  let domain = &rest[..size];
+  if size == 0 || !domain.contains(&b'.') || domain.split(|&ch| ch == b'.').any(|s| s.is_empty()) {
+    return None;
+  }
+  if domain[size - 1] == b'-' || domain[size - 1] == b'_' {
+    return None;
+  }
+  Some(local + 1 + size)
+}
+
+// size and kind of a GFM extended autolink at the start of bytes
+pub fn autolink_literal(bytes: &[u8]) -> Option<(usize, AutoLinkKind)> {
+  for scheme in [&b"https://"[..], b"http://"] {
+    if let Some(rest) = tag(bytes, scheme) {
+      let domain = autolink_domain(rest, 1)?;
+      let end = autolink_path_end(rest, domain);
+      autolink_domain(&rest[..end], 1)?;
+      return Some((scheme.len() + end, AutoLinkKind::Uri));
+    }
+  }
+  if let Some(rest) = tag(bytes, b"www.") {
+    let domain = autolink_domain(rest, 0)?;
+    let end = autolink_path_end(rest, domain);
+    autolink_domain(&rest[..end], 0)?;
+    return Some((4 + end, AutoLinkKind::Www));
+  }
+  if let Some(rest) = tag(bytes, b"mailto:") {
+    return Some((7 + email_autolink(rest)?, AutoLinkKind::Uri));
+  }
+  email_autolink(bytes).map(|size| (size, AutoLinkKind::Email))
+}
+
+// size, url span; `<...>` or a run without spaces and control characters, with
+// balanced parentheses, which may be empty
+pub fn link_destination(bytes: &[u8], offset: usize) -> Option<(usize, Span)> {
+  let mut escaped = false;
   if let Some(bytes) = single_char(bytes, b'<') {
-    let mut size = 2 + spaces;
-    let mut escaped = false;
     let (bytes, url_size) = take_while(bytes, |ch| {
-      if ch == b' ' || ch == b'\r' || ch == b'\n' {
-        return false;
-      }
       if escaped {
         escaped = false;
-        return true;
-      }
-      if ch == b'\\' {
-        escaped = true;
-        return true;
+        return ch != b'\r' && ch != b'\n';
       }
-      if ch == b'>' || ch == b'<' {
-        return false;
-      }
-      true
+      escaped = ch == b'\\';
+      ch != b'<' && ch != b'>' && ch != b'\r' && ch != b'\n'
     });
     single_char(bytes, b'>')?;
     return Some((
-      size + url_size + 1,
+      url_size + 2,
       Span {
-        start: offset + size,
-        end: offset + size + url_size,
+        start: offset + 1,
+        end: offset + 1 + url_size,
       },
     ));
-  } else {
-    let mut nested = 0;
-    let mut escaped = false;
-    let (bytes, url_size) = take_while(bytes, |ch| {
-      if ch == b' ' || ch == b'\r' || ch == b'\n' {
+  }
+  let mut nested = 0;
+  let (_, url_size) = take_while(bytes, |ch| {
+    if ch <= b' ' || ch == 0x7f {
+      return false;
+    }
+    if escaped {
+      escaped = false;
+      return true;
+    }
+    escaped = ch == b'\\';
+    if ch == b'(' {
+      nested += 1;
+    } else if ch == b')' {
+      if nested == 0 {
         return false;
       }
-      if escaped {
-        escaped = false;
-        return true;
-      }
-      if ch == b'\\' {
-        escaped = true;
-        return true;
-      }
-      if ch == b'(' {
-        nested += 1;
-      }
-      if ch == b')' {
-        if nested == 0 {
-          return false;
+      nested -= 1;
+    }
+    true
+  });
+  if nested != 0 {
+    return None;
+  }
+  Some((
+    url_size,
+    Span {
+      start: offset,
+      end: offset + url_size,
+    },
+  ))
+}
+
+// backslash escaped ASCII punctuation as the character itself
+pub fn unescape(text: &str) -> String {
+  let mut unescaped = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+  while let Some(ch) = chars.next() {
+    if ch == '\\' {
+      if let Some(&next) = chars.peek() {
+        if next.is_ascii_punctuation() {
+          unescaped.push(next);
+          chars.next();
+          continue;
         }
-        nested -= 1;
       }
-      true
-    });
-    if url_size > 0 && nested == 0 {
-      return Some((
-        url_size + 1,
-        Span {
-          start: offset + 1,
-          end: offset + 1 + url_size,
-        },
-      ));
+    }
+    unescaped.push(ch);
+  }
+  unescaped
+}
+
+// line endings become spaces, one space is stripped from each side unless the code is all spaces
+pub fn code_span_value(source: &str, spans: &[Span]) -> String {
+  let code: String = spans
+    .iter()
+    .map(|span| &source[span.start..span.end])
+    .collect::<String>()
+    .replace("\r\n", " ")
+    .replace('\n', " ");
+  if code.len() >= 2
+    && code.starts_with(' ')
+    && code.ends_with(' ')
+    && !code.bytes().all(|ch| ch == b' ')
+  {
+    code[1..code.len() - 1].to_string()
+  } else {
+    code
+  }
+}
+
+pub fn directive_name(bytes: &[u8]) -> Option<usize> {
+  if !bytes.first()?.is_ascii_alphabetic() {
+    return None;
+  }
+  let (_, size) = take_while(bytes, |ch| {
+    ch.is_ascii_alphanumeric() || ch == b'-' || ch == b'_'
+  });
+  Some(size)
+}
+
+// size, label span without brackets
+pub fn directive_label(bytes: &[u8], offset: usize) -> Option<(usize, Span)> {
+  single_char(bytes, b'[')?;
+  let mut nested = 0;
+  let mut escaped = false;
+  for (i, &ch) in bytes.iter().enumerate().skip(1) {
+    match ch {
+      b'\r' | b'\n' => return None,
+      _ if escaped => escaped = false,
+      b'\\' => escaped = true,
+      b'[' => nested += 1,
+      b']' if nested == 0 => {
+        return Some((
+          i + 1,
+          Span {
+            start: offset + 1,
+            end: offset + i,
+          },
+        ))
+      }
+      b']' => nested -= 1,
+      _ => {}
     }
   }
   None
 }
 
-// return (end pos, url span, title spans)
-pub fn link_url_title<'a, F>(mut get_raw_span: F) -> Option<(usize, Span, Vec<Span>)>
-where
-  F: FnMut() -> Option<(&'a [u8], usize)>,
-{
-  let (bytes, mut raw_start) = get_raw_span()?;
-  let (url_size, url_span) = link_url(bytes, raw_start)?;
-  let mut bytes = &bytes[url_size..];
-  let mut title: Vec<Span> = vec![];
-  let mut title_ch: Option<u8> = None;
-  let mut title_end = false;
-  let mut raw_start = raw_start + url_size;
+fn directive_attr_value(bytes: &[u8], offset: usize) -> Option<(usize, Span)> {
+  let quote = *bytes.first()?;
+  if quote == b'"' || quote == b'\'' {
+    let (rest, size) = take_while(&bytes[1..], |ch| ch != quote && ch != b'\r' && ch != b'\n');
+    single_char(rest, quote)?;
+    return Some((
+      size + 2,
+      Span {
+        start: offset + 1,
+        end: offset + 1 + size,
+      },
+    ));
+  }
+  let (_, size) = take_while(bytes, |ch| {
+    !matches!(
+      ch,
+      b'"' | b'\'' | b'=' | b'<' | b'>' | b'`' | b'}' | b'\0'..=b' '
+    )
+  });
+  if size == 0 {
+    return None;
+  }
+  Some((
+    size,
+    Span {
+      start: offset,
+      end: offset + size,
+    },
+  ))
+}
+
+// `{#id .class key=value key="value" key}`, size and attributes
+pub fn directive_attributes(bytes: &[u8], offset: usize) -> Option<(usize, Vec<DirectiveAttr>)> {
+  single_char(bytes, b'{')?;
+  let mut attributes = vec![];
+  let mut pos = 1;
   loop {
-    let mut index = 0;
-    let mut escaped = false;
-    let mut title_start: Option<usize> = if title_ch.is_some() && !title_end {
-      Some(0)
-    } else {
-      None
-    };
-    while index < bytes.len() {
-      let byte = bytes[index];
-      if title_end {
-        if byte == b')' {
-          return Some((raw_start + index, url_span, title));
-        }
-        if byte != b' ' && byte != b'\r' && byte != b'\n' {
-          return None;
-        }
+    let (_, spaces) = spaces0(&bytes[pos..]);
+    pos += spaces;
+    let ch = *bytes.get(pos)?;
+    if ch == b'}' {
+      return Some((pos + 1, attributes));
+    }
+    if pos > 1 && spaces == 0 {
+      return None;
+    }
+    if ch == b'#' || ch == b'.' {
+      let (_, size) = take_while(&bytes[pos + 1..], |ch| {
+        !matches!(
+          ch,
+          b'"' | b'\'' | b'=' | b'<' | b'>' | b'`' | b'}' | b'#' | b'.' | b'\0'..=b' '
+        )
+      });
+      if size == 0 {
+        return None;
       }
-      if title_ch.is_none() {
-        if byte == b')' {
-          return Some((raw_start + index, url_span, title));
-        }
-        if byte == b'"' || byte == b'\'' {
-          title_start = Some(index + 1);
-          title_ch = Some(byte);
-        } else if byte != b' ' && byte != b'\r' && byte != b'\n' {
-          return None;
-        }
+      let span = Span {
+        start: offset + pos + 1,
+        end: offset + pos + 1 + size,
+      };
+      attributes.push(if ch == b'#' {
+        DirectiveAttr::Id(span)
       } else {
-        if escaped {
-          escaped = false;
-        } else if byte == b'\\' {
-          escaped = true;
-        } else if byte == title_ch.unwrap() {
-          if let Some(title_start) = title_start {
-            title.push(Span {
-              start: title_start + raw_start,
-              end: raw_start + index,
-            });
-          }
-          title_end = true;
-        }
-      }
-      index += 1;
-    }
-    if let Some(title_start) = title_start {
-      title.push(Span {
-        start: title_start + raw_start,
-        end: raw_start + bytes.len(),
+        DirectiveAttr::Class(span)
       });
+      pos += size + 1;
+      continue;
     }
-    if let Some((next_bytes, next_raw_start)) = get_raw_span() {
-      bytes = next_bytes;
-      raw_start = next_raw_start;
+    if !(ch.is_ascii_alphabetic() || ch == b'_' || ch == b':') {
+      return None;
+    }
+    let (_, key_size) = take_while(&bytes[pos..], |ch| {
+      ch.is_ascii_alphanumeric() || ch == b'_' || ch == b':' || ch == b'.' || ch == b'-'
+    });
+    let key = Span {
+      start: offset + pos,
+      end: offset + pos + key_size,
+    };
+    pos += key_size;
+    if let Some(rest) = single_char(&bytes[pos..], b'=') {
+      let (size, value) = directive_attr_value(rest, offset + pos + 1)?;
+      pos += size + 1;
+      attributes.push(DirectiveAttr::KeyValue { key, value });
     } else {
-      break;
+      attributes.push(DirectiveAttr::KeyValue {
+        key,
+        value: Span {
+          start: offset + pos,
+          end: offset + pos,
+        },
+      });
     }
   }
-  None
 }
 
-pub fn is_left_flanking_delimiter(bytes: &[u8], start: usize, end: usize) -> bool {
-  let len = bytes.len();
-  if end >= len || bytes[end].is_ascii_whitespace() {
-    return false;
-  }
-  let next_byte = if let Some(c) = bytes.get(end + 1) {
-    c
+// `name[label]{attributes}` following the colons
+pub fn directive(bytes: &[u8], offset: usize) -> Option<(usize, Directive)> {
+  let name_size = directive_name(bytes)?;
+  let mut size = name_size;
+  let label = if let Some((label_size, label)) = directive_label(&bytes[size..], offset + size) {
+    size += label_size;
+    Some(label)
   } else {
-    return true;
+    None
   };
-  // TODO
-  if !next_byte.is_ascii_punctuation()
-    || (start == 0
-      || bytes[start - 1].is_ascii_whitespace()
-      || bytes[start - 1].is_ascii_punctuation())
-  {
-    return true;
-  }
-  return false;
+  let attributes =
+    if let Some((attrs_size, attributes)) = directive_attributes(&bytes[size..], offset + size) {
+      size += attrs_size;
+      attributes
+    } else {
+      vec![]
+    };
+  Some((
+    size,
+    Directive {
+      name: Span {
+        start: offset,
+        end: offset + name_size,
+      },
+      label,
+      attributes,
+    },
+  ))
 }
 
-pub fn is_right_flanking_delimiter(bytes: &[u8], start: usize, end: usize) -> bool {
-  let len = bytes.len();
-  if start == 0 || bytes[start - 1].is_ascii_whitespace() {
-    return false;
+// size, fence size, directive
+pub fn open_container_directive(bytes: &[u8], offset: usize) -> Option<(usize, usize, Directive)> {
+  let (bytes, fence) = ch_repeat_min(bytes, b':', 3)?;
+  let (size, directive) = directive(bytes, offset + fence)?;
+  let (bytes, spaces) = spaces0(&bytes[size..]);
+  let (_, eol_size) = eol(bytes)?;
+  Some((fence + size + spaces + eol_size, fence, directive))
+}
+
+pub fn close_container_directive(bytes: &[u8], fence: usize) -> Option<usize> {
+  close_fenced_code(bytes, b':', fence)
+}
+
+pub fn leaf_directive(bytes: &[u8], offset: usize) -> Option<(usize, Directive)> {
+  let bytes = tag(bytes, b"::")?;
+  let (size, directive) = directive(bytes, offset + 2)?;
+  let (bytes, spaces) = spaces0(&bytes[size..]);
+  let (_, eol_size) = eol(bytes)?;
+  Some((2 + size + spaces + eol_size, directive))
+}
+
+// unicode P and S categories, approximated by the blocks they live in
+pub fn is_punctuation(ch: char) -> bool {
+  if ch.is_ascii() {
+    return ch.is_ascii_punctuation();
   }
-  let prev_byte = if let Some(c) = bytes.get(start - 1) {
-    c
+  matches!(ch,
+    '\u{a1}'..='\u{bf}'
+    | '\u{d7}'
+    | '\u{f7}'
+    | '\u{2010}'..='\u{2027}'
+    | '\u{2030}'..='\u{205e}'
+    | '\u{20a0}'..='\u{20cf}'
+    | '\u{2100}'..='\u{214f}'
+    | '\u{2190}'..='\u{2bff}'
+    | '\u{2e00}'..='\u{2e7f}'
+    | '\u{3001}'..='\u{3003}'
+    | '\u{3008}'..='\u{3020}'
+    | '\u{ff01}'..='\u{ff0f}'
+    | '\u{ff1a}'..='\u{ff20}'
+    | '\u{ff3b}'..='\u{ff40}'
+    | '\u{ff5b}'..='\u{ff65}'
+  )
+}
+
+// the start and end of a line count as whitespace
+fn is_whitespace_or_none(ch: Option<char>) -> bool {
+  ch.is_none_or(char::is_whitespace)
+}
+
+fn is_some_punctuation(ch: Option<char>) -> bool {
+  ch.is_some_and(is_punctuation)
+}
+
+pub fn is_left_flanking_delimiter(prev: Option<char>, next: Option<char>) -> bool {
+  !is_whitespace_or_none(next)
+    && (!is_some_punctuation(next) || is_whitespace_or_none(prev) || is_some_punctuation(prev))
+}
+
+pub fn is_right_flanking_delimiter(prev: Option<char>, next: Option<char>) -> bool {
+  !is_whitespace_or_none(prev)
+    && (!is_some_punctuation(prev) || is_whitespace_or_none(next) || is_some_punctuation(next))
+}
+
+// can open, can close
+pub fn emphasis_delimiter(ch: u8, prev: Option<char>, next: Option<char>) -> (bool, bool) {
+  let left = is_left_flanking_delimiter(prev, next);
+  let right = is_right_flanking_delimiter(prev, next);
+  if ch == b'_' {
+    (
+      left && (!right || is_some_punctuation(prev)),
+      right && (!left || is_some_punctuation(next)),
+    )
   } else {
-    return true;
-  };
-  // TODO
-  if !prev_byte.is_ascii_punctuation()
-    || (end >= len || bytes[end].is_ascii_whitespace() || bytes[end].is_ascii_punctuation())
-  {
-    return true;
+    (left, right)
   }
-  return false;
 }
 
 pub fn rev_spaces0(bytes: &[u8]) -> usize {
@@ -412,3 +735,41 @@ fn test_scan_atx_heading_start() {
   // println!("{:?}", scan_blank_line(""));
   // println!("{:?}", scan_setext_heading("===== "));
 }
+
+#[test]
+fn test_autolink_literal() {
+  fn size(text: &str) -> Option<&str> {
+    autolink_literal(text.as_bytes()).map(|(size, _)| &text[..size])
+  }
+  assert_eq!(
+    size("www.commonmark.org/a.b."),
+    Some("www.commonmark.org/a.b")
+  );
+  assert_eq!(
+    size("www.google.com/search?q=(business))+ok"),
+    Some("www.google.com/search?q=(business))+ok")
+  );
+  assert_eq!(
+    size("https://en.wikipedia.org/wiki/Markup_(business))"),
+    Some("https://en.wikipedia.org/wiki/Markup_(business)")
+  );
+  assert_eq!(
+    size("www.google.com/search?q=commonmark&hl;"),
+    Some("www.google.com/search?q=commonmark")
+  );
+  assert_eq!(
+    size("www.google.com/search?q=commonmark&hl=en;"),
+    Some("www.google.com/search?q=commonmark&hl=en;")
+  );
+  assert_eq!(
+    size("http://commonmark.org,"),
+    Some("http://commonmark.org")
+  );
+  assert_eq!(size("a.b-c_d@a.b."), Some("a.b-c_d@a.b"));
+  assert_eq!(size("mailto:foo@bar.baz"), Some("mailto:foo@bar.baz"));
+  assert_eq!(size("a.b-c_d@a.b-"), None);
+  assert_eq!(size("www.xxx.yyy_zzz.com"), None);
+  assert_eq!(size("www.a_b.yyy.com"), Some("www.a_b.yyy.com"));
+  assert_eq!(size("http://localhost"), None);
+  assert_eq!(size("www."), None);
+}
@@ -0,0 +1,1285 @@
+use crate::block::frontmatter;
+use crate::footnote::*;
+use crate::html::*;
+use crate::inline::*;
+use crate::md_lexer::*;
+use crate::options::*;
+use crate::toc::*;
+use crate::token::*;
+#[cfg(any(test, feature = "serde"))]
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+// unist point, line and column start at 1; columns and offsets count bytes,
+// `Utf16Index` turns them into the UTF-16 units of JS strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "serde"), derive(Serialize, Deserialize))]
+pub struct Point {
+  pub line: usize,
+  pub column: usize,
+  pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "serde"), derive(Serialize, Deserialize))]
+pub struct Position {
+  pub start: Point,
+  pub end: Point,
+}
+
+// byte offsets to the UTF-16 code units JS strings are indexed with, for the bindings
+pub struct Utf16Index {
+  // byte offset after each non-ASCII char, with the bytes saved up to there
+  saved: Vec<(usize, usize)>,
+}
+
+impl Utf16Index {
+  pub fn new(source: &str) -> Self {
+    let mut saved = vec![];
+    let mut total = 0;
+    for (offset, ch) in source.char_indices().filter(|(_, ch)| !ch.is_ascii()) {
+      total += ch.len_utf8() - ch.len_utf16();
+      saved.push((offset + ch.len_utf8(), total));
+    }
+    Utf16Index { saved }
+  }
+
+  pub fn offset(&self, offset: usize) -> usize {
+    match self.saved.partition_point(|&(end, _)| end <= offset) {
+      0 => offset,
+      index => offset - self.saved[index - 1].1,
+    }
+  }
+
+  pub fn point(&self, point: &Point) -> Point {
+    let line_start = point.offset - (point.column - 1);
+    Point {
+      line: point.line,
+      column: self.offset(point.offset) - self.offset(line_start) + 1,
+      offset: self.offset(point.offset),
+    }
+  }
+
+  pub fn position(&self, position: &mut Position) {
+    position.start = self.point(&position.start);
+    position.end = self.point(&position.end);
+  }
+}
+
+// a problem compiling recovered from, e.g. a `<` written as text
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "serde"), derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+  pub message: String,
+  pub position: Option<Position>,
+}
+
+// mdast nodes with the mdx (`mdxjsEsm`, `mdxJsx*`, `mdx*Expression`), GFM, math
+// and directive extensions. Trees from other tools may come without positions
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+  any(test, feature = "serde"),
+  derive(Serialize, Deserialize),
+  serde(tag = "type", rename_all = "camelCase")
+)]
+pub enum Node {
+  Root {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  MdxjsEsm {
+    value: String,
+    position: Option<Position>,
+  },
+  // frontmatter, without the `---` lines
+  Yaml {
+    value: String,
+    position: Option<Position>,
+  },
+  Paragraph {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  Heading {
+    depth: usize,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  ThematicBreak {
+    position: Option<Position>,
+  },
+  Blockquote {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  List {
+    ordered: bool,
+    start: Option<usize>,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    spread: bool,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  ListItem {
+    checked: Option<bool>,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    spread: bool,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  Code {
+    lang: Option<String>,
+    meta: Option<String>,
+    value: String,
+    position: Option<Position>,
+  },
+  Math {
+    value: String,
+    position: Option<Position>,
+  },
+  FootnoteDefinition {
+    identifier: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    label: String,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  ContainerDirective {
+    name: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(with = "attribute_map"))]
+    attributes: Vec<(String, Option<String>)>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  LeafDirective {
+    name: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(with = "attribute_map"))]
+    attributes: Vec<(String, Option<String>)>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  TextDirective {
+    name: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(with = "attribute_map"))]
+    attributes: Vec<(String, Option<String>)>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  // fragments have no name
+  MdxJsxFlowElement {
+    name: Option<String>,
+    attributes: Vec<JsxAttribute>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  MdxJsxTextElement {
+    name: Option<String>,
+    attributes: Vec<JsxAttribute>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  // the value is the code between the braces
+  MdxFlowExpression {
+    value: String,
+    position: Option<Position>,
+  },
+  MdxTextExpression {
+    value: String,
+    position: Option<Position>,
+  },
+  Text {
+    value: String,
+    position: Option<Position>,
+  },
+  Emphasis {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  Strong {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  Delete {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  InlineCode {
+    value: String,
+    position: Option<Position>,
+  },
+  InlineMath {
+    value: String,
+    position: Option<Position>,
+  },
+  Break {
+    position: Option<Position>,
+  },
+  Link {
+    url: String,
+    title: Option<String>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  Image {
+    url: String,
+    title: Option<String>,
+    alt: Option<String>,
+    position: Option<Position>,
+  },
+  FootnoteReference {
+    identifier: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    label: String,
+    position: Option<Position>,
+  },
+  // the nodes below come from trees of other tools, this parser doesn't build them.
+  // raw HTML of plain markdown
+  Html {
+    value: String,
+    position: Option<Position>,
+  },
+  Definition {
+    identifier: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    label: String,
+    url: String,
+    title: Option<String>,
+    position: Option<Position>,
+  },
+  // `[text][label]`, resolved against the definitions
+  LinkReference {
+    identifier: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    label: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(rename = "referenceType"))]
+    reference_type: ReferenceType,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  ImageReference {
+    identifier: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    label: String,
+    #[cfg_attr(any(test, feature = "serde"), serde(rename = "referenceType"))]
+    reference_type: ReferenceType,
+    alt: Option<String>,
+    position: Option<Position>,
+  },
+  // the first row is the head, `align` has an entry per column
+  Table {
+    #[cfg_attr(any(test, feature = "serde"), serde(default))]
+    align: Vec<Option<String>>,
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  TableRow {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+  TableCell {
+    children: Vec<Node>,
+    position: Option<Position>,
+  },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+  any(test, feature = "serde"),
+  derive(Serialize, Deserialize),
+  serde(rename_all = "lowercase")
+)]
+pub enum ReferenceType {
+  // `[label]`
+  Shortcut,
+  // `[label][]`
+  Collapsed,
+  // `[text][label]`
+  Full,
+}
+
+impl ReferenceType {
+  // what follows the text of an unresolved reference
+  pub fn suffix(&self, identifier: &str, label: &str) -> String {
+    match self {
+      ReferenceType::Shortcut => "]".to_string(),
+      ReferenceType::Collapsed => "][]".to_string(),
+      ReferenceType::Full => {
+        format!("][{}]", if label.is_empty() { identifier } else { label })
+      }
+    }
+  }
+}
+
+impl Node {
+  pub fn position(&self) -> &Option<Position> {
+    match self {
+      Node::Root { position, .. }
+      | Node::MdxjsEsm { position, .. }
+      | Node::Yaml { position, .. }
+      | Node::Paragraph { position, .. }
+      | Node::Heading { position, .. }
+      | Node::ThematicBreak { position, .. }
+      | Node::Blockquote { position, .. }
+      | Node::List { position, .. }
+      | Node::ListItem { position, .. }
+      | Node::Code { position, .. }
+      | Node::Math { position, .. }
+      | Node::FootnoteDefinition { position, .. }
+      | Node::ContainerDirective { position, .. }
+      | Node::LeafDirective { position, .. }
+      | Node::TextDirective { position, .. }
+      | Node::MdxJsxFlowElement { position, .. }
+      | Node::MdxJsxTextElement { position, .. }
+      | Node::MdxFlowExpression { position, .. }
+      | Node::MdxTextExpression { position, .. }
+      | Node::Text { position, .. }
+      | Node::Emphasis { position, .. }
+      | Node::Strong { position, .. }
+      | Node::Delete { position, .. }
+      | Node::InlineCode { position, .. }
+      | Node::InlineMath { position, .. }
+      | Node::Break { position, .. }
+      | Node::Link { position, .. }
+      | Node::Image { position, .. }
+      | Node::FootnoteReference { position, .. }
+      | Node::Html { position, .. }
+      | Node::Definition { position, .. }
+      | Node::LinkReference { position, .. }
+      | Node::ImageReference { position, .. }
+      | Node::Table { position, .. }
+      | Node::TableRow { position, .. }
+      | Node::TableCell { position, .. } => position,
+    }
+  }
+
+  pub fn children(&self) -> &[Node] {
+    match self {
+      Node::Root { children, .. }
+      | Node::Paragraph { children, .. }
+      | Node::Heading { children, .. }
+      | Node::Blockquote { children, .. }
+      | Node::List { children, .. }
+      | Node::ListItem { children, .. }
+      | Node::FootnoteDefinition { children, .. }
+      | Node::ContainerDirective { children, .. }
+      | Node::LeafDirective { children, .. }
+      | Node::TextDirective { children, .. }
+      | Node::MdxJsxFlowElement { children, .. }
+      | Node::MdxJsxTextElement { children, .. }
+      | Node::Emphasis { children, .. }
+      | Node::Strong { children, .. }
+      | Node::Delete { children, .. }
+      | Node::Link { children, .. }
+      | Node::LinkReference { children, .. }
+      | Node::Table { children, .. }
+      | Node::TableRow { children, .. }
+      | Node::TableCell { children, .. } => children,
+      Node::MdxjsEsm { .. }
+      | Node::Yaml { .. }
+      | Node::ThematicBreak { .. }
+      | Node::Code { .. }
+      | Node::Math { .. }
+      | Node::MdxFlowExpression { .. }
+      | Node::MdxTextExpression { .. }
+      | Node::Text { .. }
+      | Node::InlineCode { .. }
+      | Node::InlineMath { .. }
+      | Node::Break { .. }
+      | Node::Image { .. }
+      | Node::FootnoteReference { .. }
+      | Node::Html { .. }
+      | Node::Definition { .. }
+      | Node::ImageReference { .. } => &[],
+    }
+  }
+
+  // every position in the tree, attributes included
+  pub fn for_each_position(&mut self, f: &mut impl FnMut(&mut Position)) {
+    let (position, children, attributes) = match self {
+      Node::MdxJsxFlowElement {
+        position,
+        children,
+        attributes,
+        ..
+      }
+      | Node::MdxJsxTextElement {
+        position,
+        children,
+        attributes,
+        ..
+      } => (position, Some(children), Some(attributes)),
+      Node::Root { position, children }
+      | Node::Paragraph { position, children }
+      | Node::Heading {
+        position, children, ..
+      }
+      | Node::Blockquote { position, children }
+      | Node::List {
+        position, children, ..
+      }
+      | Node::ListItem {
+        position, children, ..
+      }
+      | Node::FootnoteDefinition {
+        position, children, ..
+      }
+      | Node::ContainerDirective {
+        position, children, ..
+      }
+      | Node::LeafDirective {
+        position, children, ..
+      }
+      | Node::TextDirective {
+        position, children, ..
+      }
+      | Node::Emphasis { position, children }
+      | Node::Strong { position, children }
+      | Node::Delete { position, children }
+      | Node::Link {
+        position, children, ..
+      }
+      | Node::LinkReference {
+        position, children, ..
+      }
+      | Node::Table {
+        position, children, ..
+      }
+      | Node::TableRow { position, children }
+      | Node::TableCell { position, children } => (position, Some(children), None),
+      Node::MdxjsEsm { position, .. }
+      | Node::Yaml { position, .. }
+      | Node::ThematicBreak { position }
+      | Node::Code { position, .. }
+      | Node::Math { position, .. }
+      | Node::MdxFlowExpression { position, .. }
+      | Node::MdxTextExpression { position, .. }
+      | Node::Text { position, .. }
+      | Node::InlineCode { position, .. }
+      | Node::InlineMath { position, .. }
+      | Node::Break { position }
+      | Node::Image { position, .. }
+      | Node::FootnoteReference { position, .. }
+      | Node::Html { position, .. }
+      | Node::Definition { position, .. }
+      | Node::ImageReference { position, .. } => (position, None, None),
+    };
+    if let Some(position) = position {
+      f(position);
+    }
+    for attribute in attributes.into_iter().flatten() {
+      match attribute {
+        JsxAttribute::MdxJsxAttribute {
+          value, position, ..
+        } => {
+          if let Some(JsxAttributeValue::Expression(JsxAttributeValueExpression {
+            position: Some(position),
+            ..
+          })) = value
+          {
+            f(position);
+          }
+          if let Some(position) = position {
+            f(position);
+          }
+        }
+        JsxAttribute::MdxJsxExpressionAttribute { position, .. } => {
+          if let Some(position) = position {
+            f(position);
+          }
+        }
+      }
+    }
+    for child in children.into_iter().flatten() {
+      child.for_each_position(f);
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+  any(test, feature = "serde"),
+  derive(Serialize, Deserialize),
+  serde(tag = "type", rename_all = "camelCase")
+)]
+pub enum JsxAttribute {
+  // `true` attributes have no value
+  MdxJsxAttribute {
+    name: String,
+    value: Option<JsxAttributeValue>,
+    position: Option<Position>,
+  },
+  // `{...spread}`, the position is the one of the spread expression
+  MdxJsxExpressionAttribute {
+    value: String,
+    position: Option<Position>,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+  any(test, feature = "serde"),
+  derive(Serialize, Deserialize),
+  serde(untagged)
+)]
+pub enum JsxAttributeValue {
+  Literal(String),
+  Expression(JsxAttributeValueExpression),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+  any(test, feature = "serde"),
+  derive(Serialize, Deserialize),
+  serde(tag = "type", rename = "mdxJsxAttributeValueExpression")
+)]
+pub struct JsxAttributeValueExpression {
+  pub value: String,
+  // not in mdast, where the estree carries the locations
+  pub position: Option<Position>,
+}
+
+// directive attributes are an object in mdast, kept in source order here
+#[cfg(any(test, feature = "serde"))]
+mod attribute_map {
+  use serde::de::{MapAccess, Visitor};
+  use serde::{Deserializer, Serializer};
+  use std::fmt;
+
+  pub fn serialize<S: Serializer>(
+    attributes: &[(String, Option<String>)],
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(attributes.iter().map(|(key, value)| (key, value)))
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Vec<(String, Option<String>)>, D::Error> {
+    struct AttributeVisitor;
+
+    impl<'de> Visitor<'de> for AttributeVisitor {
+      type Value = Vec<(String, Option<String>)>;
+
+      fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of attribute strings or nulls")
+      }
+
+      fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut attributes = vec![];
+        while let Some(entry) = map.next_entry()? {
+          attributes.push(entry);
+        }
+        Ok(attributes)
+      }
+    }
+
+    deserializer.deserialize_map(AttributeVisitor)
+  }
+}
+
+pub struct MdastBuilder<'a> {
+  source: &'a str,
+  bytes: &'a [u8],
+  options: &'a CompileOptions,
+  special_bytes: &'a SpecialBytes,
+  line_starts: Vec<usize>,
+  diagnostics: RefCell<Vec<Diagnostic>>,
+}
+
+impl<'a> MdastBuilder<'a> {
+  pub fn new(
+    source: &'a str,
+    bytes: &'a [u8],
+    options: &'a CompileOptions,
+    special_bytes: &'a SpecialBytes,
+  ) -> Self {
+    let mut line_starts = vec![0];
+    for (i, &byte) in bytes.iter().enumerate() {
+      if byte == b'\n' {
+        line_starts.push(i + 1);
+      }
+    }
+    MdastBuilder {
+      source,
+      bytes,
+      options,
+      special_bytes,
+      line_starts,
+      diagnostics: RefCell::new(vec![]),
+    }
+  }
+
+  pub fn build(&self, ast: &AST<Token<BlockToken>>) -> Node {
+    let mut children = vec![];
+    let mut start = 0;
+    if let Some((span, end)) = frontmatter(self.source).filter(|_| self.options.frontmatter) {
+      children.push(Node::Yaml {
+        value: self.source[span.start..span.end].to_string(),
+        position: self.block_position(&Span { start: 0, end }),
+      });
+      start = end;
+    }
+    // the leading import/export block, not parsed as markdown
+    let value = self.source[start..ast.span.start].trim_end();
+    let esm = value.trim_start_matches(['\r', '\n']);
+    if !esm.is_empty() {
+      let start = start + value.len() - esm.len();
+      children.push(Node::MdxjsEsm {
+        value: esm.to_string(),
+        position: self.position(&Span {
+          start,
+          end: start + esm.len(),
+        }),
+      });
+    }
+    children.extend(self.blocks(&ast.children, false));
+    Node::Root {
+      children,
+      position: self.position(&Span {
+        start: 0,
+        end: self.source.len(),
+      }),
+    }
+  }
+
+  // recovered from while building, in source order per paragraph
+  pub fn diagnostics(self) -> Vec<Diagnostic> {
+    self.diagnostics.into_inner()
+  }
+
+  fn point(&self, offset: usize) -> Point {
+    let line = match self.line_starts.binary_search(&offset) {
+      Ok(line) => line,
+      Err(line) => line - 1,
+    };
+    Point {
+      line: line + 1,
+      column: offset - self.line_starts[line] + 1,
+      offset,
+    }
+  }
+
+  fn position(&self, span: &Span) -> Option<Position> {
+    Some(Position {
+      start: self.point(span.start),
+      end: self.point(span.end),
+    })
+  }
+
+  // block spans take the line ending along, positions end before it
+  fn block_position(&self, span: &Span) -> Option<Position> {
+    let mut end = span.end;
+    while end > span.start && matches!(self.bytes[end - 1], b'\n' | b'\r') {
+      end -= 1;
+    }
+    self.position(&Span {
+      start: span.start,
+      end,
+    })
+  }
+
+  fn text(&self, spans: &[Span]) -> String {
+    spans
+      .iter()
+      .map(|span| &self.source[span.start..span.end])
+      .collect()
+  }
+
+  fn blocks(&self, blocks: &[Token<BlockToken>], spread: bool) -> Vec<Node> {
+    blocks
+      .iter()
+      .filter_map(|block| self.block(block, spread))
+      .collect()
+  }
+
+  fn block(&self, block: &Token<BlockToken>, spread: bool) -> Option<Node> {
+    let position = self.block_position(&block.span);
+    let node = match &block.value {
+      BlockToken::Paragraph { raws } => Node::Paragraph {
+        children: self.raws(raws),
+        position,
+      },
+      BlockToken::ATXHeading { level, raws } | BlockToken::SetextHeading { level, raws } => {
+        Node::Heading {
+          depth: level.depth(),
+          children: self.raws(raws),
+          position,
+        }
+      }
+      BlockToken::ThematicBreak => Node::ThematicBreak { position },
+      BlockToken::BlockQuote { blocks, alert, .. } => {
+        let mut children = self.blocks(blocks, false);
+        if alert.is_some() {
+          self.restore_alert_marker(&block.span, &mut children);
+        }
+        Node::Blockquote { children, position }
+      }
+      BlockToken::List {
+        ch,
+        order_span,
+        blocks,
+        ..
+      } => {
+        let ordered = *ch == b'.' || *ch == b')';
+        let spread = !is_tight_list(blocks);
+        Node::List {
+          ordered,
+          start: if ordered {
+            self.source[order_span.start..order_span.end].parse().ok()
+          } else {
+            None
+          },
+          spread,
+          children: self.blocks(blocks, spread),
+          position,
+        }
+      }
+      BlockToken::ListItem {
+        blocks, checked, ..
+      } => Node::ListItem {
+        checked: *checked,
+        spread,
+        children: self.blocks(blocks, false),
+        position,
+      },
+      BlockToken::FencedCode {
+        meta_span,
+        code_spans,
+      } => {
+        let info = unescape(self.source[meta_span.start..meta_span.end].trim());
+        let (lang, meta) = match info.split_once(char::is_whitespace) {
+          Some((lang, meta)) => (Some(lang.to_string()), Some(meta.trim().to_string())),
+          None if info.is_empty() => (None, None),
+          None => (Some(info), None),
+        };
+        Node::Code {
+          lang,
+          meta,
+          value: trim_final_eol(self.text(code_spans)),
+          position,
+        }
+      }
+      BlockToken::IndentedCode(code_spans) => Node::Code {
+        lang: None,
+        meta: None,
+        value: trim_final_eol(self.text(code_spans)),
+        position,
+      },
+      BlockToken::Math(spans) => Node::Math {
+        value: trim_final_eol(self.text(spans)),
+        position,
+      },
+      BlockToken::LeafDirective(directive) => {
+        let children = match &directive.label {
+          Some(label) => self.raws(std::slice::from_ref(label)),
+          None => vec![],
+        };
+        Node::LeafDirective {
+          name: self.source[directive.name.start..directive.name.end].to_string(),
+          attributes: self.directive_attributes(directive),
+          children,
+          position,
+        }
+      }
+      BlockToken::ContainerDirective { directive, blocks } => {
+        // the label becomes a leading paragraph
+        let mut children = vec![];
+        if let Some(label) = &directive.label {
+          children.push(Node::Paragraph {
+            children: self.raws(std::slice::from_ref(label)),
+            position: self.position(label),
+          });
+        }
+        children.extend(self.blocks(blocks, false));
+        Node::ContainerDirective {
+          name: self.source[directive.name.start..directive.name.end].to_string(),
+          attributes: self.directive_attributes(directive),
+          children,
+          position,
+        }
+      }
+      BlockToken::FootnoteDefinition { label, blocks } => {
+        let label = &self.source[label.start..label.end];
+        Node::FootnoteDefinition {
+          identifier: normalize_label(label),
+          label: label.to_string(),
+          children: self.blocks(blocks, false),
+          position,
+        }
+      }
+      BlockToken::JSX(element) => self.jsx_element(element, true),
+      BlockToken::BlankLine => return None,
+    };
+    Some(node)
+  }
+
+  // GitHub alerts are no mdast node, the `[!NOTE]` marker the block parser took
+  // goes back into the text, where codegen picks it up again
+  fn restore_alert_marker(&self, span: &Span, children: &mut Vec<Node>) {
+    let start = match self.source[span.start..span.end].find("[!") {
+      Some(offset) => span.start + offset,
+      None => return,
+    };
+    let end = match self.source[start..].find(']') {
+      Some(offset) => start + offset + 1,
+      None => return,
+    };
+    let marker = &self.source[start..end];
+    let marker_position = self.position(&Span { start, end });
+    let marker_line = self.point(start).line;
+    if let Some(Node::Paragraph {
+      children: inlines,
+      position: Some(position),
+    }) = children.first_mut()
+    {
+      // the rest of the marker paragraph
+      if position.start.line == marker_line + 1 {
+        let value = format!("{}\n", marker);
+        match inlines.first_mut() {
+          Some(Node::Text {
+            value: text,
+            position: text_position,
+          }) => {
+            text.insert_str(0, &value);
+            if let Some(text_position) = text_position {
+              text_position.start = self.point(start);
+            }
+          }
+          _ => inlines.insert(
+            0,
+            Node::Text {
+              value,
+              position: marker_position,
+            },
+          ),
+        }
+        position.start = self.point(start);
+        return;
+      }
+    }
+    children.insert(
+      0,
+      Node::Paragraph {
+        children: vec![Node::Text {
+          value: marker.to_string(),
+          position: marker_position.clone(),
+        }],
+        position: marker_position,
+      },
+    );
+  }
+
+  fn directive_attributes(&self, directive: &Directive) -> Vec<(String, Option<String>)> {
+    let mut attributes = vec![];
+    let mut class_names: Vec<&str> = vec![];
+    for attr in &directive.attributes {
+      match attr {
+        DirectiveAttr::Id(span) => {
+          attributes.push((
+            "id".to_string(),
+            Some(self.source[span.start..span.end].to_string()),
+          ));
+        }
+        DirectiveAttr::Class(span) => {
+          class_names.push(&self.source[span.start..span.end]);
+        }
+        DirectiveAttr::KeyValue { key, value } => {
+          let key = &self.source[key.start..key.end];
+          let value = &self.source[value.start..value.end];
+          if key == "class" {
+            class_names.extend(value.split_whitespace());
+          } else {
+            attributes.push((key.to_string(), Some(value.to_string())));
+          }
+        }
+      }
+    }
+    if !class_names.is_empty() {
+      attributes.push(("class".to_string(), Some(class_names.join(" "))));
+    }
+    attributes
+  }
+
+  fn raws(&self, raws: &[Span]) -> Vec<Node> {
+    if raws.is_empty() {
+      return vec![];
+    }
+    let raws = raws.to_vec();
+    let mut inline_parser = InlineParser::new(
+      self.source,
+      self.bytes,
+      &raws,
+      self.options,
+      self.special_bytes,
+    );
+    let inlines = inline_parser.parse().children;
+    self
+      .diagnostics
+      .borrow_mut()
+      .extend(
+        inline_parser
+          .diagnostics
+          .iter()
+          .map(|diagnostic| Diagnostic {
+            message: diagnostic.value.to_string(),
+            position: self.position(&diagnostic.span),
+          }),
+      );
+    self.inlines(&inlines)
+  }
+
+  fn inlines(&self, inlines: &[Token<InlineToken>]) -> Vec<Node> {
+    let mut nodes = vec![];
+    for inline in inlines {
+      let position = self.position(&inline.span);
+      let node = match &inline.value {
+        InlineToken::Text(spans) => Node::Text {
+          value: self.text(spans),
+          position,
+        },
+        InlineToken::SoftBreak => Node::Text {
+          value: "\n".to_string(),
+          position,
+        },
+        InlineToken::HardBreak => Node::Break { position },
+        InlineToken::Code(spans) => Node::InlineCode {
+          value: code_span_value(self.source, spans),
+          position,
+        },
+        InlineToken::Math(spans) => Node::InlineMath {
+          value: self.text(spans),
+          position,
+        },
+        InlineToken::Emphasis(children) => Node::Emphasis {
+          children: self.inlines(children),
+          position,
+        },
+        InlineToken::Strong(children) => Node::Strong {
+          children: self.inlines(children),
+          position,
+        },
+        InlineToken::Strikethrough(children) => Node::Delete {
+          children: self.inlines(children),
+          position,
+        },
+        InlineToken::Link {
+          url,
+          title,
+          text_children,
+        } => Node::Link {
+          url: unescape(&self.source[url.start..url.end]),
+          title: self.title(title),
+          children: self.inlines(text_children),
+          position,
+        },
+        InlineToken::Image {
+          url,
+          title,
+          alt_children,
+        } => Node::Image {
+          url: unescape(&self.source[url.start..url.end]),
+          title: self.title(title),
+          alt: Some(inlines_text(self.source, alt_children)),
+          position,
+        },
+        InlineToken::AutoLink { kind, url } => {
+          let text = &self.source[url.start..url.end];
+          Node::Link {
+            url: format!("{}{}", kind.href_prefix(), text),
+            title: None,
+            children: vec![Node::Text {
+              value: text.to_string(),
+              position: self.position(url),
+            }],
+            position,
+          }
+        }
+        InlineToken::TextDirective {
+          directive,
+          children,
+        } => Node::TextDirective {
+          name: self.source[directive.name.start..directive.name.end].to_string(),
+          attributes: self.directive_attributes(directive),
+          children: self.inlines(children),
+          position,
+        },
+        InlineToken::FootnoteReference(label) => {
+          let label = &self.source[label.start..label.end];
+          Node::FootnoteReference {
+            identifier: normalize_label(label),
+            label: label.to_string(),
+            position,
+          }
+        }
+        InlineToken::JSX(element) => self.jsx_element(element, false),
+        _ => continue,
+      };
+      push_node(&mut nodes, node);
+    }
+    nodes
+  }
+
+  fn title(&self, title: &[Span]) -> Option<String> {
+    if title.is_empty() {
+      None
+    } else {
+      Some(unescape(&self.text(title)))
+    }
+  }
+
+  fn jsx_element(&self, element: &JSXElement, flow: bool) -> Node {
+    let JSXElement {
+      tag,
+      attributes,
+      children,
+      span,
+    } = element;
+    let name = if tag.is_empty() {
+      None
+    } else {
+      Some(tag.clone())
+    };
+    let attributes = attributes
+      .iter()
+      .map(|attr| match attr {
+        JSXAttr::KeyLiteralValue { key, value } => JsxAttribute::MdxJsxAttribute {
+          name: self.source[key.start..key.end].to_string(),
+          // without the quotes
+          value: Some(JsxAttributeValue::Literal(
+            self.source[value.start + 1..value.end - 1].to_string(),
+          )),
+          position: self.position(&Span {
+            start: key.start,
+            end: value.end,
+          }),
+        },
+        JSXAttr::KeyTrueValue { key } => JsxAttribute::MdxJsxAttribute {
+          name: self.source[key.start..key.end].to_string(),
+          value: None,
+          position: self.position(key),
+        },
+        JSXAttr::KeyValue { key, value } => {
+          let value_span = expression_span(value);
+          JsxAttribute::MdxJsxAttribute {
+            name: self.source[key.start..key.end].to_string(),
+            value: Some(JsxAttributeValue::Expression(JsxAttributeValueExpression {
+              value: self.expression_value(value),
+              position: self.position(&value_span),
+            })),
+            position: self.position(&Span {
+              start: key.start,
+              end: value_span.end,
+            }),
+          }
+        }
+        JSXAttr::Spread(segments) => JsxAttribute::MdxJsxExpressionAttribute {
+          value: format!("...{}", self.expression_value(segments)),
+          position: self.position(&expression_span(segments)),
+        },
+      })
+      .collect();
+    let mut nodes = vec![];
+    for child in children {
+      let node = match child {
+        JSX::Text(span) => Node::Text {
+          value: self.source[span.start..span.end].to_string(),
+          position: self.position(span),
+        },
+        JSX::Element(element) => self.jsx_element(element, flow),
+        JSX::Expression(segments) => {
+          let value = self.expression_value(segments);
+          let position = self.position(&expression_span(segments));
+          if flow {
+            Node::MdxFlowExpression { value, position }
+          } else {
+            Node::MdxTextExpression { value, position }
+          }
+        }
+      };
+      push_node(&mut nodes, node);
+    }
+    let position = self.position(span);
+    if flow {
+      Node::MdxJsxFlowElement {
+        name,
+        attributes,
+        children: nodes,
+        position,
+      }
+    } else {
+      Node::MdxJsxTextElement {
+        name,
+        attributes,
+        children: nodes,
+        position,
+      }
+    }
+  }
+
+  fn expression_value(&self, segments: &[JSXExpressionSegment]) -> String {
+    let span = expression_span(segments);
+    self.source[span.start..span.end].to_string()
+  }
+}
+
+// a blockquote starting with a `[!NOTE]` line, the children without the marker
+pub fn alert(children: &[Node]) -> Option<(Alert, Vec<Node>)> {
+  let (inlines, position) = match children.first() {
+    Some(Node::Paragraph { children, position }) => (children, position),
+    _ => return None,
+  };
+  let text = match inlines.first() {
+    Some(Node::Text { value, .. }) => value,
+    _ => return None,
+  };
+  let (marker, rest) = match text.find('\n') {
+    Some(end) => (&text[..end], Some(&text[end + 1..])),
+    // a paragraph of only the marker
+    None if inlines.len() == 1 => (text.as_str(), None),
+    None => return None,
+  };
+  let alert = alert_marker(marker.as_bytes())?;
+  let mut children = children.to_vec();
+  match rest {
+    None => {
+      children.remove(0);
+    }
+    Some(rest) => {
+      let mut inlines = inlines.clone();
+      if rest.is_empty() {
+        inlines.remove(0);
+      } else if let Some(Node::Text { value, .. }) = inlines.first_mut() {
+        *value = rest.to_string();
+      }
+      children[0] = Node::Paragraph {
+        children: inlines,
+        position: position.clone(),
+      };
+    }
+  }
+  Some((alert, children))
+}
+
+// from the first to the last segment, the braces are left out
+fn expression_span(segments: &[JSXExpressionSegment]) -> Span {
+  let span = |segment: &JSXExpressionSegment| match segment {
+    JSXExpressionSegment::JS(span) => span.clone(),
+    JSXExpressionSegment::Element(element) => element.span.clone(),
+  };
+  match (segments.first(), segments.last()) {
+    (Some(first), Some(last)) => Span {
+      start: span(first).start,
+      end: span(last).end,
+    },
+    _ => Span { start: 0, end: 0 },
+  }
+}
+
+fn trim_final_eol(mut value: String) -> String {
+  if value.ends_with('\n') {
+    value.pop();
+    if value.ends_with('\r') {
+      value.pop();
+    }
+  }
+  value
+}
+
+// adjacent text, like text around a soft break or an escape, is one node
+fn push_node(nodes: &mut Vec<Node>, node: Node) {
+  if let (
+    Some(Node::Text {
+      value: last_value,
+      position: last_position,
+    }),
+    Node::Text { value, position },
+  ) = (nodes.last_mut(), &node)
+  {
+    last_value.push_str(value);
+    if let (Some(last_position), Some(position)) = (last_position, position) {
+      last_position.end = position.end.clone();
+    }
+    return;
+  }
+  nodes.push(node);
+}
+
+#[test]
+fn test_parse_to_ast() {
+  let source = "import Chart from './chart'\n\n# Hello *world*\n\n- [x] done\n\n```js title=\"a\"\nlet a = 1;\n```\n\n<Chart kind=\"bar\" animate data={[1, 2]}>Sales {year}</Chart>\n\nA [link](/url \"t\") and `code`\nnext line\n";
+  let options = CompileOptions {
+    task_lists: true,
+    ..Default::default()
+  };
+  let ast = crate::parse_to_ast(source, &options);
+  insta::assert_yaml_snapshot!(ast);
+}
+
+#[test]
+fn test_frontmatter() {
+  let source = "---\ntitle: Hi\n---\n\nimport A from 'a'\n\n# Hello\n";
+  let options = CompileOptions {
+    frontmatter: true,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert_eq!(result.frontmatter.as_deref(), Some("title: Hi"));
+
+  let ast = crate::parse_to_ast(source, &options);
+  let children = match &ast {
+    Node::Root { children, .. } => children,
+    _ => unreachable!(),
+  };
+  assert!(matches!(&children[0], Node::Yaml { value, .. } if value == "title: Hi"));
+  assert!(
+    matches!(&children[1], Node::MdxjsEsm { value, position } if value == "import A from 'a'" && position.as_ref().unwrap().start.line == 5)
+  );
+
+  // without the option the lines are markdown
+  let result = crate::compile(source, &CompileOptions::default());
+  assert_eq!(result.frontmatter, None);
+}
+
+#[test]
+fn test_diagnostics() {
+  let source = "# Title\n\nsome <Foo bar={>\n\n2 < 3 and \\<Foo>\n";
+  let result = crate::compile(source, &CompileOptions::default());
+  let positions: Vec<_> = result
+    .diagnostics
+    .iter()
+    .filter_map(|diagnostic| diagnostic.position.as_ref())
+    .map(|position| (position.start.line, position.start.column))
+    .collect();
+  assert_eq!(positions, [(3, 6)]);
+}
+
+#[test]
+fn test_utf16_positions() {
+  let source = "# héllo 👋\n\nsé *x* <A b={1} />\n";
+  let mut ast = crate::parse_to_ast(source, &CompileOptions::default());
+  let index = Utf16Index::new(source);
+  ast.for_each_position(&mut |position| index.position(position));
+  let Node::Root { children, position } = &ast else {
+    panic!()
+  };
+  assert_eq!(
+    position.as_ref().unwrap().end.offset,
+    source.encode_utf16().count()
+  );
+  // the heading ends after the surrogate pair, 10 code units in
+  let heading = children[0].position().as_ref().unwrap();
+  assert_eq!((heading.end.column, heading.end.offset), (11, 10));
+  let Node::Paragraph { children, .. } = &children[1] else {
+    panic!()
+  };
+  let emphasis = children[1].position().as_ref().unwrap();
+  assert_eq!((emphasis.start.line, emphasis.start.column), (3, 4));
+  assert_eq!(emphasis.start.offset, 15);
+  let Node::MdxJsxTextElement { attributes, .. } = &children[3] else {
+    panic!("{:?}", children)
+  };
+  let JsxAttribute::MdxJsxAttribute { position, .. } = &attributes[0] else {
+    panic!()
+  };
+  assert_eq!(position.as_ref().unwrap().start.column, 11);
+}
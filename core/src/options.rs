@@ -0,0 +1,228 @@
+use crate::source_map::SourceMapKind;
+use crate::target::TargetKind;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Deserialize),
+  serde(default, rename_all = "camelCase")
+)]
+pub struct CompileOptions {
+  // emit `export const toc = [...]` after the default export
+  pub toc_export: bool,
+  // headings outside of min..=max depth are left out of the toc
+  pub toc_min_depth: usize,
+  pub toc_max_depth: usize,
+  // `$inline$` and `$$` fenced display math, `$` is literal otherwise
+  pub math: bool,
+  // `:text`, `::leaf` and `:::container` directives, emitted as elements named after them
+  pub directives: bool,
+  // GitHub `> [!NOTE]` alerts
+  pub alerts: bool,
+  // element for alerts, receives a `type` prop; `blockquote` with `data-alert` otherwise
+  pub alert_component: Option<String>,
+  // GFM `- [ ]` / `- [x]` task list items
+  pub task_lists: bool,
+  // element for the task checkbox, receives a `checked` prop; a disabled `input` otherwise
+  pub checkbox_component: Option<String>,
+  // GFM `[^label]` footnotes, rendered as a section after the content
+  pub footnotes: bool,
+  // prefix of footnote element ids, keeps documents on one page apart
+  pub footnote_id_prefix: String,
+  // GFM bare `https://`, `www.` and email links
+  pub autolink_literals: bool,
+  // leading `---` YAML frontmatter, left out of the content and returned as written
+  pub frontmatter: bool,
+  // path of the compiled file, the source name in source maps and development output
+  pub filepath: Option<String>,
+  // `jsxDEV` calls from `react/jsx-dev-runtime` with the source location of elements
+  pub development: bool,
+  // source map of the generated code back to the markdown, none by default
+  pub source_map: Option<SourceMapKind>,
+  // module shape of the generated code, an ES module by default
+  pub output_format: OutputFormat,
+  // `_jsxRuntime.jsx` calls, or `pragma(type, props, ...children)` for runtimes without
+  // an automatic JSX runtime; React and Preact targets only
+  pub jsx_runtime: JsxRuntime,
+  // element and fragment of the classic runtime, e.g. `h` and `Fragment`
+  pub pragma: String,
+  pub pragma_frag: String,
+  // JSX source instead of calls, for tools that compile JSX themselves; the runtime
+  // and development options don't apply to it
+  pub jsx: bool,
+  // framework the elements are created for, prop names follow it in JSX output too
+  pub target: TargetKind,
+  // package the runtime is imported from, e.g. `react` for `react/jsx-runtime`; left to
+  // the host as `_jsxRuntime` otherwise
+  pub jsx_import_source: Option<String>,
+  // package exporting `useMDXComponents`, e.g. `@mdx-js/react`; components that aren't
+  // imported come from it and the `components` prop
+  pub provider_import_source: Option<String>,
+}
+
+impl Default for CompileOptions {
+  fn default() -> Self {
+    Self {
+      toc_export: false,
+      toc_min_depth: 1,
+      toc_max_depth: 6,
+      math: false,
+      directives: false,
+      alerts: false,
+      alert_component: None,
+      task_lists: false,
+      checkbox_component: None,
+      footnotes: false,
+      footnote_id_prefix: "user-content-".to_string(),
+      autolink_literals: false,
+      frontmatter: false,
+      filepath: None,
+      development: false,
+      source_map: None,
+      output_format: OutputFormat::Program,
+      jsx_runtime: JsxRuntime::Automatic,
+      pragma: "React.createElement".to_string(),
+      pragma_frag: "React.Fragment".to_string(),
+      jsx: false,
+      target: TargetKind::React,
+      jsx_import_source: None,
+      provider_import_source: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub enum OutputFormat {
+  // an ES module, imports first and the content as `export default`
+  #[cfg_attr(feature = "serde", serde(rename = "program"))]
+  Program,
+  // `require` calls and `module.exports`
+  #[cfg_attr(feature = "serde", serde(rename = "commonjs"))]
+  CommonJs,
+  // the body of a function called with the JSX runtime, imports become `await import`
+  // and the exports are returned, for evaluating with `new Function` / `AsyncFunction`
+  #[cfg_attr(feature = "serde", serde(rename = "function-body"))]
+  FunctionBody,
+}
+
+impl OutputFormat {
+  pub fn new(format: &str) -> Option<Self> {
+    match format {
+      "program" => Some(OutputFormat::Program),
+      "commonjs" => Some(OutputFormat::CommonJs),
+      "function-body" => Some(OutputFormat::FunctionBody),
+      _ => None,
+    }
+  }
+
+  pub fn to_str(&self) -> &str {
+    match self {
+      OutputFormat::Program => "program",
+      OutputFormat::CommonJs => "commonjs",
+      OutputFormat::FunctionBody => "function-body",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Deserialize),
+  serde(rename_all = "lowercase")
+)]
+pub enum JsxRuntime {
+  // `jsx`/`jsxs` of `react/jsx-runtime`, development mode only applies to it
+  Automatic,
+  // the pragma, e.g. `React.createElement` or `h`
+  Classic,
+}
+
+impl JsxRuntime {
+  pub fn new(runtime: &str) -> Option<Self> {
+    match runtime {
+      "automatic" => Some(JsxRuntime::Automatic),
+      "classic" => Some(JsxRuntime::Classic),
+      _ => None,
+    }
+  }
+}
+
+impl CompileOptions {
+  pub fn builder() -> CompileOptionsBuilder {
+    CompileOptionsBuilder::default()
+  }
+
+  pub fn source_name(&self) -> &str {
+    self.filepath.as_deref().unwrap_or("input.mdx")
+  }
+}
+
+// `CompileOptions::builder().math(true).footnotes(true).build()`, starting from the defaults
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptionsBuilder {
+  options: CompileOptions,
+}
+
+macro_rules! setters {
+  ($($field:ident: $type:ty,)*) => {
+    $(
+      pub fn $field(mut self, $field: $type) -> Self {
+        self.options.$field = $field;
+        self
+      }
+    )*
+  };
+  // string and optional string fields
+  ($($field:ident,)*) => {
+    $(
+      pub fn $field(mut self, $field: impl Into<String>) -> Self {
+        self.options.$field = Into::<String>::into($field).into();
+        self
+      }
+    )*
+  };
+}
+
+impl CompileOptionsBuilder {
+  setters! {
+    toc_export: bool,
+    toc_min_depth: usize,
+    toc_max_depth: usize,
+    math: bool,
+    directives: bool,
+    alerts: bool,
+    task_lists: bool,
+    footnotes: bool,
+    autolink_literals: bool,
+    frontmatter: bool,
+    development: bool,
+    output_format: OutputFormat,
+    jsx_runtime: JsxRuntime,
+    jsx: bool,
+    target: TargetKind,
+  }
+
+  // optional and string values take anything that converts to a string
+  setters! {
+    alert_component,
+    checkbox_component,
+    footnote_id_prefix,
+    filepath,
+    pragma,
+    pragma_frag,
+    jsx_import_source,
+    provider_import_source,
+  }
+
+  pub fn source_map(mut self, source_map: SourceMapKind) -> Self {
+    self.options.source_map = Some(source_map);
+    self
+  }
+
+  pub fn build(self) -> CompileOptions {
+    self.options
+  }
+}
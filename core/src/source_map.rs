@@ -0,0 +1,279 @@
+use crate::mdast::Utf16Index;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Deserialize),
+  serde(rename_all = "lowercase")
+)]
+pub enum SourceMapKind {
+  // appended to the code as a `data:` url comment
+  Inline,
+  // returned next to the code, the host writes the `.map` file
+  Separate,
+}
+
+impl SourceMapKind {
+  pub fn new(kind: &str) -> Option<Self> {
+    match kind {
+      "inline" => Some(SourceMapKind::Inline),
+      "separate" => Some(SourceMapKind::Separate),
+      _ => None,
+    }
+  }
+}
+
+// a byte offset into the generated code and the 1-based line and byte column it
+// came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+  pub generated: usize,
+  pub line: usize,
+  pub column: usize,
+}
+
+// Source Map v3 JSON; columns count UTF-16 units, like JS engines do. Without the
+// source the columns are taken as they are
+pub fn source_map_json(
+  code: &str,
+  mappings: &[Mapping],
+  source: Option<&str>,
+  source_name: &str,
+) -> String {
+  // byte offsets where the source lines start, columns are looked up in the index from there
+  let line_starts: Vec<usize> = source
+    .map(|source| {
+      std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+    })
+    .unwrap_or_default();
+  let index = source.map(Utf16Index::new);
+  let source_column = |mapping: &Mapping| {
+    let (Some(source), Some(index), Some(&start)) =
+      (source, &index, line_starts.get(mapping.line - 1))
+    else {
+      return mapping.column - 1;
+    };
+    let offset = start + mapping.column - 1;
+    if offset > source.len() || !source.is_char_boundary(offset) {
+      return mapping.column - 1;
+    }
+    index.offset(offset) - index.offset(start)
+  };
+
+  let mut sorted: Vec<&Mapping> = mappings.iter().collect();
+  sorted.sort_by_key(|mapping| mapping.generated);
+  let mut sorted = sorted.into_iter().peekable();
+  let mut encoded = String::new();
+  // fields are relative to the previous segment, generated columns only on the same line
+  let (mut previous_line, mut previous_column) = (0, 0);
+  let mut line_start = 0;
+  for (i, line) in code.split('\n').enumerate() {
+    if i > 0 {
+      encoded.push(';');
+    }
+    let line_end = line_start + line.len();
+    let mut previous_generated_column = None;
+    // the column is counted on from the previous mapping of the line
+    let (mut counted, mut generated_column) = (line_start, 0);
+    while let Some(mapping) = sorted.next_if(|mapping| mapping.generated <= line_end) {
+      generated_column += code[counted..mapping.generated].encode_utf16().count();
+      counted = mapping.generated;
+      let line = mapping.line - 1;
+      let column = source_column(mapping);
+      if let Some(previous_generated_column) = previous_generated_column {
+        if (generated_column, line, column)
+          == (previous_generated_column, previous_line, previous_column)
+        {
+          continue;
+        }
+        encoded.push(',');
+      }
+      encode_vlq(
+        &mut encoded,
+        generated_column as i64 - previous_generated_column.unwrap_or(0) as i64,
+      );
+      // a single source
+      encode_vlq(&mut encoded, 0);
+      encode_vlq(&mut encoded, line as i64 - previous_line as i64);
+      encode_vlq(&mut encoded, column as i64 - previous_column as i64);
+      previous_generated_column = Some(generated_column);
+      previous_line = line;
+      previous_column = column;
+    }
+    line_start = line_end + 1;
+  }
+
+  let mut json = String::from("{\"version\":3,\"sources\":[");
+  push_json_string(&mut json, source_name);
+  json.push_str("],");
+  if let Some(source) = source {
+    json.push_str("\"sourcesContent\":[");
+    push_json_string(&mut json, source);
+    json.push_str("],");
+  }
+  json.push_str("\"names\":[],\"mappings\":");
+  push_json_string(&mut json, &encoded);
+  json.push('}');
+  json
+}
+
+// `//# sourceMappingURL=` comment with the map as a base64 `data:` url
+pub fn inline_source_map_comment(json: &str) -> String {
+  format!(
+    "\n//# sourceMappingURL=data:application/json;charset=utf-8;base64,{}",
+    base64(json.as_bytes())
+  )
+}
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_vlq(encoded: &mut String, value: i64) {
+  // the sign goes into the lowest bit
+  let mut value = if value < 0 {
+    ((-value) << 1) | 1
+  } else {
+    value << 1
+  };
+  loop {
+    let mut digit = value & 0b11111;
+    value >>= 5;
+    if value > 0 {
+      // continuation bit
+      digit |= 0b100000;
+    }
+    encoded.push(BASE64_CHARS[digit as usize] as char);
+    if value == 0 {
+      break;
+    }
+  }
+}
+
+fn base64(bytes: &[u8]) -> String {
+  let mut encoded = String::new();
+  for chunk in bytes.chunks(3) {
+    let n = chunk
+      .iter()
+      .enumerate()
+      .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        encoded.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0b111111) as usize] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
+
+fn push_json_string(json: &mut String, str: &str) {
+  json.push('"');
+  for ch in str.chars() {
+    match ch {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      ch if (ch as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", ch as u32)),
+      _ => json.push(ch),
+    }
+  }
+  json.push('"');
+}
+
+#[test]
+fn test_vlq_base64() {
+  let mut encoded = String::new();
+  for value in [0, 1, -1, 15, 16, -16, 1000] {
+    encode_vlq(&mut encoded, value);
+    encoded.push(',');
+  }
+  assert_eq!(encoded, "A,C,D,e,gB,hB,w+B,");
+  assert_eq!(base64(b"Man"), "TWFu");
+  assert_eq!(base64(b"Ma"), "TWE=");
+  assert_eq!(base64(b"M"), "TQ==");
+}
+
+#[test]
+fn test_source_map() {
+  let source = "import A from './a'\n\n# Hi\n\n<A data={{\n  x: 1,\n}}>{value}</A>\n";
+  let mut options = crate::CompileOptions {
+    source_map: Some(SourceMapKind::Separate),
+    filepath: Some("doc.mdx".to_string()),
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  // the ESM line, the heading and its text, the element, the attribute, every line of its
+  // value and the child expression
+  assert_eq!(
+    result.source_map.unwrap(),
+    r#"{"version":3,"sources":["doc.mdx"],"sourcesContent":["import A from './a'\n\n# Hi\n\n<A data={{\n  x: 1,\n}}>{value}</A>\n"],"names":[],"mappings":"AAAA;sEAEA,+BAAE,OAEF,mBAAG,KAAM;AACT;AACA,WAAI"}"#
+  );
+  options.source_map = Some(SourceMapKind::Inline);
+  let result = crate::compile(source, &options);
+  assert!(result.source_map.is_none());
+  assert!(result.code.contains(
+    "\n//# sourceMappingURL=data:application/json;charset=utf-8;base64,eyJ2ZXJzaW9uIjoz"
+  ));
+}
+
+#[test]
+fn test_source_map_utf16_and_line_endings() {
+  let options = crate::CompileOptions {
+    source_map: Some(SourceMapKind::Separate),
+    ..Default::default()
+  };
+  let mappings = |source: &str| {
+    let json = crate::compile(source, &options).source_map.unwrap();
+    let start = json.find("\"mappings\":\"").unwrap() + 12;
+    json[start..json.len() - 2].to_string()
+  };
+  // `em` at column 3 after the emoji, its text at 4; the emoji in the code counts 2 as well
+  assert_eq!(mappings("😀 *a*\n"), "oEAAA,gCAAA,MAAG,+BAAC");
+  // `\r\n` ends a line like `\n`, the second paragraph is on line 3
+  assert_eq!(mappings("a\r\n\r\n*b*\r\n"), "sEAAA,8BAAA,MAEA,8BAAA,+BAAC");
+  // JSX output maps the tags
+  let options = crate::CompileOptions {
+    jsx: true,
+    ..options.clone()
+  };
+  let json = crate::compile("# a *b*\n", &options).source_map.unwrap();
+  assert!(json.ends_with(r#""mappings":"uBAAA,IAAE,EAAE,IAAC"}"#));
+  // a tree from the host has no source to include
+  let ast = crate::parse_to_ast("# a\n", &options);
+  let json = crate::compile_ast(&ast, &options).source_map.unwrap();
+  assert!(!json.contains("sourcesContent"));
+  assert!(json.ends_with(r#""mappings":"uBAAA,IAAE"}"#));
+}
+
+#[test]
+fn test_source_map_linear() {
+  let options = crate::CompileOptions {
+    source_map: Some(SourceMapKind::Separate),
+    ..Default::default()
+  };
+  // the fastest of a few runs, four times the input should take about four times as long
+  let time = |paragraphs: usize| {
+    let source = "é *a* `b` [c](d)\n\n".repeat(paragraphs);
+    (0..3)
+      .map(|_| {
+        let start = std::time::Instant::now();
+        assert!(crate::compile(&source, &options).source_map.is_some());
+        start.elapsed()
+      })
+      .min()
+      .unwrap()
+  };
+  let (small, large) = (time(1000), time(4000));
+  assert!(
+    large < small * 10,
+    "{:?} for 1000 paragraphs, {:?} for 4000",
+    small,
+    large
+  );
+}
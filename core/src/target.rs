@@ -0,0 +1,407 @@
+use crate::mdast::Point;
+use crate::options::{CompileOptions, JsxRuntime};
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(Deserialize),
+  serde(rename_all = "lowercase")
+)]
+pub enum TargetKind {
+  // `jsx`/`jsxs` of `react/jsx-runtime`, or the classic pragma
+  React,
+  // the same calls from `preact/jsx-runtime`, with `class` props
+  Preact,
+  // Vue 3 `h(type, props, children)`, component children as the default slot
+  Vue,
+  // `solid-js/h`, children as arguments and fragments as arrays
+  Solid,
+  // `h(tag, props, children)` of hyperscript and the libraries following it
+  Hyperscript,
+}
+
+impl TargetKind {
+  pub fn new(kind: &str) -> Option<Self> {
+    match kind {
+      "react" => Some(TargetKind::React),
+      "preact" => Some(TargetKind::Preact),
+      "vue" => Some(TargetKind::Vue),
+      "solid" => Some(TargetKind::Solid),
+      "hyperscript" => Some(TargetKind::Hyperscript),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tag {
+  // `div`, written as a string
+  Host(String),
+  // `Chart` or `ui.Card`, a reference to a component in scope
+  Component(String),
+  Fragment,
+}
+
+impl Tag {
+  // host elements are lowercase, capitalized and member names are components
+  pub fn new(name: &str) -> Self {
+    if name.contains('.')
+      || name.starts_with(|ch: char| ch.is_uppercase() || ch == '_' || ch == '$')
+    {
+      Tag::Component(name.to_string())
+    } else {
+      Tag::Host(name.to_string())
+    }
+  }
+}
+
+// an element being written, handed back to the target when it's closed
+#[derive(Debug, Clone)]
+pub struct Element {
+  pub tag: Tag,
+  // several children, `jsxs` in React
+  pub many: bool,
+  // start of its node in the source, for development calls
+  pub location: Option<Point>,
+}
+
+// how a framework creates elements. The codegen writes props as `key:value,` between
+// `element_start` and `children_start`, and children followed by `,` when there are
+// many, so targets only write what surrounds them
+pub trait Target {
+  // up to the opening of the props object, e.g. `_jsxRuntime.jsx("div",{`
+  fn element_start(&self, code: &mut String, element: &Element);
+  // after the props, where the children go
+  fn children_start(&self, code: &mut String, element: &Element);
+  // after the children
+  fn element_end(&self, code: &mut String, element: &Element);
+  // after the props of elements that never have children, like `img`
+  fn empty_element_end(&self, code: &mut String, element: &Element);
+  // in place of the children when there are none
+  fn no_children(&self, code: &mut String, _element: &Element) {
+    code.push_str("null");
+  }
+  // the name of a prop in this framework, e.g. `class` for `className`
+  fn prop_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+    Cow::Borrowed(name)
+  }
+  // module imported as `_jsxRuntime` for `jsx_import_source`, none when the runtime is
+  // left to the host
+  fn runtime_module(&self, import_source: Option<&str>) -> Option<String> {
+    import_source.map(str::to_string)
+  }
+}
+
+pub fn push_js_string(code: &mut String, str: &str) {
+  code.push('"');
+  for ch in str.chars() {
+    match ch {
+      '"' => code.push_str("\\\""),
+      '\\' => code.push_str("\\\\"),
+      '\n' => code.push_str("\\n"),
+      '\r' => code.push_str("\\r"),
+      '\u{2028}' => code.push_str("\\u2028"),
+      '\u{2029}' => code.push_str("\\u2029"),
+      _ => code.push(ch),
+    }
+  }
+  code.push('"');
+}
+
+// the target the options ask for
+pub fn target(options: &CompileOptions) -> Box<dyn Target + '_> {
+  match options.target {
+    TargetKind::React => Box::new(React::new(options, "react")),
+    TargetKind::Preact => Box::new(Preact(React::new(options, "preact"))),
+    TargetKind::Vue => Box::new(Vue),
+    TargetKind::Solid => Box::new(Solid),
+    TargetKind::Hyperscript => Box::new(Hyperscript),
+  }
+}
+
+// `tag,{` with the fragment reference for fragments
+fn push_tag(code: &mut String, tag: &Tag, fragment: &str) {
+  match tag {
+    Tag::Host(name) => push_js_string(code, name),
+    Tag::Component(name) => code.push_str(name),
+    Tag::Fragment => code.push_str(fragment),
+  }
+  code.push_str(",{");
+}
+
+// `class` and `for` props of the frameworks closer to the DOM
+fn dom_prop_name(name: &str) -> &str {
+  match name {
+    "className" => "class",
+    "htmlFor" => "for",
+    _ => name,
+  }
+}
+
+pub struct React<'a> {
+  options: &'a CompileOptions,
+  // of the development runtime when no import source is given
+  default_import_source: &'static str,
+}
+
+impl<'a> React<'a> {
+  fn new(options: &'a CompileOptions, default_import_source: &'static str) -> Self {
+    React {
+      options,
+      default_import_source,
+    }
+  }
+
+  fn classic(&self) -> bool {
+    self.options.jsx_runtime == JsxRuntime::Classic
+  }
+
+  fn development(&self) -> bool {
+    self.options.development && !self.classic()
+  }
+}
+
+impl<'a> Target for React<'a> {
+  // `jsx(`, `jsxs(` with several children, `jsxDEV(` in development, or the pragma
+  fn element_start(&self, code: &mut String, element: &Element) {
+    if self.classic() {
+      code.push_str(&self.options.pragma);
+      code.push('(');
+      push_tag(code, &element.tag, &self.options.pragma_frag);
+      return;
+    }
+    if self.development() {
+      code.push_str("_jsxRuntime.jsxDEV(");
+    } else if element.many {
+      code.push_str("_jsxRuntime.jsxs(");
+    } else {
+      code.push_str("_jsxRuntime.jsx(");
+    }
+    push_tag(code, &element.tag, "_jsxRuntime.Fragment");
+  }
+
+  // the children go into the `children` prop, or follow the props as arguments of
+  // classic calls
+  fn children_start(&self, code: &mut String, element: &Element) {
+    if self.classic() {
+      code.push_str("},");
+    } else if element.many {
+      code.push_str("children:[");
+    } else {
+      code.push_str("children:");
+    }
+  }
+
+  fn element_end(&self, code: &mut String, element: &Element) {
+    if self.classic() {
+      code.push(')');
+      return;
+    }
+    if element.many {
+      code.push(']');
+    }
+    self.empty_element_end(code, element);
+  }
+
+  // closes the props object and the call, development calls get the key,
+  // `isStaticChildren`, `__source` and `__self` arguments
+  fn empty_element_end(&self, code: &mut String, element: &Element) {
+    code.push('}');
+    if self.development() {
+      code.push_str(if element.many {
+        ",undefined,true,"
+      } else {
+        ",undefined,false,"
+      });
+      match &element.location {
+        Some(point) => {
+          code.push_str("{fileName:");
+          push_js_string(code, self.options.source_name());
+          code.push_str(&format!(
+            ",lineNumber:{},columnNumber:{}}}",
+            point.line, point.column
+          ));
+        }
+        None => code.push_str("undefined"),
+      }
+      code.push_str(",this");
+    }
+    code.push(')');
+  }
+
+  // `children:null`, classic calls end after the props instead
+  fn no_children(&self, code: &mut String, _element: &Element) {
+    if !self.classic() {
+      code.push_str("null");
+    } else if code.ends_with(',') {
+      code.pop();
+    }
+  }
+
+  // `react/jsx-runtime`, development calls are always imported from
+  // `react/jsx-dev-runtime`, classic calls use the pragma in scope
+  fn runtime_module(&self, import_source: Option<&str>) -> Option<String> {
+    if self.classic() {
+      None
+    } else if self.development() {
+      Some(format!(
+        "{}/jsx-dev-runtime",
+        import_source.unwrap_or(self.default_import_source)
+      ))
+    } else {
+      import_source.map(|import_source| format!("{}/jsx-runtime", import_source))
+    }
+  }
+}
+
+// React's calls, Preact takes `class` and `for` as they are
+pub struct Preact<'a>(React<'a>);
+
+impl<'a> Target for Preact<'a> {
+  fn element_start(&self, code: &mut String, element: &Element) {
+    self.0.element_start(code, element);
+  }
+
+  fn children_start(&self, code: &mut String, element: &Element) {
+    self.0.children_start(code, element);
+  }
+
+  fn element_end(&self, code: &mut String, element: &Element) {
+    self.0.element_end(code, element);
+  }
+
+  fn empty_element_end(&self, code: &mut String, element: &Element) {
+    self.0.empty_element_end(code, element);
+  }
+
+  fn no_children(&self, code: &mut String, element: &Element) {
+    self.0.no_children(code, element);
+  }
+
+  fn prop_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+    Cow::Borrowed(dom_prop_name(name))
+  }
+
+  fn runtime_module(&self, import_source: Option<&str>) -> Option<String> {
+    self.0.runtime_module(import_source)
+  }
+}
+
+// `h(type, props, [children])`, components get `{default: () => [children]}` slots
+pub struct Vue;
+
+impl Target for Vue {
+  fn element_start(&self, code: &mut String, element: &Element) {
+    code.push_str("_jsxRuntime.h(");
+    push_tag(code, &element.tag, "_jsxRuntime.Fragment");
+  }
+
+  fn children_start(&self, code: &mut String, element: &Element) {
+    match element.tag {
+      Tag::Component(_) => code.push_str("},{default:()=>["),
+      _ => code.push_str("},["),
+    }
+  }
+
+  fn element_end(&self, code: &mut String, element: &Element) {
+    match element.tag {
+      Tag::Component(_) => code.push_str("]})"),
+      _ => code.push_str("])"),
+    }
+  }
+
+  fn empty_element_end(&self, code: &mut String, _element: &Element) {
+    code.push_str("})");
+  }
+
+  fn no_children(&self, _code: &mut String, _element: &Element) {}
+
+  fn prop_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+    Cow::Borrowed(dom_prop_name(name))
+  }
+}
+
+// `h(type, props, ...children)` of `solid-js/h`, which has no fragments but takes arrays
+pub struct Solid;
+
+impl Target for Solid {
+  fn element_start(&self, code: &mut String, element: &Element) {
+    if element.tag == Tag::Fragment {
+      code.push('[');
+      return;
+    }
+    code.push_str("_jsxRuntime.h(");
+    push_tag(code, &element.tag, "");
+  }
+
+  fn children_start(&self, code: &mut String, element: &Element) {
+    if element.tag != Tag::Fragment {
+      code.push_str("},");
+    }
+  }
+
+  fn element_end(&self, code: &mut String, element: &Element) {
+    if element.tag == Tag::Fragment {
+      code.push(']');
+    } else {
+      code.push(')');
+    }
+  }
+
+  fn empty_element_end(&self, code: &mut String, _element: &Element) {
+    code.push_str("})");
+  }
+
+  fn no_children(&self, _code: &mut String, _element: &Element) {}
+
+  fn prop_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+    Cow::Borrowed(dom_prop_name(name))
+  }
+}
+
+// `h(tag, props, [children])`, props are DOM properties and fragments are arrays
+pub struct Hyperscript;
+
+impl Target for Hyperscript {
+  fn element_start(&self, code: &mut String, element: &Element) {
+    if element.tag == Tag::Fragment {
+      code.push('[');
+      return;
+    }
+    code.push_str("_jsxRuntime.h(");
+    push_tag(code, &element.tag, "");
+  }
+
+  fn children_start(&self, code: &mut String, element: &Element) {
+    if element.tag != Tag::Fragment {
+      code.push_str("},[");
+    }
+  }
+
+  fn element_end(&self, code: &mut String, element: &Element) {
+    if element.tag == Tag::Fragment {
+      code.push(']');
+    } else {
+      code.push_str("])");
+    }
+  }
+
+  fn empty_element_end(&self, code: &mut String, _element: &Element) {
+    code.push_str("})");
+  }
+
+  fn no_children(&self, _code: &mut String, _element: &Element) {}
+
+  // DOM event properties are lowercase, `onclick`
+  fn prop_name<'n>(&self, name: &'n str) -> Cow<'n, str> {
+    match name.strip_prefix("on") {
+      Some(event) if event.starts_with(|ch: char| ch.is_ascii_uppercase()) => {
+        Cow::Owned(name.to_ascii_lowercase())
+      }
+      _ => Cow::Borrowed(name),
+    }
+  }
+}
@@ -0,0 +1,246 @@
+use crate::mdast::*;
+use crate::token::*;
+#[cfg(any(test, feature = "serde"))]
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(any(test, feature = "serde"), derive(Serialize))]
+pub struct TocEntry {
+  pub level: usize,
+  pub text: String,
+  pub slug: String,
+  pub span: Span,
+  pub children: Vec<TocEntry>,
+}
+
+// github-slugger compatible: lowercase, drop punctuation, spaces to `-`,
+// duplicates get a `-1`, `-2`... suffix
+#[derive(Default)]
+pub struct Slugger {
+  occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+  pub fn slug(&mut self, text: &str) -> String {
+    let base: String = text
+      .trim()
+      .chars()
+      .filter_map(|ch| {
+        if ch == ' ' {
+          Some('-')
+        } else if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+          Some(ch)
+        } else {
+          None
+        }
+      })
+      .flat_map(char::to_lowercase)
+      .collect();
+    let mut slug = base.clone();
+    if let Some(&count) = self.occurrences.get(&base) {
+      let mut count = count;
+      loop {
+        count += 1;
+        slug = format!("{}-{}", base, count);
+        if !self.occurrences.contains_key(&slug) {
+          break;
+        }
+      }
+      self.occurrences.insert(base, count);
+    }
+    self.occurrences.insert(slug.clone(), 0);
+    slug
+  }
+}
+
+pub struct Toc {
+  min_level: usize,
+  max_level: usize,
+  slugger: Slugger,
+  pub entries: Vec<TocEntry>,
+}
+
+impl Toc {
+  pub fn new(min_level: usize, max_level: usize) -> Self {
+    Self {
+      min_level,
+      max_level,
+      slugger: Slugger::default(),
+      entries: vec![],
+    }
+  }
+
+  // the slug of the heading, which is its `id`
+  pub fn push(&mut self, level: usize, text: String, span: Span) -> String {
+    // every heading takes part in slug deduplication, even filtered ones
+    let slug = self.slugger.slug(&text);
+    if level < self.min_level || level > self.max_level {
+      return slug;
+    }
+    insert_entry(
+      &mut self.entries,
+      TocEntry {
+        level,
+        text,
+        slug: slug.clone(),
+        span,
+        children: vec![],
+      },
+    );
+    slug
+  }
+}
+
+// heading positions end before the line ending, entries take it along like the
+// blocks they come from
+pub fn include_line_endings(entries: &mut [TocEntry], source: &str) {
+  for entry in entries {
+    let rest = &source[entry.span.end..];
+    if let Some(ending) = ["\r\n", "\n", "\r"]
+      .iter()
+      .find(|&&ending| rest.starts_with(ending))
+    {
+      entry.span.end += ending.len();
+    }
+    include_line_endings(&mut entry.children, source);
+  }
+}
+
+fn insert_entry(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+  match entries.last_mut() {
+    Some(last) if last.level < entry.level => insert_entry(&mut last.children, entry),
+    _ => entries.push(entry),
+  }
+}
+
+// plain text of inline tokens, markup stripped
+pub fn inlines_text(source: &str, inlines: &[Token<InlineToken>]) -> String {
+  let mut text = String::new();
+  push_inlines_text(&mut text, source, inlines);
+  text
+}
+
+fn push_inlines_text(text: &mut String, source: &str, inlines: &[Token<InlineToken>]) {
+  for inline in inlines {
+    match &inline.value {
+      InlineToken::Text(spans) | InlineToken::Code(spans) => {
+        for span in spans {
+          text.push_str(&source[span.start..span.end]);
+        }
+      }
+      InlineToken::Emphasis(children)
+      | InlineToken::Strong(children)
+      | InlineToken::Strikethrough(children)
+      | InlineToken::Link {
+        text_children: children,
+        ..
+      }
+      | InlineToken::Image {
+        alt_children: children,
+        ..
+      } => {
+        push_inlines_text(text, source, children);
+      }
+      InlineToken::AutoLink { url, .. } => {
+        text.push_str(&source[url.start..url.end]);
+      }
+      InlineToken::SoftBreak | InlineToken::HardBreak => {
+        text.push(' ');
+      }
+      _ => {}
+    }
+  }
+}
+
+// plain text of mdast inline nodes, like `inlines_text`
+pub fn nodes_text(nodes: &[Node]) -> String {
+  let mut text = String::new();
+  push_nodes_text(&mut text, nodes);
+  text
+}
+
+fn push_nodes_text(text: &mut String, nodes: &[Node]) {
+  for node in nodes {
+    match node {
+      Node::Text { value, .. } => {
+        text.extend(value.chars().map(|ch| if ch == '\n' { ' ' } else { ch }));
+      }
+      Node::InlineCode { value, .. } => {
+        text.push_str(value);
+      }
+      Node::Emphasis { children, .. }
+      | Node::Strong { children, .. }
+      | Node::Delete { children, .. }
+      | Node::Link { children, .. }
+      | Node::LinkReference { children, .. } => {
+        push_nodes_text(text, children);
+      }
+      Node::Image { alt, .. } | Node::ImageReference { alt, .. } => {
+        text.push_str(alt.as_deref().unwrap_or_default());
+      }
+      Node::Break { .. } => {
+        text.push(' ');
+      }
+      _ => {}
+    }
+  }
+}
+
+#[test]
+fn test_slugger() {
+  let mut slugger = Slugger::default();
+  let slugs: Vec<String> = [
+    "Hello World",
+    "Hello World",
+    "hello-world-1",
+    "What's *new*?",
+    "Über Café",
+  ]
+  .iter()
+  .map(|text| slugger.slug(text))
+  .collect();
+  assert_eq!(
+    slugs,
+    vec![
+      "hello-world",
+      "hello-world-1",
+      "hello-world-1-1",
+      "whats-new",
+      "über-café"
+    ]
+  );
+}
+
+#[test]
+fn test_toc() {
+  let source = "# Intro\n\n## Install `npm`\n\n### Node\n\n## Usage *now*\n\nTitle\n===\n";
+  let result = crate::compile(source, &crate::CompileOptions::default());
+  insta::assert_yaml_snapshot!(result.toc);
+}
+
+#[test]
+fn test_toc_export() {
+  let source = "# Intro\n\n## Install\n\n### Node\n\n#### Deep\n";
+  let options = crate::CompileOptions {
+    toc_export: true,
+    toc_min_depth: 2,
+    toc_max_depth: 3,
+    ..Default::default()
+  };
+  let result = crate::compile(source, &options);
+  assert!(result.code.ends_with(
+    ";\nexport const toc = [{level:2,text:\"Install\",slug:\"install\",children:[{level:3,text:\"Node\",slug:\"node\",children:[]}]}]"
+  ));
+  // every heading gets its slug as `id`, also those left out of the toc
+  assert!(result
+    .code
+    .contains("_jsxRuntime.jsx(\"h1\",{id:\"intro\",children:\"Intro\"})"));
+  assert!(result
+    .code
+    .contains("_jsxRuntime.jsx(\"h4\",{id:\"deep\",children:\"Deep\"})"));
+  let result = crate::compile(source, &crate::CompileOptions::default());
+  assert!(result
+    .code
+    .contains("_jsxRuntime.jsx(\"h1\",{children:\"Intro\"})"));
+}
@@ -1,4 +1,4 @@
-#[cfg(test)]
+#[cfg(any(test, feature = "serde"))]
 use serde::Serialize;
 
 #[derive(Eq, PartialEq, Debug)]
@@ -9,7 +9,7 @@ pub struct Token<T> {
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
-#[cfg_attr(test, derive(Serialize))]
+#[cfg_attr(any(test, feature = "serde"), derive(Serialize))]
 pub struct Span {
   pub start: usize,
   pub end: usize,
@@ -39,14 +39,14 @@ impl HeadingLevel {
     }
   }
 
-  pub fn to_str(&self) -> &str {
+  pub fn depth(&self) -> usize {
     match self {
-      HeadingLevel::H1 => "h1",
-      HeadingLevel::H2 => "h2",
-      HeadingLevel::H3 => "h3",
-      HeadingLevel::H4 => "h4",
-      HeadingLevel::H5 => "h5",
-      HeadingLevel::H6 => "h6",
+      HeadingLevel::H1 => 1,
+      HeadingLevel::H2 => 2,
+      HeadingLevel::H3 => 3,
+      HeadingLevel::H4 => 4,
+      HeadingLevel::H5 => 5,
+      HeadingLevel::H6 => 6,
     }
   }
 }
@@ -61,10 +61,56 @@ pub struct LinkDefinition<'source> {
 
 #[derive(Eq, PartialEq, Debug)]
 #[cfg_attr(test, derive(Serialize))]
-pub enum Align {
-  Left,
-  Center,
-  Right,
+pub enum Alert {
+  Note,
+  Tip,
+  Important,
+  Warning,
+  Caution,
+}
+
+impl Alert {
+  pub fn new(kind: &[u8]) -> Option<Alert> {
+    match kind.to_ascii_lowercase().as_slice() {
+      b"note" => Some(Alert::Note),
+      b"tip" => Some(Alert::Tip),
+      b"important" => Some(Alert::Important),
+      b"warning" => Some(Alert::Warning),
+      b"caution" => Some(Alert::Caution),
+      _ => None,
+    }
+  }
+
+  pub fn to_str(&self) -> &str {
+    match self {
+      Alert::Note => "note",
+      Alert::Tip => "tip",
+      Alert::Important => "important",
+      Alert::Warning => "warning",
+      Alert::Caution => "caution",
+    }
+  }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub enum AutoLinkKind {
+  // `<scheme:...>`, `https://...` and `mailto:...`, the text is the href
+  Uri,
+  // `www.`, href gets `http://`
+  Www,
+  // bare email, href gets `mailto:`
+  Email,
+}
+
+impl AutoLinkKind {
+  pub fn href_prefix(&self) -> &str {
+    match self {
+      AutoLinkKind::Uri => "",
+      AutoLinkKind::Www => "http://",
+      AutoLinkKind::Email => "mailto:",
+    }
+  }
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -88,11 +134,22 @@ pub enum BlockToken {
   BlockQuote {
     blocks: Vec<Token<BlockToken>>,
     level: usize,
+    alert: Option<Alert>,
   },
   FencedCode {
     meta_span: Span,
     code_spans: Vec<Span>,
   },
+  Math(Vec<Span>),
+  LeafDirective(Directive),
+  ContainerDirective {
+    directive: Directive,
+    blocks: Vec<Token<BlockToken>>,
+  },
+  FootnoteDefinition {
+    label: Span,
+    blocks: Vec<Token<BlockToken>>,
+  },
   List {
     ch: u8,
     is_tight: bool,
@@ -102,6 +159,8 @@ pub enum BlockToken {
   ListItem {
     indent: usize,
     blocks: Vec<Token<BlockToken>>,
+    // task list item state, `- [x]`
+    checked: Option<bool>,
   },
   // LinkDefinition,
   // Table,
@@ -117,36 +176,72 @@ pub enum BlockToken {
 pub enum InlineToken {
   TextSegment,
   MaybeLinkStart,
-  MaybeEmphasis {
+  // delimiter run index
+  MaybeEmphasis(usize),
+  EmphasisStart {
     ch: u8,
-    repeat: usize,
-    can_open: bool,
-    can_close: bool,
+    size: usize,
   },
-  EmphasisStart,
   EmphasisEnd,
   LinkStart {
     url: Span,
     title: Vec<Span>,
   },
+  ImageStart {
+    url: Span,
+    title: Vec<Span>,
+  },
   LinkEnd,
   //
   Emphasis(Vec<Token<InlineToken>>),
+  Strong(Vec<Token<InlineToken>>),
+  Strikethrough(Vec<Token<InlineToken>>),
   Link {
     url: Span,
     title: Vec<Span>,
     text_children: Vec<Token<InlineToken>>,
   },
+  Image {
+    url: Span,
+    title: Vec<Span>,
+    alt_children: Vec<Token<InlineToken>>,
+  },
   Text(Vec<Span>),
   Code(Vec<Span>),
+  Math(Vec<Span>),
+  TextDirective {
+    directive: Directive,
+    children: Vec<Token<InlineToken>>,
+  },
+  // label
+  FootnoteReference(Span),
   CodeSegment,
   SoftBreak,
   HardBreak,
-  // is email
-  AutoLink(bool),
+  AutoLink {
+    kind: AutoLinkKind,
+    url: Span,
+  },
   JSX(JSXElement),
 }
 
+#[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub enum DirectiveAttr {
+  Id(Span),
+  Class(Span),
+  // a key without value has an empty value span
+  KeyValue { key: Span, value: Span },
+}
+
+#[derive(Eq, PartialEq, Debug)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct Directive {
+  pub name: Span,
+  pub label: Option<Span>,
+  pub attributes: Vec<DirectiveAttr>,
+}
+
 #[derive(Eq, PartialEq, Debug)]
 pub struct AST<T> {
   pub span: Span,
@@ -159,6 +254,9 @@ pub enum ContainerBlock {
   BlockQuote(usize),
   List(u8),
   ListItem(usize),
+  // fence size
+  Directive(usize),
+  FootnoteDefinition,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -211,4 +309,6 @@ pub struct JSXElement {
   pub tag: String,
   pub attributes: Vec<JSXAttr>,
   pub children: Vec<JSX>,
+  // from `<` to the closing `>`
+  pub span: Span,
 }
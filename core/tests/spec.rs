@@ -0,0 +1,201 @@
+// Runs the CommonMark and GFM spec examples through `render_html`.
+//
+// Examples listed in tests/spec/known_failures.txt are expected to fail, any
+// other failure is a regression. Once a known failure passes it has to be
+// removed from the list, `UPDATE_KNOWN_FAILURES=1 cargo test --test spec`
+// rewrites it. Run with `--nocapture` for the pass rate per section.
+use compiler_core::{render_html, CompileOptions, HtmlOptions};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
+
+const FENCE: &str = "````````````````````````````````";
+
+struct Example {
+  number: usize,
+  section: String,
+  markdown: String,
+  html: String,
+}
+
+fn spec_dir() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/spec")
+}
+
+fn read_examples(name: &str) -> Vec<Example> {
+  let text = fs::read_to_string(spec_dir().join(name)).unwrap();
+  let mut examples = vec![];
+  let mut section = String::new();
+  let mut lines = text.lines();
+  while let Some(line) = lines.next() {
+    if line.starts_with('#') {
+      section = line.trim_start_matches('#').trim().to_string();
+      continue;
+    }
+    if !line.starts_with(FENCE) || !line[FENCE.len()..].trim().starts_with("example") {
+      continue;
+    }
+    let mut markdown = String::new();
+    let mut html = String::new();
+    let mut in_html = false;
+    for line in lines.by_ref() {
+      if line.starts_with(FENCE) {
+        break;
+      }
+      if line == "." && !in_html {
+        in_html = true;
+        continue;
+      }
+      let target = if in_html { &mut html } else { &mut markdown };
+      target.push_str(&line.replace('→', "\t"));
+      target.push('\n');
+    }
+    examples.push(Example {
+      number: examples.len() + 1,
+      section: section.clone(),
+      markdown,
+      html,
+    });
+  }
+  examples
+}
+
+fn read_known_failures() -> BTreeMap<String, BTreeSet<usize>> {
+  let text = fs::read_to_string(spec_dir().join("known_failures.txt")).unwrap_or_default();
+  let mut known_failures = BTreeMap::new();
+  for line in text.lines() {
+    if line.starts_with('#') || line.trim().is_empty() {
+      continue;
+    }
+    let (spec, numbers) = line.split_once(':').unwrap();
+    let set: &mut BTreeSet<usize> = known_failures.entry(spec.to_string()).or_default();
+    for range in numbers
+      .split(',')
+      .map(str::trim)
+      .filter(|range| !range.is_empty())
+    {
+      match range.split_once('-') {
+        Some((start, end)) => set.extend(start.parse::<usize>().unwrap()..=end.parse().unwrap()),
+        None => {
+          set.insert(range.parse().unwrap());
+        }
+      }
+    }
+  }
+  known_failures
+}
+
+fn write_known_failures(failures: &BTreeMap<String, BTreeSet<usize>>) {
+  let mut text = String::from(
+    "# spec examples expected to fail, rewrite with UPDATE_KNOWN_FAILURES=1 cargo test --test spec\n",
+  );
+  for (spec, numbers) in failures {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for &number in numbers {
+      match ranges.last_mut() {
+        Some((_, end)) if *end + 1 == number => *end = number,
+        _ => ranges.push((number, number)),
+      }
+    }
+    let ranges: Vec<String> = ranges
+      .iter()
+      .map(|&(start, end)| {
+        if start == end {
+          start.to_string()
+        } else {
+          format!("{}-{}", start, end)
+        }
+      })
+      .collect();
+    text.push_str(&format!("{}: {}\n", spec, ranges.join(", ")));
+  }
+  fs::write(spec_dir().join("known_failures.txt"), text).unwrap();
+}
+
+// passed example numbers, failed example numbers
+fn run_examples(spec: &str, options: &CompileOptions) -> (Vec<usize>, BTreeSet<usize>) {
+  let mut passed = vec![];
+  let mut failed = BTreeSet::new();
+  let mut sections: Vec<(String, usize, usize)> = vec![];
+  for example in read_examples(&format!("{}.txt", spec)) {
+    let markdown = example.markdown.clone();
+    let options = options.clone();
+    let html =
+      panic::catch_unwind(move || render_html(&markdown, &options, &HtmlOptions::default()));
+    let ok = html.is_ok_and(|html| html.is_ok_and(|html| html == example.html));
+    match sections.last_mut() {
+      Some((section, _, _)) if *section == example.section => {}
+      _ => sections.push((example.section.clone(), 0, 0)),
+    }
+    let (_, section_passed, section_total) = sections.last_mut().unwrap();
+    *section_total += 1;
+    if ok {
+      *section_passed += 1;
+      passed.push(example.number);
+    } else {
+      failed.insert(example.number);
+    }
+  }
+  println!("{}: {}/{}", spec, passed.len(), passed.len() + failed.len());
+  for (section, section_passed, section_total) in sections {
+    println!(
+      "  {:<40} {:>4}/{:<4}",
+      section, section_passed, section_total
+    );
+  }
+  (passed, failed)
+}
+
+#[test]
+fn spec_examples() {
+  let gfm_options = CompileOptions {
+    autolink_literals: true,
+    task_lists: true,
+    ..Default::default()
+  };
+  let specs = [
+    ("commonmark", CompileOptions::default()),
+    ("gfm", gfm_options),
+  ];
+  // the parser panicking on an example counts as a failure, without the noise
+  let hook = panic::take_hook();
+  panic::set_hook(Box::new(|_| {}));
+  let results: Vec<(&str, Vec<usize>, BTreeSet<usize>)> = specs
+    .iter()
+    .map(|(spec, options)| {
+      let (passed, failed) = run_examples(spec, options);
+      (*spec, passed, failed)
+    })
+    .collect();
+  panic::set_hook(hook);
+
+  if std::env::var("UPDATE_KNOWN_FAILURES").is_ok() {
+    let failures = results
+      .into_iter()
+      .map(|(spec, _, failed)| (spec.to_string(), failed))
+      .collect();
+    write_known_failures(&failures);
+    return;
+  }
+  let known_failures = read_known_failures();
+  let mut errors = vec![];
+  for (spec, passed, failed) in &results {
+    let known = known_failures.get(*spec).cloned().unwrap_or_default();
+    let regressions: Vec<&usize> = failed.difference(&known).collect();
+    if !regressions.is_empty() {
+      errors.push(format!("{} examples failing: {:?}", spec, regressions));
+    }
+    let fixed: Vec<&usize> = passed
+      .iter()
+      .filter(|number| known.contains(number))
+      .collect();
+    if !fixed.is_empty() {
+      errors.push(format!(
+        "{} examples passing, remove them from known_failures.txt: {:?}",
+        spec, fixed
+      ));
+    }
+  }
+  assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
@@ -0,0 +1,99 @@
+import assert from 'node:assert/strict'
+import { getEventListeners } from 'node:events'
+import { test } from 'node:test'
+
+import {
+  compile,
+  compileAst,
+  compileAsync,
+  compileMany,
+  parse,
+  parseToAst,
+  parseToBuffer,
+  renderHtml,
+} from '../index.js'
+
+test('compileMany gives the results in the order of the sources', async () => {
+  const sources = ['# a', 'b *c*', '- d']
+  const results = await compileMany(sources)
+  assert.deepEqual(
+    results.map((result) => result.code),
+    sources.map((source) => compile(source).code),
+  )
+})
+
+test('compileMany rejects once the signal aborts', async () => {
+  const sources = Array.from({ length: 500 }, (_, index) => `# ${index}\n\n${'some *text* '.repeat(500)}`)
+  const controller = new AbortController()
+  const results = compileMany(sources, null, controller.signal)
+  controller.abort()
+  await assert.rejects(results, { message: 'AbortError' })
+})
+
+test('compileMany rejects with a signal aborted before the call', async () => {
+  await assert.rejects(compileMany(['# a'], null, AbortSignal.abort()), { message: 'AbortError' })
+})
+
+test('compileMany removes its abort listener once it settles', async () => {
+  const { signal } = new AbortController()
+  await compileMany(['# a'], null, signal)
+  // only the `onabort` handler is left
+  assert.equal(getEventListeners(signal, 'abort').length, 1)
+  const aborted = AbortSignal.abort()
+  await assert.rejects(compileMany(['# a'], null, aborted), { message: 'AbortError' })
+  assert.equal(getEventListeners(aborted, 'abort').length, 1)
+})
+
+test('compileAsync gives the same result as compile', async () => {
+  const result = await compileAsync('# a *b*', null, new AbortController().signal)
+  assert.deepEqual(result, compile('# a *b*'))
+})
+
+test('compileAsync rejects once the signal aborts', async () => {
+  const controller = new AbortController()
+  const result = compileAsync('some *text* '.repeat(100000), null, controller.signal)
+  controller.abort()
+  await assert.rejects(result, { message: 'AbortError' })
+})
+
+test('compileAsync rejects with a signal aborted before the call', async () => {
+  await assert.rejects(compileAsync('# a', null, AbortSignal.abort()), { message: 'AbortError' })
+})
+
+test('compileAst compiles the tree from parseToAst', () => {
+  const ast = parseToAst('# a *b*\n')
+  assert.equal(ast.type, 'root')
+  assert.equal(ast.children[0].type, 'heading')
+  assert.equal(ast.children[0].depth, 1)
+  assert.equal(compileAst(ast).code, compile('# a *b*\n').code)
+})
+
+test('renderHtml renders the document as HTML', () => {
+  assert.equal(renderHtml('# a *b*\n'), '<h1>a <em>b</em></h1>\n')
+})
+
+test('compile reports diagnostics at UTF-16 offsets', () => {
+  const source = '😀 <Foo bar={>\n'
+  const [diagnostic] = compile(source).diagnostics
+  assert.equal(source[diagnostic.position.start.offset], '<')
+  assert.deepEqual(diagnostic.position.start, { line: 1, column: 4, offset: 3 })
+  assert.deepEqual(compile('# a').diagnostics, [])
+})
+
+test('parse takes a Buffer or Uint8Array like a string', () => {
+  const source = '# 😀 *a*\n'
+  const bytes = Buffer.from(source)
+  assert.equal(parse(bytes), parse(source))
+  assert.equal(parse(new Uint8Array(bytes)), parse(source))
+  assert.deepEqual(compile(bytes).toc, compile(source).toc)
+})
+
+test('parse rejects a Buffer that is not UTF-8', () => {
+  assert.throws(() => parse(Buffer.from([0x23, 0x20, 0xff])), /source is not valid UTF-8/)
+})
+
+test('parseToBuffer gives the code as UTF-8', () => {
+  const code = parseToBuffer(Buffer.from('# é\n'))
+  assert.ok(Buffer.isBuffer(code))
+  assert.equal(code.toString('utf8'), parse('# é\n'))
+})
@@ -9,4 +9,69 @@ export class ExternalObject<T> {
     [K: symbol]: T
   }
 }
-export function parse(source: string): string
+export interface ParseOptions {
+  tocExport?: boolean
+  tocMinDepth?: number
+  tocMaxDepth?: number
+  math?: boolean
+  directives?: boolean
+  alerts?: boolean
+  alertComponent?: string
+  taskLists?: boolean
+  checkboxComponent?: string
+  footnotes?: boolean
+  footnoteIdPrefix?: string
+  autolinkLiterals?: boolean
+  frontmatter?: boolean
+  filepath?: string
+  development?: boolean
+  sourceMap?: string
+  outputFormat?: string
+  jsxRuntime?: string
+  pragma?: string
+  pragmaFrag?: string
+  jsx?: boolean
+  target?: string
+  jsxImportSource?: string
+  providerImportSource?: string
+}
+export interface HtmlRenderOptions {
+  jsx?: string
+  expressions?: string
+}
+export interface TocEntry {
+  level: number
+  text: string
+  slug: string
+  start: number
+  end: number
+  children: Array<TocEntry>
+}
+export interface Point {
+  line: number
+  column: number
+  offset: number
+}
+export interface Position {
+  start: Point
+  end: Point
+}
+export interface Diagnostic {
+  message: string
+  position?: Position
+}
+export interface CompileResult {
+  code: string
+  toc: Array<TocEntry>
+  sourceMap?: string
+  frontmatter?: string
+  diagnostics: Array<Diagnostic>
+}
+export function parse(source: string | Uint8Array, options?: ParseOptions | undefined | null): string
+export function parseToBuffer(source: string | Uint8Array, options?: ParseOptions | undefined | null): Buffer
+export function compile(source: string | Uint8Array, options?: ParseOptions | undefined | null): CompileResult
+export function compileAsync(source: string | Uint8Array, options?: ParseOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<CompileResult>
+export function compileMany(sources: Array<string | Uint8Array>, options?: ParseOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<Array<CompileResult>>
+export function compileAst(ast: any, options?: ParseOptions | undefined | null): CompileResult
+export function renderHtml(source: string | Uint8Array, options?: ParseOptions | undefined | null, htmlOptions?: HtmlRenderOptions | undefined | null): string
+export function parseToAst(source: string | Uint8Array, options?: ParseOptions | undefined | null): any
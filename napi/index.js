@@ -221,6 +221,13 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { parse } = nativeBinding
+const { parse, parseToBuffer, compile, compileAsync, compileMany, compileAst, renderHtml, parseToAst } = nativeBinding
 
 module.exports.parse = parse
+module.exports.parseToBuffer = parseToBuffer
+module.exports.compile = compile
+module.exports.compileAsync = compileAsync
+module.exports.compileMany = compileMany
+module.exports.compileAst = compileAst
+module.exports.renderHtml = renderHtml
+module.exports.parseToAst = parseToAst
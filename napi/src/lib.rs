@@ -1,9 +1,515 @@
 #![deny(clippy::all)]
 #[macro_use]
 extern crate napi_derive;
-use compiler_core::parse as parse_mdx;
+use compiler_core::{
+  compile as compile_mdx, compile_ast as compile_mdx_ast, parse_to_ast as parse_mdx_to_ast,
+  render_html as render_mdx_html, CompileOptions, Compiler, ExpressionStrategy, HtmlOptions,
+  JsxRuntime, JsxStrategy, Node, OutputFormat, SourceMapKind, TargetKind, Utf16Index,
+};
+use napi::bindgen_prelude::{AbortSignal, AsyncTask, Buffer, Either, FromNapiValue, Uint8Array};
+use napi::{Env, JsFunction, JsObject, JsUnknown, NapiRaw, Ref, Status, Task};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+// a JS string, or UTF-8 bytes read in place from a `Buffer` or `Uint8Array`, which
+// saves transcoding files that were read as buffers
+type Source = Either<String, Uint8Array>;
+
+#[napi(object)]
+pub struct ParseOptions {
+  pub toc_export: Option<bool>,
+  pub toc_min_depth: Option<u32>,
+  pub toc_max_depth: Option<u32>,
+  pub math: Option<bool>,
+  pub directives: Option<bool>,
+  pub alerts: Option<bool>,
+  pub alert_component: Option<String>,
+  pub task_lists: Option<bool>,
+  pub checkbox_component: Option<String>,
+  pub footnotes: Option<bool>,
+  pub footnote_id_prefix: Option<String>,
+  pub autolink_literals: Option<bool>,
+  pub frontmatter: Option<bool>,
+  pub filepath: Option<String>,
+  pub development: Option<bool>,
+  // "inline" or "separate"
+  pub source_map: Option<String>,
+  // "program", "commonjs" or "function-body"
+  pub output_format: Option<String>,
+  // "automatic" or "classic"
+  pub jsx_runtime: Option<String>,
+  pub pragma: Option<String>,
+  pub pragma_frag: Option<String>,
+  pub jsx: Option<bool>,
+  // "react", "preact", "vue", "solid" or "hyperscript"
+  pub target: Option<String>,
+  pub jsx_import_source: Option<String>,
+  pub provider_import_source: Option<String>,
+}
+
+#[napi(object)]
+pub struct HtmlRenderOptions {
+  // "drop" or "render"
+  pub jsx: Option<String>,
+  // "drop" or "error"
+  pub expressions: Option<String>,
+}
+
+// offsets and columns here and in trees count UTF-16 code units, like JS string indices
+#[napi(object)]
+pub struct TocEntry {
+  pub level: u32,
+  pub text: String,
+  pub slug: String,
+  pub start: u32,
+  pub end: u32,
+  pub children: Vec<TocEntry>,
+}
+
+#[napi(object)]
+pub struct Point {
+  pub line: u32,
+  pub column: u32,
+  pub offset: u32,
+}
+
+#[napi(object)]
+pub struct Position {
+  pub start: Point,
+  pub end: Point,
+}
+
+// a problem compiling recovered from, e.g. a `<` written as text
+#[napi(object)]
+pub struct Diagnostic {
+  pub message: String,
+  pub position: Option<Position>,
+}
+
+#[napi(object)]
+pub struct CompileResult {
+  pub code: String,
+  pub toc: Vec<TocEntry>,
+  pub source_map: Option<String>,
+  pub frontmatter: Option<String>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+fn to_compile_options(options: Option<ParseOptions>) -> napi::Result<CompileOptions> {
+  let mut compile_options = CompileOptions::default();
+  if let Some(options) = options {
+    if let Some(toc_export) = options.toc_export {
+      compile_options.toc_export = toc_export;
+    }
+    if let Some(depth) = options.toc_min_depth {
+      compile_options.toc_min_depth = depth as usize;
+    }
+    if let Some(depth) = options.toc_max_depth {
+      compile_options.toc_max_depth = depth as usize;
+    }
+    if let Some(math) = options.math {
+      compile_options.math = math;
+    }
+    if let Some(directives) = options.directives {
+      compile_options.directives = directives;
+    }
+    if let Some(alerts) = options.alerts {
+      compile_options.alerts = alerts;
+    }
+    if options.alert_component.is_some() {
+      compile_options.alert_component = options.alert_component;
+    }
+    if let Some(task_lists) = options.task_lists {
+      compile_options.task_lists = task_lists;
+    }
+    if options.checkbox_component.is_some() {
+      compile_options.checkbox_component = options.checkbox_component;
+    }
+    if let Some(footnotes) = options.footnotes {
+      compile_options.footnotes = footnotes;
+    }
+    if let Some(footnote_id_prefix) = options.footnote_id_prefix {
+      compile_options.footnote_id_prefix = footnote_id_prefix;
+    }
+    if let Some(autolink_literals) = options.autolink_literals {
+      compile_options.autolink_literals = autolink_literals;
+    }
+    if let Some(frontmatter) = options.frontmatter {
+      compile_options.frontmatter = frontmatter;
+    }
+    if options.filepath.is_some() {
+      compile_options.filepath = options.filepath;
+    }
+    if let Some(development) = options.development {
+      compile_options.development = development;
+    }
+    if let Some(source_map) = options.source_map {
+      compile_options.source_map = Some(SourceMapKind::new(&source_map).ok_or_else(|| {
+        napi::Error::from_reason(format!("unknown source map kind `{}`", source_map))
+      })?);
+    }
+    if let Some(output_format) = options.output_format {
+      compile_options.output_format = OutputFormat::new(&output_format).ok_or_else(|| {
+        napi::Error::from_reason(format!("unknown output format `{}`", output_format))
+      })?;
+    }
+    if let Some(jsx_runtime) = options.jsx_runtime {
+      compile_options.jsx_runtime = JsxRuntime::new(&jsx_runtime).ok_or_else(|| {
+        napi::Error::from_reason(format!("unknown jsx runtime `{}`", jsx_runtime))
+      })?;
+    }
+    if let Some(pragma) = options.pragma {
+      compile_options.pragma = pragma;
+    }
+    if let Some(pragma_frag) = options.pragma_frag {
+      compile_options.pragma_frag = pragma_frag;
+    }
+    if let Some(jsx) = options.jsx {
+      compile_options.jsx = jsx;
+    }
+    if let Some(target) = options.target {
+      compile_options.target = TargetKind::new(&target)
+        .ok_or_else(|| napi::Error::from_reason(format!("unknown target `{}`", target)))?;
+    }
+    if options.jsx_import_source.is_some() {
+      compile_options.jsx_import_source = options.jsx_import_source;
+    }
+    if options.provider_import_source.is_some() {
+      compile_options.provider_import_source = options.provider_import_source;
+    }
+  }
+  Ok(compile_options)
+}
+
+fn to_html_options(options: Option<HtmlRenderOptions>) -> napi::Result<HtmlOptions> {
+  let mut html_options = HtmlOptions::default();
+  if let Some(options) = options {
+    if let Some(jsx) = options.jsx {
+      html_options.jsx = JsxStrategy::new(&jsx)
+        .ok_or_else(|| napi::Error::from_reason(format!("unknown jsx strategy `{}`", jsx)))?;
+    }
+    if let Some(expressions) = options.expressions {
+      html_options.expressions = ExpressionStrategy::new(&expressions).ok_or_else(|| {
+        napi::Error::from_reason(format!("unknown expressions strategy `{}`", expressions))
+      })?;
+    }
+  }
+  Ok(html_options)
+}
+
+// without an index the spans are the offsets of a tree from the host
+fn to_toc_entries(
+  entries: Vec<compiler_core::TocEntry>,
+  index: Option<&Utf16Index>,
+) -> Vec<TocEntry> {
+  let offset = |offset| index.map_or(offset, |index| index.offset(offset)) as u32;
+  entries
+    .into_iter()
+    .map(|entry| TocEntry {
+      level: entry.level as u32,
+      text: entry.text,
+      slug: entry.slug,
+      start: offset(entry.span.start),
+      end: offset(entry.span.end),
+      children: to_toc_entries(entry.children, index),
+    })
+    .collect()
+}
+
+fn to_point(point: compiler_core::Point) -> Point {
+  Point {
+    line: point.line as u32,
+    column: point.column as u32,
+    offset: point.offset as u32,
+  }
+}
+
+fn to_diagnostics(
+  diagnostics: Vec<compiler_core::Diagnostic>,
+  index: Option<&Utf16Index>,
+) -> Vec<Diagnostic> {
+  diagnostics
+    .into_iter()
+    .map(|diagnostic| Diagnostic {
+      message: diagnostic.message,
+      position: diagnostic.position.map(|mut position| {
+        if let Some(index) = index {
+          index.position(&mut position);
+        }
+        Position {
+          start: to_point(position.start),
+          end: to_point(position.end),
+        }
+      }),
+    })
+    .collect()
+}
+
+fn to_compile_result(
+  result: compiler_core::CompileResult,
+  index: Option<&Utf16Index>,
+) -> CompileResult {
+  CompileResult {
+    code: result.code,
+    toc: to_toc_entries(result.toc, index),
+    source_map: result.source_map,
+    frontmatter: result.frontmatter,
+    diagnostics: to_diagnostics(result.diagnostics, index),
+  }
+}
+
+fn source_str(source: &Source) -> napi::Result<&str> {
+  match source {
+    Either::A(source) => Ok(source),
+    Either::B(bytes) => std::str::from_utf8(bytes)
+      .map_err(|error| napi::Error::from_reason(format!("source is not valid UTF-8: {}", error))),
+  }
+}
+
+#[napi]
+fn parse(source: Source, options: Option<ParseOptions>) -> napi::Result<String> {
+  Ok(compile_mdx(source_str(&source)?, &to_compile_options(options)?).code)
+}
+
+// the code as UTF-8 in a `Buffer` that takes over the compiled string, for writing
+// it to a file without turning it into a JS string
+#[napi]
+fn parse_to_buffer(source: Source, options: Option<ParseOptions>) -> napi::Result<Buffer> {
+  let code = compile_mdx(source_str(&source)?, &to_compile_options(options)?).code;
+  Ok(Buffer::from(code.into_bytes()))
+}
+
+#[napi]
+fn compile(source: Source, options: Option<ParseOptions>) -> napi::Result<CompileResult> {
+  let source = source_str(&source)?;
+  let result = compile_mdx(source, &to_compile_options(options)?);
+  Ok(to_compile_result(result, Some(&Utf16Index::new(source))))
+}
+
+pub struct CompileTask {
+  source: Source,
+  options: CompileOptions,
+  // the signal was aborted before the call
+  aborted: bool,
+}
+
+impl Task for CompileTask {
+  type Output = CompileResult;
+  type JsValue = CompileResult;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    if self.aborted {
+      return Err(abort_error());
+    }
+    let source = source_str(&self.source)?;
+    let result = compile_mdx(source, &self.options);
+    Ok(to_compile_result(result, Some(&Utf16Index::new(source))))
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+fn abort_error() -> napi::Error {
+  napi::Error::new(Status::Cancelled, "AbortError".to_owned())
+}
+
+// napi's handle on the signal, aborting rejects the promise and cancels the work if it
+// hasn't started. A signal can only be passed to one call
+pub fn abort_signal(env: &Env, signal: &JsObject) -> napi::Result<AbortSignal> {
+  unsafe { AbortSignal::from_napi_value(env.raw(), signal.raw()) }
+}
+
+// `compile` on the libuv thread pool, aborting rejects the promise with an `AbortError`.
+// A document already being compiled is finished and thrown away
+#[napi]
+fn compile_async(
+  env: Env,
+  source: Source,
+  options: Option<ParseOptions>,
+  #[napi(ts_arg_type = "AbortSignal | undefined | null")] signal: Option<JsObject>,
+) -> napi::Result<AsyncTask<CompileTask>> {
+  let task = CompileTask {
+    source,
+    options: to_compile_options(options)?,
+    aborted: match &signal {
+      Some(signal) => signal.get_named_property("aborted")?,
+      None => false,
+    },
+  };
+  let signal = signal
+    .map(|signal| abort_signal(&env, &signal))
+    .transpose()?;
+  Ok(AsyncTask::with_optional_signal(task, signal))
+}
+
+// the `abort` listener of a signal, setting a flag the workers check before each
+// document. It's removed once the task settles
+pub struct AbortListener {
+  aborted: Arc<AtomicBool>,
+  signal: Ref<()>,
+  listener: Ref<()>,
+}
+
+impl AbortListener {
+  pub fn new(env: &Env, signal: &JsObject) -> napi::Result<Self> {
+    let aborted = Arc::new(AtomicBool::new(signal.get_named_property("aborted")?));
+    let flag = aborted.clone();
+    let listener = env.create_function_from_closure("onabort", move |ctx| {
+      flag.store(true, Ordering::Relaxed);
+      ctx.env.get_undefined()
+    })?;
+    let listener_ref = env.create_reference(&listener)?;
+    let add_event_listener: JsFunction = signal.get_named_property("addEventListener")?;
+    add_event_listener.call(
+      Some(signal),
+      &[
+        env.create_string("abort")?.into_unknown(),
+        listener.into_unknown(),
+      ],
+    )?;
+    Ok(AbortListener {
+      aborted,
+      signal: env.create_reference(signal)?,
+      listener: listener_ref,
+    })
+  }
+
+  pub fn remove(&mut self, env: Env) -> napi::Result<()> {
+    let signal: JsObject = env.get_reference_value(&self.signal)?;
+    let listener: JsFunction = env.get_reference_value(&self.listener)?;
+    let remove_event_listener: JsFunction = signal.get_named_property("removeEventListener")?;
+    remove_event_listener.call(
+      Some(&signal),
+      &[
+        env.create_string("abort")?.into_unknown(),
+        listener.into_unknown(),
+      ],
+    )?;
+    self.signal.unref(env)?;
+    self.listener.unref(env)?;
+    Ok(())
+  }
+
+  pub fn aborted(&self) -> Arc<AtomicBool> {
+    self.aborted.clone()
+  }
+}
+
+pub struct CompileManyTask {
+  sources: Vec<Source>,
+  compiler: Compiler,
+  aborted: Arc<AtomicBool>,
+  listener: Option<AbortListener>,
+}
+
+impl Task for CompileManyTask {
+  type Output = Vec<CompileResult>;
+  type JsValue = Vec<CompileResult>;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let sources = self
+      .sources
+      .iter()
+      .map(source_str)
+      .collect::<napi::Result<Vec<_>>>()?;
+    let Some(results) = self.compiler.compile_many_until(&sources, &self.aborted) else {
+      return Err(abort_error());
+    };
+    Ok(
+      results
+        .into_iter()
+        .zip(sources)
+        .map(|(result, source)| to_compile_result(result, Some(&Utf16Index::new(source))))
+        .collect(),
+    )
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn finally(&mut self, env: Env) -> napi::Result<()> {
+    match &mut self.listener {
+      Some(listener) => listener.remove(env),
+      None => Ok(()),
+    }
+  }
+}
+
+// the sources compiled on all cores with the same options, results in their order.
+// Aborting rejects the promise right away, the documents being compiled are finished
+// and the rest are skipped
+#[napi]
+fn compile_many(
+  env: Env,
+  sources: Vec<Source>,
+  options: Option<ParseOptions>,
+  #[napi(ts_arg_type = "AbortSignal | undefined | null")] signal: Option<JsObject>,
+) -> napi::Result<AsyncTask<CompileManyTask>> {
+  // converted first, the listener must not be dropped without being removed
+  let abort_signal = signal
+    .as_ref()
+    .map(|signal| abort_signal(&env, signal))
+    .transpose()?;
+  let compiler = Compiler::new(to_compile_options(options)?);
+  let listener = signal
+    .as_ref()
+    .map(|signal| AbortListener::new(&env, signal))
+    .transpose()?;
+  let task = CompileManyTask {
+    sources,
+    compiler,
+    aborted: listener
+      .as_ref()
+      .map_or_else(Arc::default, AbortListener::aborted),
+    listener,
+  };
+  Ok(AsyncTask::with_optional_signal(task, abort_signal))
+}
+
+// an mdast tree from the host, e.g. after remark plugins ran on it
+#[napi]
+fn compile_ast(
+  env: Env,
+  ast: JsUnknown,
+  options: Option<ParseOptions>,
+) -> napi::Result<CompileResult> {
+  let ast: Node = env.from_js_value(ast)?;
+  let result = compile_mdx_ast(&ast, &to_compile_options(options)?);
+  Ok(to_compile_result(result, None))
+}
+
+#[napi]
+fn render_html(
+  source: Source,
+  options: Option<ParseOptions>,
+  html_options: Option<HtmlRenderOptions>,
+) -> napi::Result<String> {
+  let html_options = to_html_options(html_options)?;
+  let source = source_str(&source)?;
+  render_mdx_html(source, &to_compile_options(options)?, &html_options).map_err(|error| {
+    let index = Utf16Index::new(source);
+    napi::Error::from_reason(format!(
+      "{} at {}..{}",
+      error.message,
+      index.offset(error.span.start),
+      index.offset(error.span.end)
+    ))
+  })
+}
+
+// mdast tree as a plain object
 #[napi]
-fn parse(source: String) -> String {
-  parse_mdx(&source)
+fn parse_to_ast(
+  env: Env,
+  source: Source,
+  options: Option<ParseOptions>,
+) -> napi::Result<JsUnknown> {
+  let source = source_str(&source)?;
+  let mut ast = parse_mdx_to_ast(source, &to_compile_options(options)?);
+  let index = Utf16Index::new(source);
+  ast.for_each_position(&mut |position| index.position(position));
+  env.to_js_value(&ast)
 }
@@ -1,8 +1,205 @@
 extern crate wasm_bindgen;
-use compiler_core;
+use compiler_core::{
+	CompileOptions, CompileResult, Diagnostic, ExpressionStrategy, HtmlOptions, JsxStrategy, Node,
+	Point, Position, TocEntry, Utf16Index,
+};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+// a JS `Error`, with the unist `position` of the source that caused it when known
+fn error(message: &str, position: Option<&Position>) -> JsValue {
+	let error = js_sys::Error::new(message);
+	let position = match position {
+		Some(position) => position
+			.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+			.unwrap_or(JsValue::NULL),
+		None => JsValue::NULL,
+	};
+	let _ = js_sys::Reflect::set(&error, &JsValue::from_str("position"), &position);
+	error.into()
+}
+
+fn point(source: &str, offset: usize) -> Point {
+	let before = &source[..offset];
+	let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+	Point {
+		line: before.matches('\n').count() + 1,
+		column: offset - line_start + 1,
+		offset,
+	}
+}
+
+// offsets and columns for JS count UTF-16 code units, like string indices
+fn to_utf16(result: &mut CompileResult, source: &str) {
+	fn toc_to_utf16(entries: &mut [TocEntry], index: &Utf16Index) {
+		for entry in entries {
+			entry.span.start = index.offset(entry.span.start);
+			entry.span.end = index.offset(entry.span.end);
+			toc_to_utf16(&mut entry.children, index);
+		}
+	}
+	let index = Utf16Index::new(source);
+	toc_to_utf16(&mut result.toc, &index);
+	for diagnostic in &mut result.diagnostics {
+		if let Some(position) = &mut diagnostic.position {
+			index.position(position);
+		}
+	}
+}
+
+fn ast_to_js(mut ast: Node, source: &str) -> Result<JsValue, JsValue> {
+	let index = Utf16Index::new(source);
+	ast.for_each_position(&mut |position| index.position(position));
+	Ok(ast.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+fn to_compile_options(options: JsValue) -> Result<CompileOptions, JsValue> {
+	if options.is_undefined() || options.is_null() {
+		return Ok(CompileOptions::default());
+	}
+	serde_wasm_bindgen::from_value(options).map_err(|err| error(&err.to_string(), None))
+}
+
+// what `compile` and `Compiler.compile` return, `map` is added as an object
+#[derive(Serialize)]
+struct CompileOutput<'a> {
+	code: &'a str,
+	frontmatter: Option<&'a str>,
+	toc: &'a [TocEntry],
+	diagnostics: &'a [Diagnostic],
+}
+
+fn to_compile_output(result: &CompileResult) -> Result<JsValue, JsValue> {
+	let output = CompileOutput {
+		code: &result.code,
+		frontmatter: result.frontmatter.as_deref(),
+		toc: &result.toc,
+		diagnostics: &result.diagnostics,
+	};
+	let value = output.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+	let map = match &result.source_map {
+		Some(json) => js_sys::JSON::parse(json)?,
+		None => JsValue::NULL,
+	};
+	js_sys::Reflect::set(&value, &JsValue::from_str("map"), &map)?;
+	Ok(value)
+}
+
+// options are read once and the compiler is reused for every document, e.g. on each
+// keystroke of an editor. Compiling doesn't fail on the source, what was written as
+// text instead is in `diagnostics`; invalid options throw
+#[wasm_bindgen]
+pub struct Compiler {
+	compiler: compiler_core::Compiler,
+}
+
 #[wasm_bindgen]
-pub fn parse(source: &str) -> String {
-	compiler_core::parse(source)
+impl Compiler {
+	#[wasm_bindgen(constructor)]
+	pub fn new(options: JsValue) -> Result<Compiler, JsValue> {
+		let options = to_compile_options(options)?;
+		Ok(Compiler {
+			compiler: compiler_core::Compiler::new(options),
+		})
+	}
+
+	// `{ code, map, frontmatter, toc, diagnostics }`, `map` with the `sourceMap` option
+	pub fn compile(&self, source: &str) -> Result<JsValue, JsValue> {
+		let mut result = self.compiler.compile(source);
+		to_utf16(&mut result, source);
+		to_compile_output(&result)
+	}
+
+	#[wasm_bindgen(js_name = compileAst)]
+	pub fn compile_ast(&self, ast: JsValue) -> Result<JsValue, JsValue> {
+		let ast: Node = serde_wasm_bindgen::from_value(ast)?;
+		to_compile_output(&self.compiler.compile_ast(&ast))
+	}
+
+	#[wasm_bindgen(js_name = parseAst)]
+	pub fn parse_ast(&self, source: &str) -> Result<JsValue, JsValue> {
+		ast_to_js(self.compiler.parse_to_ast(source), source)
+	}
+}
+
+#[wasm_bindgen]
+pub fn parse(source: &str, options: JsValue) -> Result<String, JsValue> {
+	let options = to_compile_options(options)?;
+	Ok(compiler_core::compile(source, &options).code)
+}
+
+// the same result as `Compiler.compile`, for a single document
+#[wasm_bindgen]
+pub fn compile(source: &str, options: JsValue) -> Result<JsValue, JsValue> {
+	let options = to_compile_options(options)?;
+	let mut result = compiler_core::compile(source, &options);
+	to_utf16(&mut result, source);
+	to_compile_output(&result)
+}
+
+// an mdast tree from the host, positions are optional
+#[wasm_bindgen(js_name = compileAst)]
+pub fn compile_ast(ast: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+	let options = to_compile_options(options)?;
+	let ast: Node = serde_wasm_bindgen::from_value(ast)?;
+	to_compile_output(&compiler_core::compile_ast(&ast, &options))
+}
+
+// `jsx` is "drop" or "render", `expressions` is "drop" or "error"
+#[wasm_bindgen(js_name = renderHtml)]
+pub fn render_html(
+	source: &str,
+	options: JsValue,
+	jsx: Option<String>,
+	expressions: Option<String>,
+) -> Result<String, JsValue> {
+	let options = to_compile_options(options)?;
+	let mut html_options = HtmlOptions::default();
+	if let Some(jsx) = jsx {
+		html_options.jsx = JsxStrategy::new(&jsx)
+			.ok_or_else(|| error(&format!("unknown jsx strategy `{}`", jsx), None))?;
+	}
+	if let Some(expressions) = expressions {
+		html_options.expressions = ExpressionStrategy::new(&expressions).ok_or_else(|| {
+			error(
+				&format!("unknown expressions strategy `{}`", expressions),
+				None,
+			)
+		})?;
+	}
+	compiler_core::render_html(source, &options, &html_options).map_err(|err| {
+		let mut position = Position {
+			start: point(source, err.span.start),
+			end: point(source, err.span.end),
+		};
+		Utf16Index::new(source).position(&mut position);
+		error(&err.message, Some(&position))
+	})
+}
+
+// mdast tree, with `null` for missing values and objects for maps like the JSON form
+#[wasm_bindgen(js_name = parseToAst)]
+pub fn parse_to_ast(source: &str, options: JsValue) -> Result<JsValue, JsValue> {
+	let options = to_compile_options(options)?;
+	ast_to_js(compiler_core::parse_to_ast(source, &options), source)
+}
+
+#[test]
+fn test_to_utf16() {
+	let source = "😀 <Foo bar={>\n\n# é\n";
+	let options = CompileOptions::builder().toc_export(true).build();
+	let mut result = compiler_core::compile(source, &options);
+	to_utf16(&mut result, source);
+	let position = result.diagnostics[0].position.as_ref().unwrap();
+	assert_eq!(
+		(
+			position.start.offset,
+			position.start.column,
+			position.end.offset
+		),
+		(3, 4, 4)
+	);
+	let entry = &result.toc[0];
+	assert_eq!((entry.span.start, entry.span.end), (16, 20));
+	assert_eq!(point(source, 5).column, 6);
 }